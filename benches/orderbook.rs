@@ -0,0 +1,189 @@
+//! Orderbook update and read costs
+//!
+//! `resort` is the previous storage (DashMap per side, full sort of both sides
+//! whenever the top may have moved), kept here as the baseline for `sorted`.
+//!
+//! Run with `cargo bench --bench orderbook`.
+
+use bybit_orderflow_bot::Orderbook;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use dashmap::DashMap;
+use ordered_float::OrderedFloat;
+use parking_lot::RwLock;
+
+const DEPTHS: [usize; 2] = [200, 500];
+const TICK: f64 = 0.1;
+const MID: f64 = 50_000.0;
+
+type Delta = (Vec<(f64, f64)>, Vec<(f64, f64)>);
+
+fn side(depth: usize, dir: f64) -> Vec<(f64, f64)> {
+    (0..depth)
+        .map(|i| (MID + dir * (i as f64 + 1.0) * TICK, 1.0 + (i % 7) as f64))
+        .collect()
+}
+
+/// Delta that moves the top: alternately quotes inside the spread and pulls that quote
+fn top_delta(round: usize) -> Delta {
+    let qty = if round.is_multiple_of(2) { 1.0 } else { 0.0 };
+    (vec![(MID - TICK / 2.0, qty)], vec![(MID + TICK / 2.0, qty)])
+}
+
+/// Delta far from the top on both sides
+fn deep_delta(depth: usize, round: usize) -> Delta {
+    let level = (depth / 2) as f64 * TICK;
+    let qty = 1.0 + (round % 5) as f64;
+    (vec![(MID - level, qty)], vec![(MID + level, qty)])
+}
+
+/// The storage `Orderbook` used before the ordered book
+struct ResortBook {
+    bids: DashMap<OrderedFloat<f64>, OrderedFloat<f64>>,
+    asks: DashMap<OrderedFloat<f64>, OrderedFloat<f64>>,
+    sorted_bids: RwLock<Vec<(f64, f64)>>,
+    sorted_asks: RwLock<Vec<(f64, f64)>>,
+    best_bid: f64,
+    best_ask: f64,
+}
+
+impl ResortBook {
+    fn new() -> Self {
+        Self {
+            bids: DashMap::new(),
+            asks: DashMap::new(),
+            sorted_bids: RwLock::new(Vec::new()),
+            sorted_asks: RwLock::new(Vec::new()),
+            best_bid: 0.0,
+            best_ask: f64::MAX,
+        }
+    }
+
+    fn apply_snapshot(&mut self, bids: Vec<(f64, f64)>, asks: Vec<(f64, f64)>) {
+        self.bids.clear();
+        self.asks.clear();
+        for (price, qty) in bids {
+            self.bids.insert(OrderedFloat(price), OrderedFloat(qty));
+        }
+        for (price, qty) in asks {
+            self.asks.insert(OrderedFloat(price), OrderedFloat(qty));
+        }
+        self.resort();
+    }
+
+    fn apply_delta(&mut self, bids: Vec<(f64, f64)>, asks: Vec<(f64, f64)>) {
+        let mut top_changed = false;
+        for (price, qty) in bids {
+            if qty == 0.0 {
+                self.bids.remove(&OrderedFloat(price));
+                top_changed = true;
+            } else {
+                self.bids.insert(OrderedFloat(price), OrderedFloat(qty));
+                top_changed |= price > self.best_bid;
+            }
+        }
+        for (price, qty) in asks {
+            if qty == 0.0 {
+                self.asks.remove(&OrderedFloat(price));
+                top_changed = true;
+            } else {
+                self.asks.insert(OrderedFloat(price), OrderedFloat(qty));
+                top_changed |= price < self.best_ask;
+            }
+        }
+        if top_changed {
+            self.resort();
+        }
+    }
+
+    fn resort(&mut self) {
+        let mut bids: Vec<_> = self.bids.iter().map(|e| (e.key().0, e.value().0)).collect();
+        bids.sort_by(|a, b| b.0.total_cmp(&a.0));
+        let mut asks: Vec<_> = self.asks.iter().map(|e| (e.key().0, e.value().0)).collect();
+        asks.sort_by(|a, b| a.0.total_cmp(&b.0));
+        self.best_bid = bids.first().map_or(0.0, |l| l.0);
+        self.best_ask = asks.first().map_or(f64::MAX, |l| l.0);
+        *self.sorted_bids.write() = bids;
+        *self.sorted_asks.write() = asks;
+    }
+
+    fn liquidity_depth(&self, depth: usize) -> f64 {
+        let bids: f64 = self.sorted_bids.read().iter().take(depth).map(|l| l.1).sum();
+        let asks: f64 = self.sorted_asks.read().iter().take(depth).map(|l| l.1).sum();
+        bids + asks
+    }
+}
+
+fn bench_snapshot(c: &mut Criterion) {
+    let mut group = c.benchmark_group("snapshot");
+    for depth in DEPTHS {
+        let (bids, asks) = (side(depth, -1.0), side(depth, 1.0));
+
+        group.bench_with_input(BenchmarkId::new("sorted", depth), &depth, |b, _| {
+            let ob = Orderbook::new("BTCUSDT".to_string());
+            b.iter(|| ob.apply_snapshot(bids.clone(), asks.clone()));
+        });
+        group.bench_with_input(BenchmarkId::new("resort", depth), &depth, |b, _| {
+            let mut ob = ResortBook::new();
+            b.iter(|| ob.apply_snapshot(bids.clone(), asks.clone()));
+        });
+    }
+    group.finish();
+}
+
+fn bench_delta(c: &mut Criterion) {
+    for (name, at_top) in [("delta_top", true), ("delta_deep", false)] {
+        let mut group = c.benchmark_group(name);
+        for depth in DEPTHS {
+            let delta = |round| if at_top { top_delta(round) } else { deep_delta(depth, round) };
+
+            group.bench_with_input(BenchmarkId::new("sorted", depth), &depth, |b, _| {
+                let ob = Orderbook::new("BTCUSDT".to_string());
+                ob.apply_snapshot(side(depth, -1.0), side(depth, 1.0));
+                let mut round = 0;
+                b.iter(|| {
+                    round += 1;
+                    let (bids, asks) = delta(round);
+                    ob.apply_delta(bids, asks);
+                });
+            });
+            group.bench_with_input(BenchmarkId::new("resort", depth), &depth, |b, _| {
+                let mut ob = ResortBook::new();
+                ob.apply_snapshot(side(depth, -1.0), side(depth, 1.0));
+                let mut round = 0;
+                b.iter(|| {
+                    round += 1;
+                    let (bids, asks) = delta(round);
+                    ob.apply_delta(bids, asks);
+                });
+            });
+        }
+        group.finish();
+    }
+}
+
+fn bench_reads(c: &mut Criterion) {
+    let mut group = c.benchmark_group("read_top_50");
+    let depth = 500;
+
+    let ob = Orderbook::new("BTCUSDT".to_string());
+    ob.apply_snapshot(side(depth, -1.0), side(depth, 1.0));
+    let mut resort = ResortBook::new();
+    resort.apply_snapshot(side(depth, -1.0), side(depth, 1.0));
+
+    group.bench_function("sorted/liquidity_depth", |b| b.iter(|| black_box(ob.liquidity_depth(50))));
+    group.bench_function("resort/liquidity_depth", |b| b.iter(|| black_box(resort.liquidity_depth(50))));
+    group.bench_function("sorted/imbalance", |b| b.iter(|| black_box(ob.imbalance(50))));
+
+    let (mut bids, mut asks) = (Vec::new(), Vec::new());
+    group.bench_function("sorted/copy_sorted_levels", |b| {
+        b.iter(|| {
+            ob.copy_sorted_levels(50, &mut bids, &mut asks);
+            black_box((bids.len(), asks.len()))
+        })
+    });
+    group.bench_function("sorted/get_sorted_levels", |b| b.iter(|| black_box(ob.get_sorted_levels(50))));
+    group.finish();
+}
+
+criterion_group!(benches, bench_snapshot, bench_delta, bench_reads);
+criterion_main!(benches);
@@ -4,6 +4,7 @@ use std::time::{SystemTime, UNIX_EPOCH};
 
 type HmacSha256 = Hmac<Sha256>;
 
+#[derive(Clone)]
 pub struct BybitAuth {
     api_key: String,
     api_secret: String,
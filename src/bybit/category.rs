@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+
+/// Bybit v5 product category
+/// Selects the REST `category` parameter, the public stream and how order quantities are expressed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Category {
+    /// USDT/USDC perpetuals and futures; quantity in base coin
+    #[default]
+    Linear,
+    /// Coin-margined contracts; quantity in USD contracts (1 contract = 1 USD)
+    Inverse,
+    /// Spot; quantity in base coin, no positions or leverage
+    Spot,
+}
+
+impl Category {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Category::Linear => "linear",
+            Category::Inverse => "inverse",
+            Category::Spot => "spot",
+        }
+    }
+
+    /// Public stream for this category, keeping the host of a configured `.../v5/public/{category}` URL
+    pub fn public_ws_url(&self, configured: &str) -> String {
+        match configured.rfind("/v5/public/") {
+            Some(i) => format!("{}/v5/public/{}", &configured[..i], self.as_str()),
+            None => configured.to_string(),
+        }
+    }
+
+    /// `orderbook.{depth}` streams Bybit publishes for this category
+    pub fn orderbook_depths(&self) -> &'static [usize] {
+        match self {
+            Category::Linear | Category::Inverse => &[1, 50, 200, 500],
+            Category::Spot => &[1, 50, 200],
+        }
+    }
+
+    /// `allLiquidation` exists for derivatives only
+    pub fn has_liquidations(&self) -> bool {
+        *self != Category::Spot
+    }
+
+    /// Positions, leverage, reduce-only and close-on-trigger exist for derivatives only
+    pub fn has_positions(&self) -> bool {
+        *self != Category::Spot
+    }
+
+    /// Order quantity for `base_qty` of the base coin at `price`
+    pub fn order_qty(&self, base_qty: f64, price: f64) -> f64 {
+        match self {
+            Category::Inverse => (base_qty * price).round(),
+            Category::Linear | Category::Spot => base_qty,
+        }
+    }
+
+    /// Base-coin amount of an order, trade or book size quoted in this category's units
+    pub fn base_qty(&self, qty: f64, price: f64) -> f64 {
+        match self {
+            Category::Inverse if price > 0.0 => qty / price,
+            Category::Inverse => 0.0,
+            Category::Linear | Category::Spot => qty,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_category_endpoints_and_quantities() {
+        assert_eq!(
+            Category::Spot.public_ws_url("wss://stream.bybit.com/v5/public/linear"),
+            "wss://stream.bybit.com/v5/public/spot"
+        );
+        assert_eq!(Category::Inverse.public_ws_url("ws://127.0.0.1:1/custom"), "ws://127.0.0.1:1/custom");
+
+        // 0.02 BTC at $50k is 1000 USD contracts
+        assert_eq!(Category::Inverse.order_qty(0.02, 50_000.0), 1000.0);
+        assert_eq!(Category::Inverse.base_qty(1000.0, 50_000.0), 0.02);
+        assert_eq!(Category::Linear.order_qty(0.02, 50_000.0), 0.02);
+
+        assert!(!Category::Spot.orderbook_depths().contains(&500));
+
+        let parsed: Category = serde_json::from_str("\"inverse\"").unwrap();
+        assert_eq!(parsed, Category::Inverse);
+    }
+}
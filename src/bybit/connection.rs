@@ -0,0 +1,121 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+/// Lifecycle of the public market-data connection
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConnectionState {
+    /// Dialing; `attempt` counts consecutive tries since the last healthy session
+    Connecting { attempt: u32 },
+    /// Subscribed and receiving data
+    Live,
+    /// Still connected but data has gone quiet; book state may be stale
+    Degraded { reason: String },
+    /// Disconnected, next attempt after `retry_in`
+    Down { reason: String, retry_in: Duration },
+}
+
+impl ConnectionState {
+    pub fn is_live(&self) -> bool {
+        matches!(self, ConnectionState::Live)
+    }
+}
+
+/// Exponential backoff between reconnect attempts
+#[derive(Debug, Clone)]
+pub struct ReconnectPolicy {
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+    pub multiplier: f64,
+    /// Fraction of the delay that is randomised (0.0 = none, 1.0 = full jitter)
+    pub jitter: f64,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            multiplier: 2.0,
+            jitter: 0.5,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// Delay before the next attempt after `failures` consecutive failed sessions
+    pub fn delay(&self, failures: u32) -> Duration {
+        let base = self.initial_delay.as_secs_f64() * self.multiplier.max(1.0).powi(failures as i32);
+        let capped = base.min(self.max_delay.as_secs_f64());
+        let jitter = self.jitter.clamp(0.0, 1.0);
+
+        Duration::from_secs_f64(capped * (1.0 - jitter * random_unit()))
+    }
+}
+
+/// Keep-alive and stale-data detection for a live connection
+#[derive(Debug, Clone)]
+pub struct HeartbeatPolicy {
+    /// How often `{"op":"ping"}` is sent
+    pub ping_interval: Duration,
+    /// Reconnect if a ping is not answered within this time
+    pub pong_timeout: Duration,
+    /// Report `Degraded` after this long without data
+    pub degraded_after: Duration,
+    /// Reconnect after this long without data
+    pub stale_after: Duration,
+    /// Reconnect if a resynced topic gets no snapshot within this time
+    pub resync_timeout: Duration,
+}
+
+impl Default for HeartbeatPolicy {
+    fn default() -> Self {
+        Self {
+            ping_interval: Duration::from_secs(20),
+            pong_timeout: Duration::from_secs(10),
+            degraded_after: Duration::from_secs(10),
+            stale_after: Duration::from_secs(30),
+            resync_timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Uniform value in [0, 1) from the std randomly-keyed hasher
+fn random_unit() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(0);
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_grows_and_caps() {
+        let policy = ReconnectPolicy {
+            initial_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(1),
+            multiplier: 2.0,
+            jitter: 0.0,
+        };
+
+        assert_eq!(policy.delay(0), Duration::from_millis(100));
+        assert_eq!(policy.delay(2), Duration::from_millis(400));
+        assert_eq!(policy.delay(10), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_jitter_stays_within_bounds() {
+        let policy = ReconnectPolicy {
+            initial_delay: Duration::from_secs(1),
+            jitter: 0.5,
+            ..Default::default()
+        };
+
+        for _ in 0..100 {
+            let delay = policy.delay(0);
+            assert!(delay > Duration::from_millis(500) && delay <= Duration::from_secs(1));
+        }
+    }
+}
@@ -0,0 +1,259 @@
+use parking_lot::Mutex;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::Notify;
+use tracing::error;
+
+use super::event::BybitEvent;
+use super::websocket::MessageHandler;
+
+/// What to do with a frame when a topic queue is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Discard the incoming frame
+    Drop,
+    /// Discard the oldest queued frame so the queue holds the freshest data
+    Coalesce,
+    /// Wait for the worker to make room (backpressures the socket reader)
+    Block,
+}
+
+impl OverflowPolicy {
+    /// Values accepted by `from_config`
+    pub const CONFIG_VALUES: &'static [&'static str] = &["block", "drop", "coalesce"];
+
+    pub fn from_config(value: &str) -> Self {
+        match value {
+            "drop" => OverflowPolicy::Drop,
+            "coalesce" => OverflowPolicy::Coalesce,
+            _ => OverflowPolicy::Block,
+        }
+    }
+}
+
+/// Point-in-time counters for one topic queue
+#[derive(Debug, Clone)]
+pub struct DispatchStats {
+    pub topic: String,
+    pub depth: usize,
+    pub capacity: usize,
+    pub policy: OverflowPolicy,
+    pub dispatched: u64,
+    pub dropped: u64,
+    pub coalesced: u64,
+}
+
+/// Bounded FIFO drained by a single worker, so frames of a topic are handled in arrival order
+pub struct TopicQueue {
+    topic: String,
+    handler: MessageHandler,
+    capacity: usize,
+    policy: OverflowPolicy,
+    items: Mutex<VecDeque<BybitEvent>>,
+    data_ready: Notify,
+    space_ready: Notify,
+    worker_started: AtomicBool,
+    stopped: AtomicBool,
+    /// Set (under the items lock) while the handler runs a popped frame
+    busy: AtomicBool,
+    dispatched: AtomicU64,
+    dropped: AtomicU64,
+    coalesced: AtomicU64,
+}
+
+impl TopicQueue {
+    pub fn new(topic: String, handler: MessageHandler, capacity: usize, policy: OverflowPolicy) -> Self {
+        let capacity = capacity.max(1);
+
+        Self {
+            topic,
+            handler,
+            capacity,
+            policy,
+            items: Mutex::new(VecDeque::with_capacity(capacity)),
+            data_ready: Notify::new(),
+            space_ready: Notify::new(),
+            worker_started: AtomicBool::new(false),
+            stopped: AtomicBool::new(false),
+            busy: AtomicBool::new(false),
+            dispatched: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+            coalesced: AtomicU64::new(0),
+        }
+    }
+
+    pub fn topic(&self) -> &str {
+        &self.topic
+    }
+
+    /// Enqueue a frame, applying the overflow policy when full
+    pub async fn push(&self, msg: BybitEvent) {
+        let mut msg = Some(msg);
+
+        loop {
+            if self.stopped.load(Ordering::Acquire) {
+                return;
+            }
+
+            {
+                let mut items = self.items.lock();
+                if items.len() < self.capacity {
+                    items.extend(msg.take());
+                    self.data_ready.notify_one();
+                    return;
+                }
+
+                match self.policy {
+                    OverflowPolicy::Drop => {
+                        self.dropped.fetch_add(1, Ordering::Relaxed);
+                        return;
+                    }
+                    OverflowPolicy::Coalesce => {
+                        items.pop_front();
+                        items.extend(msg.take());
+                        self.coalesced.fetch_add(1, Ordering::Relaxed);
+                        self.data_ready.notify_one();
+                        return;
+                    }
+                    OverflowPolicy::Block => {}
+                }
+            }
+
+            self.space_ready.notified().await;
+        }
+    }
+
+    /// Spawn the worker task once; requires a running Tokio runtime
+    pub fn start(self: &Arc<Self>) {
+        if self.worker_started.swap(true, Ordering::AcqRel) {
+            return;
+        }
+
+        let queue = self.clone();
+        tokio::spawn(async move { queue.work().await });
+    }
+
+    /// Discard queued frames and let the worker exit (topic was unsubscribed)
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::Release);
+        self.items.lock().clear();
+        self.data_ready.notify_one();
+        self.space_ready.notify_waiters();
+    }
+
+    async fn work(&self) {
+        loop {
+            let next = {
+                let mut items = self.items.lock();
+                let next = items.pop_front();
+                self.busy.store(next.is_some(), Ordering::Release);
+                next
+            };
+
+            match next {
+                Some(msg) => {
+                    self.space_ready.notify_one();
+                    self.dispatched.fetch_add(1, Ordering::Relaxed);
+                    if let Err(e) = (self.handler)(msg) {
+                        error!("Handler error on {}: {}", self.topic, e);
+                    }
+                    self.busy.store(false, Ordering::Release);
+                }
+                None if self.stopped.load(Ordering::Acquire) => return,
+                None => self.data_ready.notified().await,
+            }
+        }
+    }
+
+    /// Nothing queued and no frame being handled
+    pub fn is_idle(&self) -> bool {
+        let items = self.items.lock();
+        items.is_empty() && !self.busy.load(Ordering::Acquire)
+    }
+
+    pub fn stats(&self) -> DispatchStats {
+        DispatchStats {
+            topic: self.topic.clone(),
+            depth: self.items.lock().len(),
+            capacity: self.capacity,
+            policy: self.policy,
+            dispatched: self.dispatched.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+            coalesced: self.coalesced.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bybit::event::Update;
+    use std::sync::atomic::AtomicUsize;
+
+    fn message(seq: u64) -> BybitEvent {
+        BybitEvent::Other(Update {
+            topic: "test".to_string(),
+            snapshot: false,
+            ts: Some(seq),
+            cts: None,
+            data: serde_json::json!(seq),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_frames_are_handled_in_order() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let seen_clone = seen.clone();
+        let handler: MessageHandler = Arc::new(move |msg: BybitEvent| {
+            seen_clone.lock().push(msg.ts().unwrap());
+            Ok(())
+        });
+
+        let queue = Arc::new(TopicQueue::new("test".to_string(), handler, 4, OverflowPolicy::Block));
+        queue.start();
+
+        for seq in 0..100 {
+            queue.push(message(seq)).await;
+        }
+
+        while queue.stats().dispatched < 100 {
+            tokio::task::yield_now().await;
+        }
+
+        assert_eq!(*seen.lock(), (0..100).collect::<Vec<_>>());
+        assert_eq!(queue.stats().dropped, 0);
+    }
+
+    #[tokio::test]
+    async fn test_overflow_policies_without_worker() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let calls_clone = calls.clone();
+        let handler: MessageHandler = Arc::new(move |_| {
+            calls_clone.fetch_add(1, Ordering::Relaxed);
+            Ok(())
+        });
+
+        // No worker started, so the queue fills up
+        let drop_queue = TopicQueue::new("d".to_string(), handler.clone(), 2, OverflowPolicy::Drop);
+        for seq in 0..5 {
+            drop_queue.push(message(seq)).await;
+        }
+        let stats = drop_queue.stats();
+        assert_eq!((stats.depth, stats.dropped), (2, 3));
+        assert_eq!(drop_queue.items.lock().front().unwrap().ts(), Some(0));
+
+        let coalesce_queue = TopicQueue::new("c".to_string(), handler, 2, OverflowPolicy::Coalesce);
+        for seq in 0..5 {
+            coalesce_queue.push(message(seq)).await;
+        }
+        let stats = coalesce_queue.stats();
+        assert_eq!((stats.depth, stats.coalesced), (2, 3));
+        assert_eq!(coalesce_queue.items.lock().front().unwrap().ts(), Some(3));
+
+        assert_eq!(calls.load(Ordering::Relaxed), 0);
+
+        let parsed: Vec<_> = OverflowPolicy::CONFIG_VALUES.iter().map(|value| OverflowPolicy::from_config(value)).collect();
+        assert_eq!(parsed, vec![OverflowPolicy::Block, OverflowPolicy::Drop, OverflowPolicy::Coalesce]);
+    }
+}
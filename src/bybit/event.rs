@@ -0,0 +1,318 @@
+use dashmap::DashMap;
+use serde::de::{self, DeserializeSeed, Deserializer, IgnoredAny, MapAccess, Visitor};
+use serde::Deserialize;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use super::types::{AllLiquidationData, KlineData, OrderbookData, TickerData, TradeData};
+
+/// Public-stream frame, decoded once on the socket task and moved into its topic handler
+#[derive(Debug, Clone)]
+pub enum BybitEvent {
+    Orderbook(Update<OrderbookData>),
+    Trades(Update<Vec<TradeData>>),
+    /// Boxed: the ticker is several times larger than the other payloads
+    Ticker(Update<Box<TickerData>>),
+    Liquidations(Update<Vec<AllLiquidationData>>),
+    Klines(Update<Vec<KlineData>>),
+    /// Topic without a typed decoder; `data` is kept as JSON
+    Other(Update<serde_json::Value>),
+    /// Reply to an `op` request (subscribe, unsubscribe, ping)
+    Op(OpResponse),
+}
+
+/// Envelope of a topic frame
+#[derive(Debug, Clone)]
+pub struct Update<T> {
+    pub topic: String,
+    /// `type: "snapshot"`; everything else is a delta
+    pub snapshot: bool,
+    pub ts: Option<u64>,
+    pub cts: Option<u64>,
+    pub data: T,
+}
+
+impl Update<()> {
+    fn with<T>(self, data: T) -> Update<T> {
+        Update { topic: self.topic, snapshot: self.snapshot, ts: self.ts, cts: self.cts, data }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct OpResponse {
+    pub op: String,
+    pub success: Option<bool>,
+    pub ret_msg: Option<String>,
+    pub req_id: Option<String>,
+}
+
+impl BybitEvent {
+    /// Decode a frame in place with simd-json; `frame` is scratch space afterwards
+    pub fn decode(frame: &mut [u8]) -> simd_json::Result<Self> {
+        simd_json::serde::from_slice(frame)
+    }
+
+    pub fn topic(&self) -> Option<&str> {
+        match self {
+            BybitEvent::Orderbook(u) => Some(&u.topic),
+            BybitEvent::Trades(u) => Some(&u.topic),
+            BybitEvent::Ticker(u) => Some(&u.topic),
+            BybitEvent::Liquidations(u) => Some(&u.topic),
+            BybitEvent::Klines(u) => Some(&u.topic),
+            BybitEvent::Other(u) => Some(&u.topic),
+            BybitEvent::Op(_) => None,
+        }
+    }
+
+    /// Whether this is a full snapshot of its topic
+    pub fn is_snapshot(&self) -> bool {
+        match self {
+            BybitEvent::Orderbook(u) => u.snapshot,
+            BybitEvent::Trades(u) => u.snapshot,
+            BybitEvent::Ticker(u) => u.snapshot,
+            BybitEvent::Liquidations(u) => u.snapshot,
+            BybitEvent::Klines(u) => u.snapshot,
+            BybitEvent::Other(u) => u.snapshot,
+            BybitEvent::Op(_) => false,
+        }
+    }
+
+    /// Exchange timestamp (ms) of a topic frame
+    pub fn ts(&self) -> Option<u64> {
+        match self {
+            BybitEvent::Orderbook(u) => u.ts,
+            BybitEvent::Trades(u) => u.ts,
+            BybitEvent::Ticker(u) => u.ts,
+            BybitEvent::Liquidations(u) => u.ts,
+            BybitEvent::Klines(u) => u.ts,
+            BybitEvent::Other(u) => u.ts,
+            BybitEvent::Op(_) => None,
+        }
+    }
+}
+
+/// Decoded `data`, typed by the topic prefix
+enum Payload {
+    Orderbook(OrderbookData),
+    Trades(Vec<TradeData>),
+    Ticker(Box<TickerData>),
+    Liquidations(Vec<AllLiquidationData>),
+    Klines(Vec<KlineData>),
+    Other(serde_json::Value),
+}
+
+/// Picks the `data` type from the topic seen earlier in the same frame
+struct PayloadSeed<'a>(&'a str);
+
+impl<'de> DeserializeSeed<'de> for PayloadSeed<'_> {
+    type Value = Payload;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Payload, D::Error> {
+        let topic = self.0;
+
+        Ok(if topic.starts_with("orderbook.") {
+            Payload::Orderbook(OrderbookData::deserialize(deserializer)?)
+        } else if topic.starts_with("publicTrade.") {
+            Payload::Trades(Vec::deserialize(deserializer)?)
+        } else if topic.starts_with("tickers.") {
+            Payload::Ticker(Box::new(TickerData::deserialize(deserializer)?))
+        } else if topic.starts_with("allLiquidation.") {
+            Payload::Liquidations(Vec::deserialize(deserializer)?)
+        } else if topic.starts_with("kline.") {
+            Payload::Klines(Vec::deserialize(deserializer)?)
+        } else {
+            Payload::Other(serde_json::Value::deserialize(deserializer)?)
+        })
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(field_identifier, rename_all = "snake_case")]
+enum Field {
+    Topic,
+    Type,
+    Ts,
+    Cts,
+    Data,
+    Op,
+    Success,
+    RetMsg,
+    ReqId,
+    #[serde(other)]
+    Ignored,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum MsgType {
+    Snapshot,
+    #[serde(other)]
+    Delta,
+}
+
+struct EventVisitor;
+
+impl<'de> Visitor<'de> for EventVisitor {
+    type Value = BybitEvent;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a Bybit WebSocket frame")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<BybitEvent, A::Error> {
+        let mut topic: Option<String> = None;
+        let mut snapshot = false;
+        let mut ts = None;
+        let mut cts = None;
+        let mut payload = None;
+        let mut op = OpResponse::default();
+
+        while let Some(field) = map.next_key()? {
+            match field {
+                Field::Topic => topic = Some(map.next_value()?),
+                Field::Type => snapshot = matches!(map.next_value()?, MsgType::Snapshot),
+                Field::Ts => ts = map.next_value()?,
+                Field::Cts => cts = map.next_value()?,
+                // Bybit sends `topic` first, so `data` decodes straight into its type
+                Field::Data => payload = Some(match topic.as_deref() {
+                    Some(topic) => map.next_value_seed(PayloadSeed(topic))?,
+                    None => Payload::Other(map.next_value()?),
+                }),
+                Field::Op => op.op = map.next_value()?,
+                Field::Success => op.success = map.next_value()?,
+                Field::RetMsg => op.ret_msg = map.next_value()?,
+                Field::ReqId => op.req_id = map.next_value()?,
+                Field::Ignored => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+
+        let (topic, payload) = match (topic, payload) {
+            (Some(topic), Some(Payload::Other(data))) => {
+                // `data` arrived before `topic`: retype it now
+                let payload = PayloadSeed(&topic).deserialize(data).map_err(de::Error::custom)?;
+                (topic, payload)
+            }
+            (Some(topic), Some(payload)) => (topic, payload),
+            (None, _) if !op.op.is_empty() => return Ok(BybitEvent::Op(op)),
+            _ => return Err(de::Error::custom("frame has neither topic data nor op")),
+        };
+
+        let update = Update { topic, snapshot, ts, cts, data: () };
+        Ok(match payload {
+            Payload::Orderbook(data) => BybitEvent::Orderbook(update.with(data)),
+            Payload::Trades(data) => BybitEvent::Trades(update.with(data)),
+            Payload::Ticker(data) => BybitEvent::Ticker(update.with(data)),
+            Payload::Liquidations(data) => BybitEvent::Liquidations(update.with(data)),
+            Payload::Klines(data) => BybitEvent::Klines(update.with(data)),
+            Payload::Other(data) => BybitEvent::Other(update.with(data)),
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for BybitEvent {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(EventVisitor)
+    }
+}
+
+/// Decode cost for one topic
+#[derive(Debug, Clone)]
+pub struct DecodeStats {
+    pub topic: String,
+    pub frames: u64,
+    pub avg_ns: u64,
+    pub max_ns: u64,
+}
+
+#[derive(Default)]
+struct DecodeCounters {
+    frames: AtomicU64,
+    total_ns: AtomicU64,
+    max_ns: AtomicU64,
+}
+
+/// Per-topic decode timings; control frames are counted under "op"
+#[derive(Default)]
+pub struct DecodeMeter {
+    topics: DashMap<String, DecodeCounters>,
+}
+
+impl DecodeMeter {
+    pub fn record(&self, topic: Option<&str>, elapsed: Duration) {
+        let topic = topic.unwrap_or("op");
+        let ns = elapsed.as_nanos() as u64;
+
+        let record = |counters: &DecodeCounters| {
+            counters.frames.fetch_add(1, Ordering::Relaxed);
+            counters.total_ns.fetch_add(ns, Ordering::Relaxed);
+            counters.max_ns.fetch_max(ns, Ordering::Relaxed);
+        };
+
+        // Avoid allocating the key on the hot path once the topic is known
+        match self.topics.get(topic) {
+            Some(counters) => record(&counters),
+            None => record(&self.topics.entry(topic.to_string()).or_default()),
+        }
+    }
+
+    pub fn stats(&self) -> Vec<DecodeStats> {
+        let mut stats: Vec<DecodeStats> = self.topics.iter()
+            .map(|entry| {
+                let frames = entry.frames.load(Ordering::Relaxed);
+                DecodeStats {
+                    topic: entry.key().clone(),
+                    frames,
+                    avg_ns: entry.total_ns.load(Ordering::Relaxed) / frames.max(1),
+                    max_ns: entry.max_ns.load(Ordering::Relaxed),
+                }
+            })
+            .collect();
+        stats.sort_by(|a, b| a.topic.cmp(&b.topic));
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(frame: &str) -> BybitEvent {
+        BybitEvent::decode(&mut frame.as_bytes().to_vec()).unwrap()
+    }
+
+    #[test]
+    fn test_decodes_orderbook_levels_as_numbers() {
+        let event = decode(r#"{"topic":"orderbook.50.BTCUSDT","type":"snapshot","ts":1700000000000,"data":{"s":"BTCUSDT","b":[["50000.5","1.25"]],"a":[["50001","0.5"],["50002","bad"]],"u":7,"seq":42},"cts":1699999999999}"#);
+
+        let BybitEvent::Orderbook(update) = event else {
+            panic!("expected orderbook event");
+        };
+        assert!(update.snapshot);
+        assert_eq!(update.ts, Some(1_700_000_000_000));
+        assert_eq!(update.cts, Some(1_699_999_999_999));
+        assert_eq!(update.data.bids, vec![(50000.5, 1.25)]);
+        // Unparseable levels are skipped
+        assert_eq!(update.data.asks, vec![(50001.0, 0.5)]);
+        assert_eq!((update.data.update_id, update.data.seq), (7, 42));
+    }
+
+    #[test]
+    fn test_decodes_by_topic_regardless_of_field_order() {
+        let event = decode(r#"{"data":[{"T":1,"s":"BTCUSDT","S":"Buy","v":"0.1","p":"50000","i":"t1"}],"type":"snapshot","topic":"publicTrade.BTCUSDT","ts":2}"#);
+        assert!(matches!(event, BybitEvent::Trades(ref u) if u.data.len() == 1 && u.data[0].trade_id == "t1"));
+
+        let event = decode(r#"{"topic":"funding.BTCUSDT","ts":3,"data":{"rate":"0.0001"}}"#);
+        assert!(matches!(event, BybitEvent::Other(ref u) if u.data["rate"] == "0.0001" && !u.snapshot));
+
+        let event = decode(r#"{"success":false,"ret_msg":"error:handler not found","conn_id":"c1","req_id":"9","op":"subscribe"}"#);
+        let BybitEvent::Op(op) = event else {
+            panic!("expected op response");
+        };
+        assert_eq!((op.op.as_str(), op.success, op.req_id.as_deref()), ("subscribe", Some(false), Some("9")));
+
+        assert!(BybitEvent::decode(&mut br#"{"conn_id":"c1"}"#.to_vec()).is_err());
+    }
+}
@@ -1,7 +1,17 @@
 pub mod types;
+pub mod event;
 pub mod auth;
 pub mod websocket;
+pub mod private;
+pub mod dispatch;
+pub mod connection;
+pub mod category;
 
 pub use types::*;
+pub use event::{BybitEvent, DecodeStats, OpResponse, Update};
 pub use auth::BybitAuth;
-pub use websocket::BybitWebSocket;
+pub use websocket::{BybitWebSocket, InvalidateHandler, MessageHandler, ResyncHandle, SubscriptionError, SubscriptionHandle};
+pub use private::{BybitPrivateStream, PrivateEvent};
+pub use dispatch::{DispatchStats, OverflowPolicy};
+pub use connection::{ConnectionState, HeartbeatPolicy, ReconnectPolicy};
+pub use category::Category;
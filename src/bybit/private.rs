@@ -0,0 +1,300 @@
+use tokio::sync::broadcast;
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::Message;
+use futures::{SinkExt, StreamExt};
+use serde::Deserialize;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{info, warn, error, debug};
+use anyhow::{Context, Result};
+
+use super::auth::BybitAuth;
+use super::connection::{HeartbeatPolicy, ReconnectPolicy};
+use super::types::{ExecutionUpdate, OrderUpdate, PositionUpdate, WalletUpdate};
+use super::websocket::WsStream;
+
+/// Account event pushed on the private stream
+#[derive(Debug, Clone)]
+pub enum PrivateEvent {
+    Order(Vec<OrderUpdate>),
+    Execution(Vec<ExecutionUpdate>),
+    Position(Vec<PositionUpdate>),
+    Wallet(Vec<WalletUpdate>),
+}
+
+/// Envelope shared by private data and control frames
+#[derive(Debug, Deserialize)]
+struct PrivateFrame {
+    topic: Option<String>,
+    op: Option<String>,
+    success: Option<bool>,
+    ret_msg: Option<String>,
+    data: Option<serde_json::Value>,
+}
+
+/// Authenticated client for `/v5/private`
+/// Decoded events are published on a broadcast channel
+pub struct BybitPrivateStream {
+    url: String,
+    auth: BybitAuth,
+    events: broadcast::Sender<PrivateEvent>,
+    reconnect: ReconnectPolicy,
+    heartbeat: HeartbeatPolicy,
+}
+
+impl BybitPrivateStream {
+    const TOPICS: [&'static str; 4] = ["order", "execution", "position", "wallet"];
+
+    pub fn new(url: String, auth: BybitAuth) -> Self {
+        let (events, _) = broadcast::channel(1024);
+
+        Self {
+            url,
+            auth,
+            events,
+            reconnect: ReconnectPolicy::default(),
+            heartbeat: HeartbeatPolicy::default(),
+        }
+    }
+
+    /// Set the backoff between reconnect attempts
+    pub fn with_reconnect_policy(mut self, reconnect: ReconnectPolicy) -> Self {
+        self.reconnect = reconnect;
+        self
+    }
+
+    /// Set the ping cadence and the silence that forces a reconnect
+    pub fn with_heartbeat(mut self, heartbeat: HeartbeatPolicy) -> Self {
+        self.heartbeat = heartbeat;
+        self
+    }
+
+    /// Subscribe to decoded account events
+    pub fn events(&self) -> broadcast::Receiver<PrivateEvent> {
+        self.events.subscribe()
+    }
+
+    pub async fn run(self: Arc<Self>) -> Result<()> {
+        // Consecutive sessions that failed before authenticating
+        let mut failures: u32 = 0;
+
+        loop {
+            info!("Connecting to Bybit private WebSocket: {}", self.url);
+
+            match connect_async(&self.url).await {
+                Ok((ws_stream, _)) => {
+                    if let Err(e) = self.handle_stream(ws_stream, &mut failures).await {
+                        error!("Private WebSocket error: {}", e);
+                    }
+                }
+                Err(e) => {
+                    error!("Private connection failed: {}", e);
+                }
+            }
+
+            let retry_in = self.reconnect.delay(failures);
+            failures = failures.saturating_add(1);
+
+            warn!("Private WebSocket disconnected, reconnecting in {:?}", retry_in);
+            tokio::time::sleep(retry_in).await;
+        }
+    }
+
+    async fn handle_stream(&self, ws_stream: WsStream, failures: &mut u32) -> Result<()> {
+        let (mut write, mut read) = ws_stream.split();
+
+        // Authenticate before subscribing
+        let (api_key, expires, signature) = self.auth.generate_ws_auth();
+        let auth_msg = serde_json::json!({
+            "op": "auth",
+            "args": [api_key, expires, signature]
+        });
+        write.send(Message::Text(auth_msg.to_string())).await?;
+
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while let Some(msg) = read.next().await {
+                let Message::Text(text) = msg? else {
+                    continue;
+                };
+
+                let frame: PrivateFrame = serde_json::from_str(&text)?;
+                if frame.op.as_deref() == Some("auth") {
+                    if frame.success == Some(true) {
+                        return Ok(());
+                    }
+                    anyhow::bail!("Private auth rejected: {}", frame.ret_msg.unwrap_or_default());
+                }
+            }
+
+            anyhow::bail!("Connection closed during auth")
+        })
+        .await
+        .context("Private auth timed out")??;
+
+        info!("🔐 Private WebSocket authenticated");
+        // An authenticated session was healthy; start backoff over
+        *failures = 0;
+
+        let sub_msg = serde_json::json!({
+            "op": "subscribe",
+            "args": Self::TOPICS
+        });
+        write.send(Message::Text(sub_msg.to_string())).await?;
+        info!("Subscribed to private topics: {:?}", Self::TOPICS);
+
+        // Bybit expects an application-level ping on private streams; account events can be
+        // rare, so staleness is judged on any frame, pongs included
+        let mut ping = tokio::time::interval(self.heartbeat.ping_interval);
+        let mut health = tokio::time::interval(Duration::from_secs(1));
+        let mut ping_sent: Option<Instant> = None;
+        let mut last_read = Instant::now();
+
+        loop {
+            let msg = tokio::select! {
+                msg = read.next() => match msg {
+                    Some(msg) => msg?,
+                    None => break,
+                },
+                _ = ping.tick() => {
+                    if ping_sent.is_none() {
+                        let ping = serde_json::json!({ "op": "ping" }).to_string();
+                        write.send(Message::Text(ping)).await?;
+                        ping_sent = Some(Instant::now());
+                    }
+                    continue;
+                }
+                _ = health.tick() => {
+                    if let Some(sent) = ping_sent {
+                        if sent.elapsed() > self.heartbeat.pong_timeout {
+                            anyhow::bail!("no pong within {:?}", self.heartbeat.pong_timeout);
+                        }
+                    }
+
+                    let silence = last_read.elapsed();
+                    if silence > self.heartbeat.stale_after {
+                        anyhow::bail!("nothing received for {:?}", silence);
+                    }
+                    continue;
+                }
+            };
+
+            last_read = Instant::now();
+            match msg {
+                Message::Text(text) => match Self::decode(&text) {
+                    Ok(Some(event)) => {
+                        // No receivers is fine, events are fire-and-forget
+                        let _ = self.events.send(event);
+                    }
+                    Ok(None) => {
+                        // Only one ping is outstanding, so any op reply to it is the pong
+                        if Self::is_pong(&text) {
+                            ping_sent = None;
+                        }
+                    }
+                    Err(e) => error!("Failed to parse private message: {}", e),
+                },
+                Message::Close(_) => {
+                    warn!("Private WebSocket closed by server");
+                    break;
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Bybit answers `{"op":"ping"}` with op "pong" here, "ping" on public streams
+    fn is_pong(text: &str) -> bool {
+        serde_json::from_str::<PrivateFrame>(text)
+            .is_ok_and(|frame| matches!(frame.op.as_deref(), Some("ping" | "pong")))
+    }
+
+    /// Decode a private frame; control frames yield `None`
+    pub fn decode(text: &str) -> Result<Option<PrivateEvent>> {
+        let frame: PrivateFrame = serde_json::from_str(text)?;
+
+        let (Some(topic), Some(data)) = (frame.topic.as_deref(), frame.data) else {
+            if frame.success == Some(false) {
+                warn!("Private stream {:?} failed: {}", frame.op, frame.ret_msg.unwrap_or_default());
+            } else {
+                debug!("Private control message: {:?}", frame.op);
+            }
+            return Ok(None);
+        };
+
+        // Category-scoped topics look like "order.linear"
+        let event = match topic.split('.').next().unwrap_or(topic) {
+            "order" => PrivateEvent::Order(serde_json::from_value(data)?),
+            "execution" => PrivateEvent::Execution(serde_json::from_value(data)?),
+            "position" => PrivateEvent::Position(serde_json::from_value(data)?),
+            "wallet" => PrivateEvent::Wallet(serde_json::from_value(data)?),
+            other => {
+                debug!("Unhandled private topic: {}", other);
+                return Ok(None);
+            }
+        };
+
+        Ok(Some(event))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_execution() {
+        let text = r#"{"topic":"execution","id":"1","creationTime":1700000000000,"data":[
+            {"category":"linear","symbol":"BTCUSDT","orderId":"abc","execId":"e1","side":"Sell",
+             "execPrice":"49500.5","execQty":"0.010","execFee":"0.27","execType":"Trade",
+             "execTime":"1700000000000","isMaker":false,"stopOrderType":"StopLoss","closedSize":"0.010"}
+        ]}"#;
+
+        match BybitPrivateStream::decode(text).unwrap() {
+            Some(PrivateEvent::Execution(fills)) => {
+                assert_eq!(fills.len(), 1);
+                assert_eq!(fills[0].exec_price, "49500.5");
+                assert_eq!(fills[0].stop_order_type, "StopLoss");
+                assert!(!fills[0].is_maker);
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_decode_control_and_wallet() {
+        let auth = r#"{"success":true,"ret_msg":"","op":"auth","conn_id":"x"}"#;
+        assert!(BybitPrivateStream::decode(auth).unwrap().is_none());
+        assert!(!BybitPrivateStream::is_pong(auth));
+
+        let pong = r#"{"req_id":"1","op":"pong","args":["1700000000000"],"conn_id":"x"}"#;
+        assert!(BybitPrivateStream::decode(pong).unwrap().is_none());
+        assert!(BybitPrivateStream::is_pong(pong));
+
+        let wallet = r#"{"topic":"wallet","data":[{"accountType":"UNIFIED","totalEquity":"1000.5","totalPerpUPL":"-2.5","coin":[]}]}"#;
+        match BybitPrivateStream::decode(wallet).unwrap() {
+            Some(PrivateEvent::Wallet(wallets)) => assert_eq!(wallets[0].total_perp_upl, "-2.5"),
+            other => panic!("unexpected event: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_position_close_matches_leg_and_time() {
+        let text = r#"{"topic":"position","data":[
+            {"category":"linear","symbol":"BTCUSDT","positionIdx":2,"side":"","size":"0","updatedTime":"1700000005000","seq":7}
+        ]}"#;
+
+        let Some(PrivateEvent::Position(positions)) = BybitPrivateStream::decode(text).unwrap() else {
+            panic!("expected a position event");
+        };
+        let update = &positions[0];
+        assert_eq!(update.seq, 7);
+
+        assert!(update.closes("Sell", 1_700_000_000_000));
+        // Other hedge-mode leg
+        assert!(!update.closes("Buy", 1_700_000_000_000));
+        // Stamped before the position was opened
+        assert!(!update.closes("Sell", 1_700_000_006_000));
+    }
+}
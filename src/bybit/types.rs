@@ -1,15 +1,16 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 
+/// Levels are parsed from Bybit's decimal strings during decoding
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct OrderbookData {
     #[serde(rename = "s")]
     pub symbol: String,
     
-    #[serde(rename = "b")]
-    pub bids: Vec<(String, String)>,
+    #[serde(rename = "b", deserialize_with = "decimal_levels")]
+    pub bids: Vec<(f64, f64)>,
     
-    #[serde(rename = "a")]
-    pub asks: Vec<(String, String)>,
+    #[serde(rename = "a", deserialize_with = "decimal_levels")]
+    pub asks: Vec<(f64, f64)>,
     
     #[serde(rename = "u")]
     pub update_id: u64,
@@ -37,6 +38,9 @@ pub struct TradeData {
     
     #[serde(rename = "i")]
     pub trade_id: String,
+
+    #[serde(rename = "L", default)]
+    pub tick_direction: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -57,29 +61,196 @@ pub struct LiquidationData {
     pub updated_time: u64,
 }
 
-#[derive(Debug, Clone, Deserialize)]
-pub struct WsMessage {
-    pub topic: Option<String>,
-    #[serde(rename = "type")]
-    pub msg_type: Option<String>,
-    pub data: Option<serde_json::Value>,
-    pub ts: Option<u64>,
+/// Entry of the `allLiquidation.{symbol}` stream
+/// `side` is the side of the liquidated position: "Buy" means a long was liquidated
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AllLiquidationData {
+    #[serde(rename = "T")]
+    pub timestamp: u64,
+
+    #[serde(rename = "s")]
+    pub symbol: String,
+
+    #[serde(rename = "S")]
+    pub side: String,
+
+    #[serde(rename = "v")]
+    pub size: String,
+
+    #[serde(rename = "p")]
+    pub price: String,
+}
+
+/// Entry of the `tickers.{symbol}` stream
+/// Snapshots carry every field, deltas only the fields that changed
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TickerData {
+    pub symbol: String,
+    pub last_price: Option<String>,
+    pub mark_price: Option<String>,
+    pub index_price: Option<String>,
+    pub funding_rate: Option<String>,
+    pub next_funding_time: Option<String>,
+    pub open_interest: Option<String>,
+    pub open_interest_value: Option<String>,
+    pub high_price_24h: Option<String>,
+    pub low_price_24h: Option<String>,
+    pub volume_24h: Option<String>,
+    pub turnover_24h: Option<String>,
+    pub price_24h_pcnt: Option<String>,
+    pub bid1_price: Option<String>,
+    pub ask1_price: Option<String>,
+}
+
+/// Entry of the `kline.{interval}.{symbol}` stream
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct KlineData {
+    pub start: u64,
+    pub end: u64,
+    pub interval: String,
+    pub open: String,
+    pub close: String,
+    pub high: String,
+    pub low: String,
+    pub volume: String,
+    pub turnover: String,
+    pub confirm: bool,
+    pub timestamp: u64,
+}
+
+/// Entry of the private `order` stream
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct OrderUpdate {
+    pub category: String,
+    pub symbol: String,
+    pub order_id: String,
+    pub order_link_id: String,
+    pub side: String,
+    pub order_type: String,
+    pub price: String,
+    pub qty: String,
+    pub order_status: String,
+    pub avg_price: String,
+    pub cum_exec_qty: String,
+    pub stop_order_type: String,
+    pub reduce_only: bool,
+    pub reject_reason: String,
+    pub updated_time: String,
+}
+
+/// Entry of the private `execution` stream (one per fill)
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ExecutionUpdate {
+    pub category: String,
+    pub symbol: String,
+    pub order_id: String,
+    pub order_link_id: String,
+    pub exec_id: String,
+    pub side: String,
+    pub exec_price: String,
+    pub exec_qty: String,
+    pub exec_fee: String,
+    pub exec_type: String,
+    pub exec_time: String,
+    pub is_maker: bool,
+    pub stop_order_type: String,
+    pub closed_size: String,
+}
+
+/// Entry of the private `position` stream
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PositionUpdate {
+    pub category: String,
+    pub symbol: String,
+    pub position_idx: u8,
+    pub side: String,
+    pub size: String,
+    pub entry_price: String,
+    pub mark_price: String,
+    pub position_value: String,
+    pub unrealised_pnl: String,
+    pub cum_realised_pnl: String,
+    pub stop_loss: String,
+    pub take_profit: String,
+    pub position_status: String,
+    pub updated_time: String,
+    pub seq: i64,
+}
+
+impl PositionUpdate {
+    /// Whether this update reports the `side` position opened at `opened_at_ms` as flat
+    /// Size-0 updates for the other hedge-mode leg, or stamped before the open
+    /// (leverage changes, late frames), do not count
+    pub fn closes(&self, side: &str, opened_at_ms: u64) -> bool {
+        let flat = self.size.parse::<f64>().is_ok_and(|size| size == 0.0);
+        // positionIdx 1 / 2 are the Buy / Sell legs in hedge mode, 0 is one-way
+        let same_leg = match self.position_idx {
+            1 => side == "Buy",
+            2 => side == "Sell",
+            _ => true,
+        };
+        // Flat one-way positions report an empty side
+        let same_side = self.side.is_empty() || self.side == side;
+        let after_open = self.updated_time.parse::<u64>().is_ok_and(|ts| ts >= opened_at_ms);
+
+        flat && same_leg && same_side && after_open
+    }
+}
+
+/// Entry of the private `wallet` stream
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WalletUpdate {
+    pub account_type: String,
+    pub total_equity: String,
+    pub total_wallet_balance: String,
+    pub total_margin_balance: String,
+    pub total_available_balance: String,
+    #[serde(rename = "totalPerpUPL")]
+    pub total_perp_upl: String,
 }
 
-impl OrderbookData {
-    pub fn parse_levels(&self) -> (Vec<(f64, f64)>, Vec<(f64, f64)>) {
-        let bids: Vec<(f64, f64)> = self.bids.iter()
-            .filter_map(|(p, q)| {
-                Some((p.parse().ok()?, q.parse().ok()?))
-            })
-            .collect();
-        
-        let asks: Vec<(f64, f64)> = self.asks.iter()
-            .filter_map(|(p, q)| {
-                Some((p.parse().ok()?, q.parse().ok()?))
-            })
-            .collect();
-        
-        (bids, asks)
+/// `[["price", "size"], ...]` -> `(price, size)`, skipping unparseable levels
+/// Strings are borrowed from the frame where the decoder allows it, so nothing is allocated per level
+fn decimal_levels<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<(f64, f64)>, D::Error> {
+    struct Decimal(Option<f64>);
+
+    impl<'de> Deserialize<'de> for Decimal {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            struct DecimalVisitor;
+
+            impl serde::de::Visitor<'_> for DecimalVisitor {
+                type Value = Decimal;
+
+                fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                    f.write_str("a decimal string or number")
+                }
+
+                fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Decimal, E> {
+                    Ok(Decimal(v.parse().ok()))
+                }
+
+                fn visit_f64<E: serde::de::Error>(self, v: f64) -> Result<Decimal, E> {
+                    Ok(Decimal(Some(v)))
+                }
+
+                fn visit_u64<E: serde::de::Error>(self, v: u64) -> Result<Decimal, E> {
+                    Ok(Decimal(Some(v as f64)))
+                }
+
+                fn visit_i64<E: serde::de::Error>(self, v: i64) -> Result<Decimal, E> {
+                    Ok(Decimal(Some(v as f64)))
+                }
+            }
+
+            deserializer.deserialize_any(DecimalVisitor)
+        }
     }
+
+    let levels = Vec::<(Decimal, Decimal)>::deserialize(deserializer)?;
+    Ok(levels.into_iter().filter_map(|(p, q)| Some((p.0?, q.0?))).collect())
 }
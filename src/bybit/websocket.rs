@@ -1,119 +1,570 @@
 use tokio::net::TcpStream;
+use tokio::sync::{broadcast, mpsc, oneshot};
 use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
 use tokio_tungstenite::tungstenite::Message;
+use futures::stream::SplitSink;
 use futures::{SinkExt, StreamExt};
 use dashmap::DashMap;
+use parking_lot::RwLock;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tracing::{info, warn, error, debug};
 use anyhow::Result;
 
+use crate::capture::{Recorder, ReplayFeed, ReplayStats};
+use crate::utils::now_ns;
+
+use super::connection::{ConnectionState, HeartbeatPolicy, ReconnectPolicy};
+use super::dispatch::{DispatchStats, OverflowPolicy, TopicQueue};
+use super::event::{BybitEvent, DecodeMeter, DecodeStats, OpResponse};
+
 pub type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
-pub type MessageHandler = Arc<dyn Fn(serde_json::Value) -> Result<()> + Send + Sync>;
+pub type MessageHandler = Arc<dyn Fn(BybitEvent) -> Result<()> + Send + Sync>;
+/// Drops state built from a topic's snapshot once the connection is lost
+pub type InvalidateHandler = Arc<dyn Fn() + Send + Sync>;
+
+/// Bybit caps the number of topics in one subscribe/unsubscribe request
+const MAX_ARGS_PER_REQUEST: usize = 10;
+
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum SubscriptionError {
+    #[error("{op} rejected for {topics:?}: {msg}")]
+    Rejected { op: String, topics: Vec<String>, msg: String },
+
+    /// No ack arrived; the topics stay registered and are restored on reconnect
+    #[error("no acknowledgement for {0} within the timeout")]
+    Timeout(String),
+
+    /// The connection dropped before the ack, so the outcome is unknown; the topics
+    /// are re-subscribed on reconnect and a rejection then is published on
+    /// `subscription_failures`
+    #[error("connection closed before {0} was acknowledged")]
+    Disconnected(String),
+}
+
+/// Subscribe/unsubscribe request awaiting its ack, keyed by `req_id`
+struct PendingAck {
+    op: String,
+    topics: Vec<String>,
+    /// `None` for requests sent by the socket task itself (restore, resync); failures are
+    /// published on `subscription_failures`
+    reply: Option<oneshot::Sender<Result<(), SubscriptionError>>>,
+}
+
+/// Cloneable handle used by message handlers to ask for a fresh snapshot of a topic
+#[derive(Clone)]
+pub struct ResyncHandle {
+    tx: mpsc::UnboundedSender<String>,
+}
+
+impl ResyncHandle {
+    /// Request an unsubscribe/subscribe cycle for `topic` on the live connection
+    pub fn request(&self, topic: String) {
+        if self.tx.send(topic).is_err() {
+            warn!("Resync request dropped: WebSocket task is not running");
+        }
+    }
+}
+
+/// Cloneable handle for adding and removing topics on a running `BybitWebSocket`
+#[derive(Clone)]
+pub struct SubscriptionHandle {
+    ws: Arc<BybitWebSocket>,
+}
+
+impl SubscriptionHandle {
+    /// Register topics and subscribe them on the live connection
+    /// While disconnected the topics are only registered and get subscribed on the next connect
+    pub async fn subscribe(&self, topics: Vec<(String, MessageHandler)>) -> Result<(), SubscriptionError> {
+        let ws = &self.ws;
+
+        let requests = {
+            let mut active = ws.subscriptions.write();
+            let mut added = Vec::new();
+
+            for (topic, handler) in topics {
+                if active.contains(&topic) {
+                    debug!("Already subscribed to {}", topic);
+                    continue;
+                }
+
+                let queue = ws.make_queue(topic.clone(), handler);
+                queue.start();
+                ws.queues.insert(topic.clone(), queue);
+                active.push(topic.clone());
+                added.push(topic);
+            }
+
+            if added.is_empty() || !ws.connected.load(Ordering::Acquire) {
+                return Ok(());
+            }
+
+            info!("Subscribing to topics: {:?}", added);
+            ws.ack_requests("subscribe", &added)
+        };
+
+        let result = ws.send_and_wait("subscribe", requests).await;
+        if let Err(SubscriptionError::Rejected { topics, .. }) = &result {
+            ws.forget_topics(topics);
+        }
+
+        result
+    }
+
+    /// Stop routing the topics and unsubscribe them on the live connection
+    pub async fn unsubscribe(&self, topics: Vec<String>) -> Result<(), SubscriptionError> {
+        let ws = &self.ws;
+
+        let requests = {
+            let mut active = ws.subscriptions.write();
+            let removed: Vec<String> = topics.into_iter()
+                .filter(|topic| active.contains(topic))
+                .collect();
+
+            ws.forget_locked(&mut active, &removed);
+
+            if removed.is_empty() || !ws.connected.load(Ordering::Acquire) {
+                return Ok(());
+            }
+
+            info!("Unsubscribing from topics: {:?}", removed);
+            ws.ack_requests("unsubscribe", &removed)
+        };
+
+        ws.send_and_wait("unsubscribe", requests).await
+    }
+
+    /// Topics that are registered and restored on every (re)connect
+    pub fn active_topics(&self) -> Vec<String> {
+        self.ws.subscriptions.read().clone()
+    }
+}
 
 pub struct BybitWebSocket {
     url: String,
-    subscriptions: Vec<String>,
-    handlers: Arc<DashMap<String, MessageHandler>>,
+    /// Active topics, re-subscribed on every (re)connect
+    subscriptions: RwLock<Vec<String>>,
+    /// Per-topic ordered queues, keyed by topic (or topic prefix)
+    queues: Arc<DashMap<String, Arc<TopicQueue>>>,
+    queue_capacity: usize,
+    overflow_policy: OverflowPolicy,
+    /// Policy overrides keyed by topic prefix (e.g. "tickers")
+    overflow_overrides: Vec<(String, OverflowPolicy)>,
+    /// Topic prefixes that need a fresh snapshot after a reconnect, and how to invalidate their state
+    snapshot_topics: Vec<(String, InvalidateHandler)>,
+    resync_tx: mpsc::UnboundedSender<String>,
+    resync_rx: tokio::sync::Mutex<mpsc::UnboundedReceiver<String>>,
+    connected: AtomicBool,
+    next_req_id: AtomicU64,
+    ack_timeout: Duration,
+    pending_acks: DashMap<String, PendingAck>,
+    /// Control frames for the live connection
+    outbound_tx: mpsc::UnboundedSender<String>,
+    outbound_rx: tokio::sync::Mutex<mpsc::UnboundedReceiver<String>>,
+    reconnect: ReconnectPolicy,
+    heartbeat: HeartbeatPolicy,
+    /// Raw frame capture, if enabled
+    recorder: Option<Arc<Recorder>>,
+    state: RwLock<ConnectionState>,
+    state_tx: broadcast::Sender<ConnectionState>,
+    /// Subscribe rejections of requests nobody awaits (restore after reconnect, resync)
+    failures_tx: broadcast::Sender<SubscriptionError>,
+    decode_meter: DecodeMeter,
 }
 
 impl BybitWebSocket {
     pub fn new(url: String) -> Self {
+        let (resync_tx, resync_rx) = mpsc::unbounded_channel();
+        let (outbound_tx, outbound_rx) = mpsc::unbounded_channel();
+        let (state_tx, _) = broadcast::channel(64);
+        let (failures_tx, _) = broadcast::channel(64);
+
         Self {
             url,
-            subscriptions: Vec::new(),
-            handlers: Arc::new(DashMap::new()),
+            subscriptions: RwLock::new(Vec::new()),
+            queues: Arc::new(DashMap::new()),
+            queue_capacity: 1024,
+            overflow_policy: OverflowPolicy::Block,
+            overflow_overrides: Vec::new(),
+            snapshot_topics: Vec::new(),
+            resync_tx,
+            resync_rx: tokio::sync::Mutex::new(resync_rx),
+            connected: AtomicBool::new(false),
+            next_req_id: AtomicU64::new(1),
+            ack_timeout: Duration::from_secs(5),
+            pending_acks: DashMap::new(),
+            outbound_tx,
+            outbound_rx: tokio::sync::Mutex::new(outbound_rx),
+            reconnect: ReconnectPolicy::default(),
+            heartbeat: HeartbeatPolicy::default(),
+            recorder: None,
+            state: RwLock::new(ConnectionState::Connecting { attempt: 0 }),
+            state_tx,
+            failures_tx,
+            decode_meter: DecodeMeter::default(),
         }
     }
-    
+
+    /// Set the per-topic queue size and the default overflow policy
+    pub fn with_dispatch(mut self, queue_capacity: usize, overflow_policy: OverflowPolicy) -> Self {
+        self.queue_capacity = queue_capacity;
+        self.overflow_policy = overflow_policy;
+        self
+    }
+
+    pub fn with_reconnect_policy(mut self, reconnect: ReconnectPolicy) -> Self {
+        self.reconnect = reconnect;
+        self
+    }
+
+    pub fn with_heartbeat(mut self, heartbeat: HeartbeatPolicy) -> Self {
+        self.heartbeat = heartbeat;
+        self
+    }
+
+    /// Write every received frame to `recorder`
+    pub fn with_recorder(mut self, recorder: Arc<Recorder>) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    /// Use `policy` for topics starting with `prefix`
+    pub fn with_overflow_override(mut self, prefix: String, policy: OverflowPolicy) -> Self {
+        self.overflow_overrides.push((prefix, policy));
+        self
+    }
+
+    /// Treat topics starting with `prefix` as snapshot-based: `invalidate` runs on every disconnect,
+    /// and after a (re)connect the link only turns `Live` once each of them has sent a snapshot
+    pub fn with_snapshot_topic(mut self, prefix: String, invalidate: InvalidateHandler) -> Self {
+        self.snapshot_topics.push((prefix, invalidate));
+        self
+    }
+
+    fn policy_for(&self, topic: &str) -> OverflowPolicy {
+        self.overflow_overrides.iter()
+            .find(|(prefix, _)| topic.starts_with(prefix.as_str()))
+            .map(|(_, policy)| *policy)
+            .unwrap_or(self.overflow_policy)
+    }
+
+    fn make_queue(&self, topic: String, handler: MessageHandler) -> Arc<TopicQueue> {
+        let policy = self.policy_for(&topic);
+        debug!("Dispatch queue for {}: {:?} on overflow", topic, policy);
+        Arc::new(TopicQueue::new(topic, handler, self.queue_capacity, policy))
+    }
+
+    /// Register a topic before `run()`; use `control_handle()` once running
     pub fn subscribe(&mut self, topic: String, handler: MessageHandler) {
         info!("Subscribing to topic: {}", topic);
-        self.subscriptions.push(topic.clone());
-        self.handlers.insert(topic, handler);
+        let queue = self.make_queue(topic.clone(), handler);
+        self.subscriptions.get_mut().push(topic.clone());
+        self.queues.insert(topic, queue);
+    }
+
+    /// Get a handle for changing subscriptions on the live connection
+    pub fn control_handle(self: &Arc<Self>) -> SubscriptionHandle {
+        SubscriptionHandle { ws: self.clone() }
+    }
+
+    /// Queue depth and drop counters per topic
+    pub fn dispatch_stats(&self) -> Vec<DispatchStats> {
+        self.queues.iter().map(|entry| entry.value().stats()).collect()
+    }
+
+    /// Per-topic frame decode cost
+    pub fn decode_stats(&self) -> Vec<DecodeStats> {
+        self.decode_meter.stats()
+    }
+
+    /// Current connection lifecycle state
+    pub fn connection_state(&self) -> ConnectionState {
+        self.state.read().clone()
+    }
+
+    /// Subscribe to connection lifecycle transitions
+    pub fn connection_events(&self) -> broadcast::Receiver<ConnectionState> {
+        self.state_tx.subscribe()
     }
-    
+
+    /// Subscribe to rejections of restored or resynced topics; rejected subscriptions are dropped
+    pub fn subscription_failures(&self) -> broadcast::Receiver<SubscriptionError> {
+        self.failures_tx.subscribe()
+    }
+
+    /// Get a handle that handlers can use to trigger a topic resync
+    pub fn resync_handle(&self) -> ResyncHandle {
+        ResyncHandle { tx: self.resync_tx.clone() }
+    }
+
     pub async fn connect(&self) -> Result<WsStream> {
         info!("Connecting to Bybit WebSocket: {}", self.url);
-        
+
         let (ws_stream, response) = connect_async(&self.url).await?;
-        
+
         info!("WebSocket connected: {:?}", response.status());
-        
+
         Ok(ws_stream)
     }
-    
+
+    fn start_dispatch(&self) {
+        for entry in self.queues.iter() {
+            entry.value().start();
+        }
+    }
+
+    /// Wait until every topic queue is drained and no handler is running
+    async fn wait_idle(&self) {
+        while !self.queues.iter().all(|entry| entry.value().is_idle()) {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+    }
+
+    /// Feed recorded frames through the registered handlers instead of the network
+    /// Returns once every frame has been handled and, for a stepped feed, every slice evaluated
+    pub async fn replay(&self, feed: ReplayFeed) -> Result<ReplayStats> {
+        self.start_dispatch();
+        self.set_state(ConnectionState::Live);
+        info!("🎞️  Replaying {} capture file(s) ({:?})", feed.files().len(), feed.pacing());
+
+        let started = tokio::time::Instant::now();
+        let mut stats = ReplayStats::default();
+        let mut last_ts_ms = 0;
+
+        for record in feed.records() {
+            let record = record?;
+            if stats.frames + stats.skipped == 0 {
+                stats.first_recv_ns = record.recv_ns;
+            }
+            stats.last_recv_ns = record.recv_ns;
+
+            if let Some(offset) = feed.pacing().offset(record.recv_ns.saturating_sub(stats.first_recv_ns)) {
+                tokio::time::sleep_until(started + offset).await;
+            }
+
+            // Hand over each event-time slice this frame closes, with every earlier frame handled
+            let ts_ms = record.ts.unwrap_or(record.recv_ns / 1_000_000);
+            while let Some(boundary) = feed.due_step(ts_ms) {
+                self.wait_idle().await;
+                feed.step(boundary).await;
+            }
+            feed.clock().advance(ts_ms);
+            last_ts_ms = ts_ms;
+
+            match BybitEvent::decode(&mut record.frame.into_bytes()) {
+                Ok(event) => {
+                    self.route_message(event).await;
+                    stats.frames += 1;
+                }
+                Err(e) => {
+                    debug!("Skipping unparseable captured frame: {}", e);
+                    stats.skipped += 1;
+                }
+            }
+        }
+
+        self.wait_idle().await;
+        // Final state after the last frame
+        feed.step(last_ts_ms).await;
+        info!("🎞️  Replay finished: {} frames in {:?}", stats.frames, started.elapsed());
+        self.set_state(ConnectionState::Down {
+            reason: "replay finished".to_string(),
+            retry_in: Duration::ZERO,
+        });
+
+        Ok(stats)
+    }
+
     pub async fn run(self: Arc<Self>) -> Result<()> {
+        self.start_dispatch();
+
+        // Consecutive sessions that failed before proving healthy
+        let mut failures: u32 = 0;
+
         loop {
-            match self.connect().await {
+            self.set_state(ConnectionState::Connecting { attempt: failures + 1 });
+
+            let reason = match self.connect().await {
                 Ok(ws_stream) => {
-                    if let Err(e) = self.handle_stream(ws_stream).await {
-                        error!("WebSocket error: {}", e);
-                        warn!("Reconnecting in 5s...");
-                        tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+                    let started = Instant::now();
+                    let result = self.handle_stream(ws_stream).await;
+                    self.disconnect();
+
+                    // A session that outlived the stale window was healthy; start backoff over
+                    if started.elapsed() >= self.heartbeat.stale_after {
+                        failures = 0;
+                    }
+
+                    match result {
+                        Ok(()) => "closed by server".to_string(),
+                        Err(e) => e.to_string(),
                     }
                 }
-                Err(e) => {
-                    error!("Connection failed: {}", e);
-                    tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
-                }
-            }
+                Err(e) => format!("connection failed: {}", e),
+            };
+
+            let retry_in = self.reconnect.delay(failures);
+            failures = failures.saturating_add(1);
+
+            error!("WebSocket down ({}), reconnecting in {:?}", reason, retry_in);
+            self.set_state(ConnectionState::Down { reason, retry_in });
+            tokio::time::sleep(retry_in).await;
         }
     }
-    
+
     async fn handle_stream(&self, ws_stream: WsStream) -> Result<()> {
         let (mut write, mut read) = ws_stream.split();
-        
-        // Send subscriptions
-        for topic in &self.subscriptions {
-            let sub_msg = serde_json::json!({
-                "op": "subscribe",
-                "args": [topic]
-            });
-            
-            write.send(Message::Text(sub_msg.to_string())).await?;
-            info!("Subscribed to: {}", topic);
-        }
-        
-        // Ping task (keep-alive every 20s)
-        let ping_task = {
-            let write = Arc::new(tokio::sync::Mutex::new(write));
-            let write_clone = write.clone();
-            
-            tokio::spawn(async move {
-                let mut interval = tokio::time::interval(
-                    tokio::time::Duration::from_secs(20)
-                );
-                
-                loop {
-                    interval.tick().await;
-                    let mut w = write_clone.lock().await;
-                    if w.send(Message::Ping(vec![])).await.is_err() {
-                        break;
-                    }
-                }
-            })
+
+        // Control frames queued for the previous connection are covered by the restore below
+        let mut outbound_rx = self.outbound_rx.lock().await;
+        while outbound_rx.try_recv().is_ok() {}
+
+        // Restore active topics; from here on live changes go straight to the socket
+        let (restore, topic_count, awaiting) = {
+            let active = self.subscriptions.read();
+            self.connected.store(true, Ordering::Release);
+            let frames: Vec<String> = active.chunks(MAX_ARGS_PER_REQUEST)
+                .map(|batch| self.request_frame("subscribe", batch, None))
+                .collect();
+            let awaiting: Vec<String> = active.iter()
+                .filter(|topic| self.snapshot_topics.iter().any(|(prefix, _)| topic.starts_with(prefix.as_str())))
+                .cloned()
+                .collect();
+            (frames, active.len(), awaiting)
         };
-        
+
+        for frame in restore {
+            write.send(Message::Text(frame)).await?;
+        }
+        info!("Subscribed to {} topics", topic_count);
+
+        // Topics waiting for the snapshot that completes a resync; the restored snapshot
+        // topics count too, so the link is only live once their state is rebuilt
+        let mut resyncing: HashMap<String, Instant> = awaiting.into_iter()
+            .map(|topic| (topic, Instant::now()))
+            .collect();
+        if resyncing.is_empty() {
+            self.set_state(ConnectionState::Live);
+        }
+
+        let write = tokio::sync::Mutex::new(write);
+
+        // Any resync requested while disconnected is covered by the fresh subscriptions above
+        let mut resync_rx = self.resync_rx.lock().await;
+        while resync_rx.try_recv().is_ok() {}
+
+        // Application-level heartbeat and stale-data watchdog
+        let mut ping = tokio::time::interval(self.heartbeat.ping_interval);
+        let mut health = tokio::time::interval(Duration::from_secs(1));
+        let mut ping_sent: Option<Instant> = None;
+        let mut last_data = Instant::now();
+
         // Message processing loop
-        while let Some(msg) = read.next().await {
-            let msg = msg?;
-            
+        loop {
+            let msg = tokio::select! {
+                msg = read.next() => match msg {
+                    Some(msg) => msg?,
+                    None => break,
+                },
+                Some(topic) = resync_rx.recv() => {
+                    self.resubscribe(&write, &topic).await?;
+                    resyncing.entry(topic).or_insert_with(Instant::now);
+                    continue;
+                }
+                Some(frame) = outbound_rx.recv() => {
+                    write.lock().await.send(Message::Text(frame)).await?;
+                    continue;
+                }
+                _ = ping.tick() => {
+                    if ping_sent.is_none() {
+                        let ping = serde_json::json!({ "op": "ping" }).to_string();
+                        write.lock().await.send(Message::Text(ping)).await?;
+                        ping_sent = Some(Instant::now());
+                    }
+                    continue;
+                }
+                _ = health.tick() => {
+                    if let Some(sent) = ping_sent {
+                        if sent.elapsed() > self.heartbeat.pong_timeout {
+                            anyhow::bail!("no pong within {:?}", self.heartbeat.pong_timeout);
+                        }
+                    }
+
+                    if let Some((topic, _)) = resyncing.iter()
+                        .find(|(_, since)| since.elapsed() > self.heartbeat.resync_timeout)
+                    {
+                        anyhow::bail!("no snapshot for {} within {:?} of resync", topic, self.heartbeat.resync_timeout);
+                    }
+
+                    let silence = last_data.elapsed();
+                    if silence > self.heartbeat.stale_after {
+                        anyhow::bail!("no data for {:?}", silence);
+                    }
+                    if silence > self.heartbeat.degraded_after && self.connection_state().is_live() {
+                        warn!("⚠️  No market data for {:?}", silence);
+                        self.set_state(ConnectionState::Degraded {
+                            reason: format!("no data for {}s", silence.as_secs()),
+                        });
+                    }
+                    continue;
+                }
+            };
+
             match msg {
                 Message::Text(text) => {
+                    let recv_ns = now_ns();
+                    // Decoding happens in place, so the recorder gets its own copy of the text
+                    let raw = self.recorder.as_ref().map(|_| text.clone());
+                    let mut frame = text.into_bytes();
+
                     let start = std::time::Instant::now();
-                    
-                    // Parse JSON
-                    match serde_json::from_str::<serde_json::Value>(&text) {
-                        Ok(parsed) => {
-                            let parse_latency = start.elapsed().as_micros();
-                            if parse_latency > 100 {
-                                warn!("Slow JSON parse: {}μs", parse_latency);
+                    let decoded = BybitEvent::decode(&mut frame);
+                    let decode_time = start.elapsed();
+
+                    if let (Some(recorder), Some(raw)) = (&self.recorder, &raw) {
+                        let (ts, topic) = match &decoded {
+                            Ok(event) => (event.ts(), event.topic()),
+                            Err(_) => (None, None),
+                        };
+                        recorder.record(recv_ns, ts, topic, raw);
+                    }
+
+                    match decoded {
+                        Ok(event) => {
+                            self.decode_meter.record(event.topic(), decode_time);
+                            if decode_time.as_micros() > 100 {
+                                warn!("Slow decode of {}: {}μs", event.topic().unwrap_or("op"), decode_time.as_micros());
+                            }
+
+                            match &event {
+                                // Bybit answers `{"op":"ping"}` with op "ping" (public) or "pong" (private)
+                                BybitEvent::Op(op) if matches!(op.op.as_str(), "ping" | "pong") => {
+                                    debug!("Pong received");
+                                    ping_sent = None;
+                                    continue;
+                                }
+                                BybitEvent::Op(_) => {}
+                                _ => {
+                                    last_data = Instant::now();
+                                    if !resyncing.is_empty() && event.is_snapshot() {
+                                        if let Some(topic) = event.topic() {
+                                            resyncing.remove(topic);
+                                        }
+                                    }
+                                    if resyncing.is_empty() && !self.connection_state().is_live() {
+                                        info!("✅ Market data resumed");
+                                        self.set_state(ConnectionState::Live);
+                                    }
+                                }
                             }
-                            
-                            // Route to handler
-                            self.route_message(parsed).await;
+
+                            // Route to the topic queue
+                            self.route_message(event).await;
                         }
                         Err(e) => {
-                            error!("Failed to parse message: {}", e);
+                            error!("Failed to decode message: {}", e);
                         }
                     }
                 }
@@ -127,35 +578,281 @@ impl BybitWebSocket {
                 _ => {}
             }
         }
-        
-        ping_task.abort();
+
         Ok(())
     }
-    
-    async fn route_message(&self, msg: serde_json::Value) {
-        // Extract topic from message
-        if let Some(topic_value) = msg.get("topic") {
-            if let Some(topic_str) = topic_value.as_str() {
-                // Find matching handler by prefix
-                for handler_entry in self.handlers.iter() {
-                    let topic_key = handler_entry.key();
-                    
-                    // Match exact or prefix (e.g., "orderbook.50.BTCUSDT" matches "orderbook.50.")
-                    if topic_str == topic_key || topic_str.starts_with(topic_key) {
-                        if let Some(data) = msg.get("data") {
-                            let handler = handler_entry.value().clone();
-                            let data_clone = data.clone();
-                            
-                            tokio::spawn(async move {
-                                if let Err(e) = handler(data_clone) {
-                                    error!("Handler error: {}", e);
-                                }
-                            });
+
+    /// Drop and re-add a topic so Bybit pushes a fresh snapshot
+    async fn resubscribe(
+        &self,
+        write: &tokio::sync::Mutex<SplitSink<WsStream, Message>>,
+        topic: &str,
+    ) -> Result<()> {
+        warn!("🔁 Resyncing topic: {}", topic);
+
+        let topics = [topic.to_string()];
+        let mut w = write.lock().await;
+        for op in ["unsubscribe", "subscribe"] {
+            let msg = self.request_frame(op, &topics, None);
+            w.send(Message::Text(msg)).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Build a subscribe/unsubscribe frame and register its ack
+    fn request_frame(
+        &self,
+        op: &str,
+        topics: &[String],
+        reply: Option<oneshot::Sender<Result<(), SubscriptionError>>>,
+    ) -> String {
+        let req_id = format!("ofb-{}", self.next_req_id.fetch_add(1, Ordering::Relaxed));
+        let frame = serde_json::json!({
+            "req_id": req_id,
+            "op": op,
+            "args": topics,
+        });
+
+        self.pending_acks.insert(req_id, PendingAck {
+            op: op.to_string(),
+            topics: topics.to_vec(),
+            reply,
+        });
+
+        frame.to_string()
+    }
+
+    /// Batch `topics` under the per-request limit, one awaited ack per batch
+    fn ack_requests(
+        &self,
+        op: &str,
+        topics: &[String],
+    ) -> Vec<(String, oneshot::Receiver<Result<(), SubscriptionError>>)> {
+        topics.chunks(MAX_ARGS_PER_REQUEST)
+            .map(|batch| {
+                let (tx, rx) = oneshot::channel();
+                (self.request_frame(op, batch, Some(tx)), rx)
+            })
+            .collect()
+    }
+
+    async fn send_and_wait(
+        &self,
+        op: &str,
+        requests: Vec<(String, oneshot::Receiver<Result<(), SubscriptionError>>)>,
+    ) -> Result<(), SubscriptionError> {
+        let mut acks = Vec::with_capacity(requests.len());
+        for (frame, rx) in requests {
+            // A closed channel means the socket task is gone; the restore on reconnect covers it
+            let _ = self.outbound_tx.send(frame);
+            acks.push(rx);
+        }
+
+        let result = async {
+            for rx in acks {
+                match tokio::time::timeout(self.ack_timeout, rx).await {
+                    Ok(Ok(result)) => result?,
+                    Ok(Err(_)) => return Err(SubscriptionError::Disconnected(op.to_string())),
+                    Err(_) => return Err(SubscriptionError::Timeout(op.to_string())),
+                }
+            }
+            Ok(())
+        }.await;
+
+        // Batches still pending after an early return lost their receivers above
+        self.pending_acks.retain(|_, pending| !pending.reply.as_ref().is_some_and(|tx| tx.is_closed()));
+
+        result
+    }
+
+    /// Remove topics from the active set and stop their dispatch queues
+    fn forget_topics(&self, topics: &[String]) {
+        self.forget_locked(&mut self.subscriptions.write(), topics);
+    }
+
+    fn forget_locked(&self, active: &mut Vec<String>, topics: &[String]) {
+        active.retain(|topic| !topics.contains(topic));
+        for topic in topics {
+            if let Some((_, queue)) = self.queues.remove(topic) {
+                queue.stop();
+            }
+        }
+    }
+
+    fn set_state(&self, state: ConnectionState) {
+        *self.state.write() = state.clone();
+        // No receivers is fine, events are fire-and-forget
+        let _ = self.state_tx.send(state);
+    }
+
+    /// Mark the connection down and fail all in-flight acks
+    fn disconnect(&self) {
+        // Held so live changes see either the old or the new connection state, never a mix
+        let _active = self.subscriptions.write();
+        self.connected.store(false, Ordering::Release);
+        // Dropping the senders resolves waiting requests; the restore re-sends them
+        self.pending_acks.clear();
+        // Updates missed while down leave snapshot-based state behind the exchange
+        for (_, invalidate) in &self.snapshot_topics {
+            invalidate();
+        }
+    }
+
+    /// Resolve a subscribe/unsubscribe ack against its pending request
+    fn handle_ack(&self, msg: &OpResponse) {
+        let Some((_, pending)) = msg.req_id.as_deref().and_then(|id| self.pending_acks.remove(id)) else {
+            debug!("Unmatched {:?} ack: {:?}", msg.op, msg.req_id);
+            return;
+        };
+
+        let result = if msg.success == Some(false) {
+            Err(SubscriptionError::Rejected {
+                op: pending.op,
+                topics: pending.topics,
+                msg: msg.ret_msg.clone().unwrap_or_default(),
+            })
+        } else {
+            Ok(())
+        };
+
+        match pending.reply {
+            Some(tx) => {
+                let _ = tx.send(result);
+            }
+            None => {
+                if let Err(e) = result {
+                    error!("Subscription failed: {}", e);
+                    // A refused restore/resync subscribe means the topic is gone for good
+                    if let SubscriptionError::Rejected { op, topics, .. } = &e {
+                        if op == "subscribe" {
+                            self.forget_topics(topics);
+                            // No receivers is fine, failures are also logged
+                            let _ = self.failures_tx.send(e);
                         }
-                        break;
                     }
                 }
             }
         }
     }
+
+    /// Queue a frame on its topic's worker; frames of one topic are handled in arrival order
+    async fn route_message(&self, event: BybitEvent) {
+        let topic_str = match &event {
+            BybitEvent::Op(op) if matches!(op.op.as_str(), "subscribe" | "unsubscribe") => {
+                self.handle_ack(op);
+                return;
+            }
+            BybitEvent::Op(op) => {
+                debug!("Control message: {:?}", op.op);
+                return;
+            }
+            _ => event.topic().unwrap_or_default(),
+        };
+
+        // Find matching queue by prefix
+        let queue = self.queues.iter()
+            // Match exact or prefix (e.g., "orderbook.50.BTCUSDT" matches "orderbook.50.")
+            .find(|entry| topic_str == entry.key() || topic_str.starts_with(entry.key().as_str()))
+            .map(|entry| entry.value().clone());
+
+        if let Some(queue) = queue {
+            queue.push(event).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn noop() -> MessageHandler {
+        Arc::new(|_| Ok(()))
+    }
+
+    fn ack(sent: &serde_json::Value, success: bool) -> BybitEvent {
+        BybitEvent::Op(OpResponse {
+            op: sent["op"].as_str().unwrap().to_string(),
+            success: Some(success),
+            ret_msg: Some(if success { "" } else { "error:handler not found" }.to_string()),
+            req_id: sent["req_id"].as_str().map(str::to_string),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_while_disconnected_is_deferred() {
+        let ws = Arc::new(BybitWebSocket::new("wss://localhost/v5/public/linear".to_string()));
+        let handle = ws.control_handle();
+
+        handle.subscribe(vec![("tickers.BTCUSDT".to_string(), noop())]).await.unwrap();
+        assert_eq!(handle.active_topics(), vec!["tickers.BTCUSDT".to_string()]);
+        assert!(ws.outbound_rx.lock().await.try_recv().is_err());
+
+        handle.unsubscribe(vec!["tickers.BTCUSDT".to_string()]).await.unwrap();
+        assert!(handle.active_topics().is_empty());
+        assert!(ws.queues.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_live_subscribe_batches_and_surfaces_rejection() {
+        let ws = Arc::new(BybitWebSocket::new("wss://localhost/v5/public/linear".to_string()));
+        ws.connected.store(true, Ordering::Release);
+        let handle = ws.control_handle();
+
+        let topics: Vec<(String, MessageHandler)> = (0..12)
+            .map(|i| (format!("publicTrade.COIN{}USDT", i), noop()))
+            .collect();
+        let requester = {
+            let handle = handle.clone();
+            tokio::spawn(async move { handle.subscribe(topics).await })
+        };
+
+        let mut outbound = ws.outbound_rx.lock().await;
+        let first: serde_json::Value = serde_json::from_str(&outbound.recv().await.unwrap()).unwrap();
+        let second: serde_json::Value = serde_json::from_str(&outbound.recv().await.unwrap()).unwrap();
+        assert_eq!(first["args"].as_array().unwrap().len(), MAX_ARGS_PER_REQUEST);
+        assert_eq!(second["args"].as_array().unwrap().len(), 2);
+
+        ws.route_message(ack(&first, true)).await;
+        ws.route_message(ack(&second, false)).await;
+
+        match requester.await.unwrap() {
+            Err(SubscriptionError::Rejected { topics, .. }) => assert_eq!(topics.len(), 2),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert_eq!(handle.active_topics().len(), MAX_ARGS_PER_REQUEST);
+        assert!(ws.pending_acks.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_early_rejection_clears_pending_and_restore_rejection_is_published() {
+        let ws = Arc::new(BybitWebSocket::new("wss://localhost/v5/public/linear".to_string()));
+        ws.connected.store(true, Ordering::Release);
+        let handle = ws.control_handle();
+        let mut failures = ws.subscription_failures();
+
+        let topics: Vec<(String, MessageHandler)> = (0..12)
+            .map(|i| (format!("publicTrade.COIN{}USDT", i), noop()))
+            .collect();
+        let requester = {
+            let handle = handle.clone();
+            tokio::spawn(async move { handle.subscribe(topics).await })
+        };
+
+        // The first batch is rejected while the second is still in flight
+        let mut outbound = ws.outbound_rx.lock().await;
+        let first: serde_json::Value = serde_json::from_str(&outbound.recv().await.unwrap()).unwrap();
+        ws.route_message(ack(&first, false)).await;
+        assert!(matches!(requester.await.unwrap(), Err(SubscriptionError::Rejected { .. })));
+        assert!(ws.pending_acks.is_empty());
+
+        // A restore frame has no awaiting caller
+        let restore: serde_json::Value = serde_json::from_str(
+            &ws.request_frame("subscribe", &["publicTrade.COIN10USDT".to_string()], None),
+        ).unwrap();
+        ws.route_message(ack(&restore, false)).await;
+
+        assert!(matches!(failures.recv().await.unwrap(), SubscriptionError::Rejected { .. }));
+        assert!(!handle.active_topics().contains(&"publicTrade.COIN10USDT".to_string()));
+    }
 }
@@ -0,0 +1,5 @@
+pub mod recorder;
+pub mod replay;
+
+pub use recorder::{CaptureRecord, CaptureStats, Recorder, RecorderConfig};
+pub use replay::{Pacing, ReplayFeed, ReplayStats, ReplaySteps};
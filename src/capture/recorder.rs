@@ -0,0 +1,279 @@
+use crossbeam::channel::{self, Receiver, RecvTimeoutError, Sender, TrySendError};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use tracing::{error, info};
+use anyhow::Result;
+
+/// One captured WebSocket frame, stored as a JSON line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaptureRecord {
+    /// Local receive time (ns since epoch)
+    pub recv_ns: u64,
+    /// Exchange `ts` (ms), if the frame carried one
+    pub ts: Option<u64>,
+    pub topic: Option<String>,
+    /// Frame text exactly as received
+    pub frame: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct RecorderConfig {
+    pub dir: PathBuf,
+    /// File name prefix, e.g. "public" -> public-20240101-120000-0000.jsonl.gz
+    pub prefix: String,
+    /// Topic prefixes to keep; empty keeps every frame
+    pub topics: Vec<String>,
+    /// Rotate after this many uncompressed bytes
+    pub max_file_bytes: u64,
+    /// Rotate after this long
+    pub max_file_age: Duration,
+    /// Frames buffered for the writer thread before new ones are dropped
+    pub queue_capacity: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CaptureStats {
+    pub written: u64,
+    pub dropped: u64,
+    pub files: u64,
+}
+
+#[derive(Default)]
+struct Counters {
+    written: AtomicU64,
+    dropped: AtomicU64,
+    files: AtomicU64,
+}
+
+/// Writes raw frames to rotating gzip JSON-lines files on a background thread
+/// `record` never blocks the socket reader; frames are dropped if the writer falls behind
+pub struct Recorder {
+    topics: Vec<String>,
+    tx: Mutex<Option<Sender<CaptureRecord>>>,
+    writer: Mutex<Option<JoinHandle<()>>>,
+    counters: Arc<Counters>,
+}
+
+impl Recorder {
+    pub fn start(config: RecorderConfig) -> Result<Self> {
+        fs::create_dir_all(&config.dir)?;
+
+        let (tx, rx) = channel::bounded(config.queue_capacity.max(1));
+        let counters = Arc::new(Counters::default());
+        let topics = config.topics.clone();
+
+        let writer = {
+            let counters = counters.clone();
+            std::thread::Builder::new()
+                .name("capture-writer".to_string())
+                .spawn(move || {
+                    if let Err(e) = write_loop(config, rx, &counters) {
+                        error!("Capture writer stopped: {}", e);
+                    }
+                })?
+        };
+
+        Ok(Self {
+            topics,
+            tx: Mutex::new(Some(tx)),
+            writer: Mutex::new(Some(writer)),
+            counters,
+        })
+    }
+
+    /// Whether frames of `topic` are captured; control frames are kept only without a filter
+    pub fn wants(&self, topic: Option<&str>) -> bool {
+        if self.topics.is_empty() {
+            return true;
+        }
+
+        topic.is_some_and(|t| self.topics.iter().any(|prefix| t.starts_with(prefix.as_str())))
+    }
+
+    /// Queue a frame for the writer
+    pub fn record(&self, recv_ns: u64, ts: Option<u64>, topic: Option<&str>, frame: &str) {
+        if !self.wants(topic) {
+            return;
+        }
+
+        let record = CaptureRecord {
+            recv_ns,
+            ts,
+            topic: topic.map(str::to_string),
+            frame: frame.to_string(),
+        };
+
+        let tx = self.tx.lock();
+        let Some(tx) = tx.as_ref() else {
+            return;
+        };
+
+        match tx.try_send(record) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) | Err(TrySendError::Disconnected(_)) => {
+                self.counters.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    pub fn stats(&self) -> CaptureStats {
+        CaptureStats {
+            written: self.counters.written.load(Ordering::Relaxed),
+            dropped: self.counters.dropped.load(Ordering::Relaxed),
+            files: self.counters.files.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Flush queued frames and finish the current file
+    pub fn close(&self) {
+        // Dropping the sender lets the writer drain the queue and exit
+        self.tx.lock().take();
+
+        if let Some(writer) = self.writer.lock().take() {
+            let _ = writer.join();
+        }
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+struct CaptureFile {
+    encoder: GzEncoder<BufWriter<File>>,
+    opened: Instant,
+    bytes: u64,
+}
+
+impl CaptureFile {
+    fn open(config: &RecorderConfig, counters: &Counters) -> Result<Self> {
+        // Sequence suffix keeps names unique when rotating within the same second
+        let seq = counters.files.fetch_add(1, Ordering::Relaxed);
+        let stamp = chrono::Utc::now().format("%Y%m%d-%H%M%S");
+        let path = config.dir.join(format!("{}-{}-{:04}.jsonl.gz", config.prefix, stamp, seq));
+        info!("📼 Capturing to {}", path.display());
+
+        let file = File::create(&path)?;
+
+        Ok(Self {
+            encoder: GzEncoder::new(BufWriter::new(file), Compression::fast()),
+            opened: Instant::now(),
+            bytes: 0,
+        })
+    }
+
+    fn is_full(&self, config: &RecorderConfig) -> bool {
+        // Never rotate an empty file, so an idle feed doesn't litter the directory
+        self.bytes > 0 && (self.bytes >= config.max_file_bytes || self.opened.elapsed() >= config.max_file_age)
+    }
+
+    fn finish(self) -> Result<()> {
+        self.encoder.finish()?.flush()?;
+        Ok(())
+    }
+}
+
+fn write_loop(config: RecorderConfig, rx: Receiver<CaptureRecord>, counters: &Counters) -> Result<()> {
+    let mut file = CaptureFile::open(&config, counters)?;
+
+    loop {
+        let received = rx.recv_timeout(Duration::from_secs(1));
+
+        if !matches!(received, Err(RecvTimeoutError::Disconnected)) && file.is_full(&config) {
+            let next = CaptureFile::open(&config, counters)?;
+            std::mem::replace(&mut file, next).finish()?;
+        }
+
+        match received {
+            Ok(record) => {
+                let mut line = serde_json::to_vec(&record)?;
+                line.push(b'\n');
+                file.encoder.write_all(&line)?;
+                file.bytes += line.len() as u64;
+                counters.written.fetch_add(1, Ordering::Relaxed);
+            }
+            // Sync-flush when idle so a crash loses at most the last second
+            Err(RecvTimeoutError::Timeout) => file.encoder.flush()?,
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+
+    file.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use std::io::{BufRead, BufReader};
+
+    fn config(name: &str, max_file_bytes: u64) -> RecorderConfig {
+        let dir = std::env::temp_dir().join(format!("capture-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        RecorderConfig {
+            dir,
+            prefix: "public".to_string(),
+            topics: vec!["orderbook.".to_string()],
+            max_file_bytes,
+            max_file_age: Duration::from_secs(3600),
+            queue_capacity: 1024,
+        }
+    }
+
+    fn read_all(dir: &PathBuf) -> Vec<CaptureRecord> {
+        let mut paths: Vec<_> = fs::read_dir(dir).unwrap().map(|e| e.unwrap().path()).collect();
+        paths.sort();
+
+        paths.iter()
+            .flat_map(|path| BufReader::new(GzDecoder::new(File::open(path).unwrap())).lines())
+            .map(|line| serde_json::from_str(&line.unwrap()).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_records_round_trip_with_topic_filter() {
+        let config = config("filter", u64::MAX);
+        let dir = config.dir.clone();
+        let recorder = Recorder::start(config).unwrap();
+
+        recorder.record(1, Some(10), Some("orderbook.50.BTCUSDT"), r#"{"topic":"orderbook.50.BTCUSDT"}"#);
+        recorder.record(2, Some(11), Some("publicTrade.BTCUSDT"), r#"{"topic":"publicTrade.BTCUSDT"}"#);
+        recorder.record(3, None, None, r#"{"op":"ping"}"#);
+        recorder.close();
+
+        let records = read_all(&dir);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].recv_ns, 1);
+        assert_eq!(records[0].ts, Some(10));
+        assert_eq!(records[0].frame, r#"{"topic":"orderbook.50.BTCUSDT"}"#);
+        assert_eq!(recorder.stats().written, 1);
+    }
+
+    #[test]
+    fn test_rotates_by_size() {
+        let config = config("rotate", 1);
+        let dir = config.dir.clone();
+        let recorder = Recorder::start(config).unwrap();
+
+        for seq in 0..3 {
+            recorder.record(seq, Some(seq), Some("orderbook.1.BTCUSDT"), "{}");
+        }
+        recorder.close();
+
+        assert_eq!(recorder.stats().files, 3);
+        let records = read_all(&dir);
+        assert_eq!(records.iter().map(|r| r.recv_ns).collect::<Vec<_>>(), vec![0, 1, 2]);
+    }
+}
@@ -0,0 +1,240 @@
+use anyhow::{Context, Result};
+use flate2::read::GzDecoder;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+
+use super::recorder::CaptureRecord;
+use crate::utils::Clock;
+
+/// How fast recorded frames are fed back
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pacing {
+    /// Original inter-frame gaps
+    RealTime,
+    /// Gaps divided by the factor (e.g. 10.0 = ten times faster)
+    Accelerated(f64),
+    /// No waiting between frames
+    AsFastAsPossible,
+}
+
+impl Pacing {
+    /// Values accepted by `from_config`; "accelerated" also needs a speed above zero
+    pub const CONFIG_VALUES: &'static [&'static str] = &["realtime", "accelerated", "max"];
+
+    pub fn from_config(pacing: &str, speed: f64) -> Self {
+        match pacing {
+            "accelerated" if speed > 0.0 => Pacing::Accelerated(speed),
+            "max" => Pacing::AsFastAsPossible,
+            _ => Pacing::RealTime,
+        }
+    }
+
+    /// Wall-clock offset from replay start for a frame received `elapsed_ns` after the first one
+    pub fn offset(&self, elapsed_ns: u64) -> Option<Duration> {
+        match self {
+            Pacing::RealTime => Some(Duration::from_nanos(elapsed_ns)),
+            Pacing::Accelerated(speed) => Some(Duration::from_nanos((elapsed_ns as f64 / speed) as u64)),
+            Pacing::AsFastAsPossible => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ReplayStats {
+    /// Frames routed to handlers
+    pub frames: u64,
+    /// Frames that no longer decode as a `BybitEvent`
+    pub skipped: u64,
+    pub first_recv_ns: u64,
+    pub last_recv_ns: u64,
+}
+
+/// Event-time slices handed from a replay to the strategy loop, one at a time
+///
+/// The replay waits at each slice boundary until the loop asks for the next slice,
+/// so every slice is evaluated whatever the pacing.
+pub struct ReplaySteps {
+    rx: mpsc::Receiver<oneshot::Sender<()>>,
+    /// Releases the replay from the slice being evaluated
+    done: Option<oneshot::Sender<()>>,
+}
+
+impl ReplaySteps {
+    /// Finish the previous slice and wait for the next one; `None` once the replay is over
+    pub async fn next(&mut self) -> Option<()> {
+        if let Some(done) = self.done.take() {
+            let _ = done.send(());
+        }
+        self.done = Some(self.rx.recv().await?);
+        Some(())
+    }
+}
+
+struct Stepper {
+    step_ms: u64,
+    /// Next slice boundary in event time, 0 until the first frame
+    next_ms: AtomicU64,
+    tx: mpsc::Sender<oneshot::Sender<()>>,
+}
+
+/// Recorded capture files read back in order; driven by `BybitWebSocket::replay`
+pub struct ReplayFeed {
+    files: Vec<PathBuf>,
+    pacing: Pacing,
+    clock: Clock,
+    stepper: Option<Stepper>,
+}
+
+impl ReplayFeed {
+    /// Open a single capture file, or every `.jsonl`/`.jsonl.gz` file in a directory by name order
+    pub fn open(path: impl AsRef<Path>, pacing: Pacing) -> Result<Self> {
+        let path = path.as_ref();
+
+        let files = if path.is_dir() {
+            let mut files: Vec<PathBuf> = fs::read_dir(path)
+                .with_context(|| format!("Cannot read capture dir {}", path.display()))?
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|p| {
+                    let name = p.to_string_lossy();
+                    name.ends_with(".jsonl") || name.ends_with(".jsonl.gz")
+                })
+                .collect();
+            files.sort();
+            files
+        } else {
+            vec![path.to_path_buf()]
+        };
+
+        if files.is_empty() {
+            anyhow::bail!("No capture files in {}", path.display());
+        }
+
+        Ok(Self { files, pacing, clock: Clock::wall(), stepper: None })
+    }
+
+    /// Move `clock` along the exchange timestamps of the replayed frames
+    pub fn with_clock(mut self, clock: Clock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Stop at every `step_ms` of event time until the returned `ReplaySteps` takes the slice
+    pub fn stepped(mut self, step_ms: u64) -> (Self, ReplaySteps) {
+        let (tx, rx) = mpsc::channel(1);
+        self.stepper = Some(Stepper { step_ms: step_ms.max(1), next_ms: AtomicU64::new(0), tx });
+        (self, ReplaySteps { rx, done: None })
+    }
+
+    pub fn clock(&self) -> &Clock {
+        &self.clock
+    }
+
+    /// Slice boundary reached by a frame stamped `ts_ms`, if any; call until `None`
+    pub fn due_step(&self, ts_ms: u64) -> Option<u64> {
+        let stepper = self.stepper.as_ref()?;
+        let next = stepper.next_ms.load(Ordering::Acquire);
+        if next == 0 {
+            stepper.next_ms.store(ts_ms + stepper.step_ms, Ordering::Release);
+            return None;
+        }
+
+        (next <= ts_ms).then(|| {
+            stepper.next_ms.store(next + stepper.step_ms, Ordering::Release);
+            next
+        })
+    }
+
+    /// Move the clock to `at_ms` and wait until the strategy loop has evaluated that slice
+    pub async fn step(&self, at_ms: u64) {
+        self.clock.advance(at_ms);
+
+        let Some(stepper) = &self.stepper else {
+            return;
+        };
+        let (done_tx, done_rx) = oneshot::channel();
+        // A loop that has stopped listening just lets the replay run on
+        if stepper.tx.send(done_tx).await.is_ok() {
+            let _ = done_rx.await;
+        }
+    }
+
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    pub fn pacing(&self) -> Pacing {
+        self.pacing
+    }
+
+    /// Records across all files, in capture order
+    pub fn records(&self) -> impl Iterator<Item = Result<CaptureRecord>> + Send + '_ {
+        self.files.iter().flat_map(|path| -> Box<dyn Iterator<Item = Result<CaptureRecord>> + Send> {
+            let file = match File::open(path) {
+                Ok(file) => file,
+                Err(e) => return Box::new(std::iter::once(Err(e.into()))),
+            };
+
+            let reader: Box<dyn Read + Send> = if path.to_string_lossy().ends_with(".gz") {
+                Box::new(GzDecoder::new(file))
+            } else {
+                Box::new(file)
+            };
+
+            Box::new(BufReader::new(reader).lines().map(|line| Ok(serde_json::from_str(&line?)?)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pacing_offsets() {
+        assert_eq!(Pacing::RealTime.offset(2_000_000_000), Some(Duration::from_secs(2)));
+        assert_eq!(Pacing::Accelerated(4.0).offset(2_000_000_000), Some(Duration::from_millis(500)));
+        assert_eq!(Pacing::AsFastAsPossible.offset(2_000_000_000), None);
+
+        assert_eq!(Pacing::from_config("accelerated", 0.0), Pacing::RealTime);
+        assert_eq!(Pacing::from_config("max", 1.0), Pacing::AsFastAsPossible);
+        let pacings: Vec<_> = Pacing::CONFIG_VALUES.iter().map(|value| Pacing::from_config(value, 4.0)).collect();
+        assert_eq!(pacings, vec![Pacing::RealTime, Pacing::Accelerated(4.0), Pacing::AsFastAsPossible]);
+    }
+
+    #[tokio::test]
+    async fn test_steps_follow_event_time() {
+        let dir = std::env::temp_dir().join(format!("replay-steps-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("frames.jsonl");
+        fs::write(&path, "").unwrap();
+
+        let clock = Clock::event();
+        let (feed, mut steps) = ReplayFeed::open(&path, Pacing::AsFastAsPossible).unwrap()
+            .with_clock(clock.clone())
+            .stepped(5_000);
+
+        // The first frame starts the slices; a frame 12s later closes two of them
+        assert_eq!(feed.due_step(100_000), None);
+        assert_eq!(feed.due_step(104_999), None);
+        assert_eq!(feed.due_step(112_000), Some(105_000));
+        assert_eq!(feed.due_step(112_000), Some(110_000));
+        assert_eq!(feed.due_step(112_000), None);
+
+        // The replay waits until the loop has taken the slice and asks for the next one
+        let replay = tokio::spawn(async move {
+            feed.step(105_000).await;
+        });
+        steps.next().await.unwrap();
+        assert_eq!(clock.now_ms(), 105_000);
+        assert!(!replay.is_finished());
+
+        assert!(steps.next().await.is_none());
+        replay.await.unwrap();
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,7 +1,13 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::sync::Arc;
 
+use crate::bybit::{Category, OverflowPolicy};
+use crate::capture::Pacing;
+use crate::market_data::{CandleSource, TriggerPrice};
+use crate::orderbook::{DepthMeasure, ReferencePrice};
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Config {
     pub bybit: BybitConfig,
@@ -13,6 +19,14 @@ pub struct Config {
     pub strategy: StrategyConfig,
     #[serde(default)]
     pub validation: ValidationConfig,
+    #[serde(default)]
+    pub market_data: MarketDataConfig,
+    #[serde(default)]
+    pub websocket: WebSocketConfig,
+    #[serde(default)]
+    pub capture: CaptureConfig,
+    #[serde(default)]
+    pub replay: ReplayConfig,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -22,16 +36,37 @@ pub struct BybitConfig {
     pub testnet: bool,
     pub ws_url: String,
     pub rest_url: String,
+    #[serde(default = "default_private_ws_url")]
+    pub private_ws_url: String,
+    #[serde(default = "default_trade_ws_url")]
+    pub trade_ws_url: String,
+    #[serde(default)]
+    pub use_ws_trade: bool,                   // Place orders over the WS trade API, REST as fallback
 }
 
+fn default_private_ws_url() -> String { "wss://stream-demo.bybit.com/v5/private".to_string() }
+fn default_trade_ws_url() -> String { "wss://stream-demo.bybit.com/v5/trade".to_string() }
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct TradingConfig {
     pub symbol: String,
+    #[serde(default)]
+    pub category: Category,
     pub risk_per_trade_pct: f64,
     pub max_leverage: u8,
     pub target_maker_ratio: f64,
     pub min_time_between_trades_ms: u64,
     pub max_trades_per_hour: u32,
+    /// Price used for sizing, entries and exit checks:
+    /// "mid", "microprice", "weighted_mid" or "imbalance_microprice"
+    #[serde(default = "default_reference_price")]
+    pub reference_price: String,
+    /// Levels used by "weighted_mid" and "imbalance_microprice"
+    #[serde(default = "default_fair_value_depth")]
+    pub fair_value_depth: usize,
+    /// When > 0, "weighted_mid" uses levels within this many bps of mid instead
+    #[serde(default)]
+    pub fair_value_band_bps: f64,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -40,7 +75,7 @@ pub struct RiskConfig {
     pub max_consecutive_losses: u8,
     pub max_latency_ms: u64,
     pub max_spread_pct: f64,
-    pub min_liquidity_btc: f64,
+    pub min_liquidity_usd: f64,               // Top-10-level book value in the quote currency
     pub kill_switch_enabled: bool,
     // Phase 3B: Dynamic risk management
     #[serde(default = "default_base_sl_pct")]
@@ -51,6 +86,10 @@ pub struct RiskConfig {
     pub volatility_multiplier: f64,
     #[serde(default = "default_atr_period")]
     pub atr_period: usize,
+    #[serde(default = "default_atr_interval")]
+    pub atr_interval: String,                 // Bybit kline interval for ATR bars ("1", "5", "60", "D")
+    #[serde(default = "default_atr_source")]
+    pub atr_source: String,                   // "trades", "kline" or "both"
     // Native Bybit SL/TP Orders
     #[serde(default = "default_use_native_sltp")]
     pub use_native_sltp: bool,
@@ -60,25 +99,47 @@ pub struct RiskConfig {
     pub sltp_trigger_by: String,              // "LastPrice", "MarkPrice", "IndexPrice"
     #[serde(default = "default_keep_software_monitoring")]
     pub keep_software_monitoring: bool,       // Keep software monitoring as backup
+    #[serde(default = "default_max_slippage_bps")]
+    pub max_slippage_bps: f64,                // Skip entries whose book-walk slippage vs mid is larger
+    #[serde(default = "default_order_usd")]
+    pub order_usd: f64,                       // Order value before volatility sizing
+    #[serde(default = "default_max_order_usd")]
+    pub max_order_usd: f64,                   // Cap on order value after volatility sizing
+    #[serde(default = "default_ticker_max_age_ms")]
+    pub ticker_max_age_ms: u64,               // Exits fall back to the book when the ticker is older
 }
 
 fn default_base_sl_pct() -> f64 { 0.01 }
 fn default_base_tp_pct() -> f64 { 0.02 }
 fn default_volatility_multiplier() -> f64 { 0.5 }
 fn default_atr_period() -> usize { 14 }
+fn default_atr_interval() -> String { "1".to_string() }
+fn default_atr_source() -> String { "both".to_string() }
 fn default_use_native_sltp() -> bool { true }
 fn default_sltp_order_type() -> String { "Market".to_string() }
 fn default_sltp_trigger_by() -> String { "LastPrice".to_string() }
 fn default_keep_software_monitoring() -> bool { true }
+fn default_max_slippage_bps() -> f64 { 10.0 }
+fn default_order_usd() -> f64 { 1_000.0 }
+fn default_max_order_usd() -> f64 { 5_000.0 }
+fn default_ticker_max_age_ms() -> u64 { 5_000 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct PerformanceConfig {
     pub enable_metrics: bool,
     pub metrics_port: u16,
     pub log_level: String,
+    /// Depth of the `orderbook.{depth}` stream; must be one the trading category publishes
     pub orderbook_depth: usize,
+    /// Also subscribe to `orderbook.1` and take best bid/ask from it
+    #[serde(default)]
+    pub bbo_feed: bool,
+    #[serde(default = "default_bbo_mismatch_tolerance_ms")]
+    pub bbo_mismatch_tolerance_ms: u64,
 }
 
+fn default_bbo_mismatch_tolerance_ms() -> u64 { 1000 }
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct TelegramConfig {
     pub enabled: bool,
@@ -99,16 +160,28 @@ pub struct StrategyConfig {
     pub pressure_weight: f64,
     #[serde(default = "default_depth_consistency_weight")]
     pub depth_consistency_weight: f64,
+    #[serde(default = "default_ofi_weight")]
+    pub ofi_weight: f64,
 
     // Analysis parameters
     #[serde(default = "default_depth_levels")]
     pub depth_levels: Vec<usize>,
+    #[serde(default = "default_depth_mode")]
+    pub depth_mode: String,                  // Imbalance depths: "levels", "bps" (bands) or "decay"
+    #[serde(default = "default_depth_bands_bps")]
+    pub depth_bands_bps: Vec<f64>,           // Band widths / decay lengths for "bps" and "decay"
+    #[serde(default = "default_imbalance_depth")]
+    pub imbalance_depth: f64,                // Main imbalance input: levels, or band / decay length (bps) per depth_mode
     #[serde(default = "default_whale_threshold")]
     pub whale_threshold_multiplier: f64,
-    #[serde(default = "default_min_whale_size")]
-    pub min_whale_size_btc: f64,
+    #[serde(default = "default_min_whale_notional")]
+    pub min_whale_notional_usd: f64,         // Smallest level value (quote currency) that can be a whale
     #[serde(default = "default_delta_windows")]
-    pub delta_windows: Vec<u64>,
+    pub delta_windows: Vec<u64>,             // CVD windows (ms), from the trade tape
+    #[serde(default = "default_ofi_depths")]
+    pub ofi_depths: Vec<usize>,              // Book levels for multi-level order-flow imbalance
+    #[serde(default = "default_ofi_window_ms")]
+    pub ofi_window_ms: u64,                  // OFI window fed to the strategy
 }
 
 impl Default for StrategyConfig {
@@ -119,10 +192,16 @@ impl Default for StrategyConfig {
             whale_weight: default_whale_weight(),
             pressure_weight: default_pressure_weight(),
             depth_consistency_weight: default_depth_consistency_weight(),
+            ofi_weight: default_ofi_weight(),
             depth_levels: default_depth_levels(),
+            depth_mode: default_depth_mode(),
+            depth_bands_bps: default_depth_bands_bps(),
+            imbalance_depth: default_imbalance_depth(),
             whale_threshold_multiplier: default_whale_threshold(),
-            min_whale_size_btc: default_min_whale_size(),
+            min_whale_notional_usd: default_min_whale_notional(),
             delta_windows: default_delta_windows(),
+            ofi_depths: default_ofi_depths(),
+            ofi_window_ms: default_ofi_window_ms(),
         }
     }
 }
@@ -133,9 +212,17 @@ fn default_whale_weight() -> f64 { 0.20 }
 fn default_pressure_weight() -> f64 { 0.15 }
 fn default_depth_consistency_weight() -> f64 { 0.10 }
 fn default_depth_levels() -> Vec<usize> { vec![5, 10, 20] }
+fn default_depth_mode() -> String { "levels".to_string() }
+fn default_depth_bands_bps() -> Vec<f64> { vec![5.0, 10.0, 25.0] }
+fn default_imbalance_depth() -> f64 { 10.0 }
 fn default_whale_threshold() -> f64 { 3.0 }
-fn default_min_whale_size() -> f64 { 0.5 }
+fn default_min_whale_notional() -> f64 { 25_000.0 }
 fn default_delta_windows() -> Vec<u64> { vec![1000, 5000, 30000] }
+fn default_reference_price() -> String { "mid".to_string() }
+fn default_fair_value_depth() -> usize { 5 }
+fn default_ofi_weight() -> f64 { 0.15 }
+fn default_ofi_depths() -> Vec<usize> { vec![1, 5, 10] }
+fn default_ofi_window_ms() -> u64 { 5000 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ValidationConfig {
@@ -149,6 +236,10 @@ pub struct ValidationConfig {
     pub max_data_age_ms: u64,
     #[serde(default = "default_min_depth_levels")]
     pub min_depth_levels: usize,
+    #[serde(default = "default_liquidity_mode")]
+    pub liquidity_mode: String,              // Liquidity check depth: "levels", "bps" or "decay"
+    #[serde(default = "default_liquidity_depth")]
+    pub liquidity_depth: f64,                // Levels, band width or decay length (bps) for liquidity_mode
 }
 
 impl Default for ValidationConfig {
@@ -159,6 +250,8 @@ impl Default for ValidationConfig {
             min_liquidity_multiplier: default_min_liquidity_multiplier(),
             max_data_age_ms: default_max_data_age_ms(),
             min_depth_levels: default_min_depth_levels(),
+            liquidity_mode: default_liquidity_mode(),
+            liquidity_depth: default_liquidity_depth(),
         }
     }
 }
@@ -168,6 +261,212 @@ fn default_max_spread_multiplier() -> f64 { 3.0 }
 fn default_min_liquidity_multiplier() -> f64 { 0.25 }
 fn default_max_data_age_ms() -> u64 { 5000 }
 fn default_min_depth_levels() -> usize { 5 }
+fn default_liquidity_mode() -> String { "levels".to_string() }
+fn default_liquidity_depth() -> f64 { 10.0 }
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MarketDataConfig {
+    #[serde(default = "default_trade_tape_capacity")]
+    pub trade_tape_capacity: usize,
+
+    // Liquidation cascade detection
+    #[serde(default = "default_liquidation_short_window_ms")]
+    pub liquidation_short_window_ms: u64,
+    #[serde(default = "default_liquidation_baseline_window_ms")]
+    pub liquidation_baseline_window_ms: u64,
+    #[serde(default = "default_cascade_spike_multiplier")]
+    pub cascade_spike_multiplier: f64,
+    #[serde(default = "default_cascade_min_notional")]
+    pub cascade_min_notional: f64,
+    #[serde(default = "default_cascade_hold_ms")]
+    pub cascade_hold_ms: u64,
+    #[serde(default = "default_block_fading_cascades")]
+    pub block_fading_cascades: bool,          // Don't trade against an active cascade
+
+    // Iceberg / hidden-liquidity detection
+    #[serde(default = "default_iceberg_window_ms")]
+    pub iceberg_window_ms: u64,
+    #[serde(default = "default_iceberg_exec_ratio")]
+    pub iceberg_exec_ratio: f64,
+    #[serde(default = "default_iceberg_min_refills")]
+    pub iceberg_min_refills: u32,
+    #[serde(default = "default_iceberg_refill_window_ms")]
+    pub iceberg_refill_window_ms: u64,
+    #[serde(default = "default_iceberg_refill_ratio")]
+    pub iceberg_refill_ratio: f64,            // A refill restores at least this fraction of the displayed size
+    #[serde(default = "default_iceberg_alerts")]
+    pub iceberg_alerts: bool,                 // Telegram alert on each detected iceberg
+    #[serde(default = "default_iceberg_alert_cooldown_ms")]
+    pub iceberg_alert_cooldown_ms: u64,       // Report a price level at most once per cooldown
+}
+
+impl Default for MarketDataConfig {
+    fn default() -> Self {
+        Self {
+            trade_tape_capacity: default_trade_tape_capacity(),
+            liquidation_short_window_ms: default_liquidation_short_window_ms(),
+            liquidation_baseline_window_ms: default_liquidation_baseline_window_ms(),
+            cascade_spike_multiplier: default_cascade_spike_multiplier(),
+            cascade_min_notional: default_cascade_min_notional(),
+            cascade_hold_ms: default_cascade_hold_ms(),
+            block_fading_cascades: default_block_fading_cascades(),
+            iceberg_window_ms: default_iceberg_window_ms(),
+            iceberg_exec_ratio: default_iceberg_exec_ratio(),
+            iceberg_min_refills: default_iceberg_min_refills(),
+            iceberg_refill_window_ms: default_iceberg_refill_window_ms(),
+            iceberg_refill_ratio: default_iceberg_refill_ratio(),
+            iceberg_alerts: default_iceberg_alerts(),
+            iceberg_alert_cooldown_ms: default_iceberg_alert_cooldown_ms(),
+        }
+    }
+}
+
+fn default_trade_tape_capacity() -> usize { 10_000 }
+fn default_liquidation_short_window_ms() -> u64 { 5_000 }
+fn default_liquidation_baseline_window_ms() -> u64 { 300_000 }
+fn default_cascade_spike_multiplier() -> f64 { 5.0 }
+fn default_cascade_min_notional() -> f64 { 250_000.0 }
+fn default_cascade_hold_ms() -> u64 { 30_000 }
+fn default_block_fading_cascades() -> bool { true }
+fn default_iceberg_window_ms() -> u64 { 30_000 }
+fn default_iceberg_exec_ratio() -> f64 { 2.0 }
+fn default_iceberg_min_refills() -> u32 { 3 }
+fn default_iceberg_refill_window_ms() -> u64 { 1_000 }
+fn default_iceberg_refill_ratio() -> f64 { 0.8 }
+fn default_iceberg_alerts() -> bool { false }
+fn default_iceberg_alert_cooldown_ms() -> u64 { 300_000 }
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct WebSocketConfig {
+    // Per-topic dispatch queues
+    #[serde(default = "default_queue_capacity")]
+    pub queue_capacity: usize,
+    #[serde(default = "default_overflow_policy")]
+    pub overflow_policy: String,              // "block", "drop" or "coalesce"
+    #[serde(default)]
+    pub overflow_overrides: HashMap<String, String>, // Topic prefix -> policy
+
+    // Reconnect backoff
+    #[serde(default = "default_reconnect_initial_ms")]
+    pub reconnect_initial_ms: u64,
+    #[serde(default = "default_reconnect_max_ms")]
+    pub reconnect_max_ms: u64,
+    #[serde(default = "default_reconnect_multiplier")]
+    pub reconnect_multiplier: f64,
+    #[serde(default = "default_reconnect_jitter")]
+    pub reconnect_jitter: f64,                // 0.0 - 1.0
+
+    // Heartbeat and stale-data detection
+    #[serde(default = "default_ping_interval_ms")]
+    pub ping_interval_ms: u64,
+    #[serde(default = "default_pong_timeout_ms")]
+    pub pong_timeout_ms: u64,
+    #[serde(default = "default_degraded_after_ms")]
+    pub degraded_after_ms: u64,
+    #[serde(default = "default_stale_after_ms")]
+    pub stale_after_ms: u64,
+    #[serde(default = "default_resync_timeout_ms")]
+    pub resync_timeout_ms: u64,
+}
+
+impl Default for WebSocketConfig {
+    fn default() -> Self {
+        Self {
+            queue_capacity: default_queue_capacity(),
+            overflow_policy: default_overflow_policy(),
+            overflow_overrides: HashMap::new(),
+            reconnect_initial_ms: default_reconnect_initial_ms(),
+            reconnect_max_ms: default_reconnect_max_ms(),
+            reconnect_multiplier: default_reconnect_multiplier(),
+            reconnect_jitter: default_reconnect_jitter(),
+            ping_interval_ms: default_ping_interval_ms(),
+            pong_timeout_ms: default_pong_timeout_ms(),
+            degraded_after_ms: default_degraded_after_ms(),
+            stale_after_ms: default_stale_after_ms(),
+            resync_timeout_ms: default_resync_timeout_ms(),
+        }
+    }
+}
+
+fn default_queue_capacity() -> usize { 1024 }
+fn default_overflow_policy() -> String { "block".to_string() }
+fn default_reconnect_initial_ms() -> u64 { 500 }
+fn default_reconnect_max_ms() -> u64 { 30_000 }
+fn default_reconnect_multiplier() -> f64 { 2.0 }
+fn default_reconnect_jitter() -> f64 { 0.5 }
+fn default_ping_interval_ms() -> u64 { 20_000 }
+fn default_pong_timeout_ms() -> u64 { 10_000 }
+fn default_degraded_after_ms() -> u64 { 10_000 }
+fn default_stale_after_ms() -> u64 { 30_000 }
+fn default_resync_timeout_ms() -> u64 { 5_000 }
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CaptureConfig {
+    // Raw market-data capture
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_capture_dir")]
+    pub dir: String,
+    #[serde(default)]
+    pub topics: Vec<String>,                  // Topic prefixes to capture, empty = all
+    #[serde(default = "default_capture_max_file_mb")]
+    pub max_file_mb: u64,                     // Rotate after this much raw data
+    #[serde(default = "default_capture_max_file_secs")]
+    pub max_file_secs: u64,                   // ...or after this long
+    #[serde(default = "default_capture_queue_capacity")]
+    pub queue_capacity: usize,                // Frames buffered before dropping
+}
+
+impl Default for CaptureConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            dir: default_capture_dir(),
+            topics: Vec::new(),
+            max_file_mb: default_capture_max_file_mb(),
+            max_file_secs: default_capture_max_file_secs(),
+            queue_capacity: default_capture_queue_capacity(),
+        }
+    }
+}
+
+fn default_capture_dir() -> String { "captures".to_string() }
+fn default_capture_max_file_mb() -> u64 { 256 }
+fn default_capture_max_file_secs() -> u64 { 3600 }
+fn default_capture_queue_capacity() -> usize { 65_536 }
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ReplayConfig {
+    // Offline replay of capture files through the live handlers
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_capture_dir")]
+    pub path: String,                         // Capture file or directory
+    #[serde(default = "default_replay_pacing")]
+    pub pacing: String,                       // "realtime", "accelerated" or "max"
+    #[serde(default = "default_replay_speed")]
+    pub speed: f64,                           // Speed-up factor for "accelerated"
+}
+
+impl Default for ReplayConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: default_capture_dir(),
+            pacing: default_replay_pacing(),
+            speed: default_replay_speed(),
+        }
+    }
+}
+
+fn default_replay_pacing() -> String { "realtime".to_string() }
+fn default_replay_speed() -> f64 { 1.0 }
+
+/// Keys renamed when the size thresholds moved from BTC to USD notional, with their replacements
+const RENAMED_KEYS: &[(&str, &str)] = &[
+    ("risk.min_liquidity_btc", "risk.min_liquidity_usd"),
+    ("strategy.min_whale_size_btc", "strategy.min_whale_notional_usd"),
+];
 
 impl Config {
     pub fn load() -> Result<Arc<Self>> {
@@ -186,7 +485,97 @@ impl Config {
             builder = builder.set_override("bybit.api_secret", api_secret)?;
         }
 
-        let config = builder.build()?;
-        Ok(Arc::new(config.try_deserialize()?))
+        let raw = builder.build()?;
+
+        // A BTC amount can't be carried over as USD, so old configs must be updated by hand
+        for (old, new) in RENAMED_KEYS {
+            let env = format!("BOT_{}", old.replace('.', "_").to_uppercase());
+            anyhow::ensure!(
+                raw.get::<config::Value>(old).is_err() && std::env::var(&env).is_err(),
+                "{} ({}) is no longer read: set {} instead, in USD",
+                old,
+                env,
+                new
+            );
+        }
+
+        let config: Self = raw.try_deserialize()?;
+
+        let depths = config.trading.category.orderbook_depths();
+        anyhow::ensure!(
+            depths.contains(&config.performance.orderbook_depth),
+            "performance.orderbook_depth must be one of {:?} for {}, got {}",
+            depths,
+            config.trading.category.as_str(),
+            config.performance.orderbook_depth
+        );
+
+        anyhow::ensure!(
+            ReferencePrice::CONFIG_VALUES.contains(&config.trading.reference_price.as_str()),
+            "trading.reference_price must be one of {:?}, got {:?}",
+            ReferencePrice::CONFIG_VALUES,
+            config.trading.reference_price
+        );
+
+        for (key, mode) in [
+            ("strategy.depth_mode", &config.strategy.depth_mode),
+            ("validation.liquidity_mode", &config.validation.liquidity_mode),
+        ] {
+            anyhow::ensure!(
+                DepthMeasure::CONFIG_MODES.contains(&mode.as_str()),
+                "{} must be one of {:?}, got {:?}",
+                key,
+                DepthMeasure::CONFIG_MODES,
+                mode
+            );
+        }
+
+        // Sent to Bybit as slTriggerBy / tpTriggerBy, so the software exits must read the same price
+        anyhow::ensure!(
+            TriggerPrice::CONFIG_VALUES.contains(&config.risk.sltp_trigger_by.as_str()),
+            "risk.sltp_trigger_by must be one of {:?}, got {:?}",
+            TriggerPrice::CONFIG_VALUES,
+            config.risk.sltp_trigger_by
+        );
+
+        anyhow::ensure!(
+            CandleSource::CONFIG_VALUES.contains(&config.risk.atr_source.as_str()),
+            "risk.atr_source must be one of {:?}, got {:?}",
+            CandleSource::CONFIG_VALUES,
+            config.risk.atr_source
+        );
+
+        let overrides = config.websocket.overflow_overrides.iter()
+            .map(|(prefix, policy)| (format!("websocket.overflow_overrides.{}", prefix), policy));
+        for (key, policy) in std::iter::once(("websocket.overflow_policy".to_string(), &config.websocket.overflow_policy)).chain(overrides) {
+            anyhow::ensure!(
+                OverflowPolicy::CONFIG_VALUES.contains(&policy.as_str()),
+                "{} must be one of {:?}, got {:?}",
+                key,
+                OverflowPolicy::CONFIG_VALUES,
+                policy
+            );
+        }
+
+        anyhow::ensure!(
+            Pacing::CONFIG_VALUES.contains(&config.replay.pacing.as_str()),
+            "replay.pacing must be one of {:?}, got {:?}",
+            Pacing::CONFIG_VALUES,
+            config.replay.pacing
+        );
+        anyhow::ensure!(
+            config.replay.pacing != "accelerated" || config.replay.speed > 0.0,
+            "replay.speed must be above zero for accelerated pacing, got {}",
+            config.replay.speed
+        );
+
+        let windows = &config.strategy.delta_windows;
+        anyhow::ensure!(
+            windows.len() >= 2 && windows.iter().all(|&w| w > 0),
+            "strategy.delta_windows needs at least two non-zero windows (short, medium), got {:?}",
+            windows
+        );
+
+        Ok(Arc::new(config))
     }
 }
@@ -1,17 +1,40 @@
 use anyhow::{Context, Result};
+use dashmap::DashMap;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
 
 use crate::bybit::auth::BybitAuth;
-use crate::bybit::types::*;
+use crate::bybit::{Category, TickerData};
+use crate::market_data::{candles, Candle, InstrumentSpec, TickerState};
+
+pub mod ws_trade;
+
+pub use ws_trade::{WsTradeError, WsTradeSession};
+
+/// Bybit retCode for an orderLinkId that was already used
+const DUPLICATE_ORDER_LINK_ID: i64 = 110072;
+
+static NEXT_ORDER_LINK_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Client order id, so a create whose WS outcome is unknown can be retried over REST
+fn order_link_id() -> String {
+    format!("ofb-{}-{}", crate::utils::now_ms(), NEXT_ORDER_LINK_ID.fetch_add(1, Ordering::Relaxed))
+}
 
 pub struct BybitClient {
     client: Client,
     auth: Option<BybitAuth>,
     rest_url: String,
     recv_window: u64,
+    category: Category,
+    /// Trading rules by symbol, filled by `load_instrument`
+    instruments: DashMap<String, InstrumentSpec>,
+    /// Preferred order entry path; REST is used when the socket is down
+    ws_trade: Option<Arc<WsTradeSession>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -47,6 +70,17 @@ pub enum OrderType {
     Limit,
 }
 
+/// Changes to a live order; `None` fields are left untouched
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AmendRequest {
+    pub symbol: String,
+    pub order_id: String,
+    pub qty: Option<f64>,
+    pub price: Option<f64>,
+    pub stop_loss: Option<f64>,
+    pub take_profit: Option<f64>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OrderResponse {
     pub order_id: String,
@@ -87,12 +121,86 @@ impl BybitClient {
             auth,
             rest_url,
             recv_window: 5000,
+            category: Category::Linear,
+            instruments: DashMap::new(),
+            ws_trade: None,
         }
     }
 
-    pub async fn place_order(&self, request: OrderRequest) -> Result<OrderResponse> {
-        let url = format!("{}/v5/order/create", self.rest_url);
-        
+    /// Trade `category` instead of linear contracts
+    pub fn with_category(mut self, category: Category) -> Self {
+        self.category = category;
+        self
+    }
+
+    pub fn category(&self) -> Category {
+        self.category
+    }
+
+    /// Trading rules for `symbol`; falls back to `InstrumentSpec::fallback` until loaded
+    pub fn instrument(&self, symbol: &str) -> InstrumentSpec {
+        match self.instruments.get(symbol) {
+            Some(spec) => spec.clone(),
+            None => InstrumentSpec::fallback(symbol, self.category),
+        }
+    }
+
+    /// Route order entry through a WS trade session, keeping REST as fallback
+    pub fn with_ws_trade(mut self, session: Arc<WsTradeSession>) -> Self {
+        self.ws_trade = Some(session);
+        self
+    }
+
+    /// Send `op` over the trade socket
+    /// Returns `Ok(None)` when the caller should use REST: the socket is down, or the
+    /// request was sent but its outcome is unknown. Creates carry an orderLinkId, so
+    /// the REST retry cannot open a second order
+    async fn try_ws(&self, op: &str, body: &serde_json::Value) -> Result<Option<serde_json::Value>> {
+        let Some(session) = &self.ws_trade else {
+            return Ok(None);
+        };
+
+        match session.request(op, body.clone()).await {
+            Ok(data) => Ok(Some(data)),
+            Err(WsTradeError::NotConnected) => {
+                tracing::debug!("Trade socket down, sending {} over REST", op);
+                Ok(None)
+            }
+            Err(e @ (WsTradeError::Closed(_) | WsTradeError::Timeout(_))) => {
+                tracing::warn!("⚠️  {}; retrying {} over REST", e, op);
+                Ok(None)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Look up an order by its orderLinkId
+    async fn find_order_by_link_id(&self, symbol: &str, order_link_id: &str) -> Result<serde_json::Value> {
+        let url = format!("{}/v5/order/realtime", self.rest_url);
+        let params = json!({
+            "category": self.category.as_str(),
+            "symbol": symbol,
+            "orderLinkId": order_link_id,
+        });
+
+        let response = self_signed_get(&self.client, &url, &self.auth, params, self.recv_window).await?;
+        let resp_json: serde_json::Value = response.json().await?;
+
+        let ret_code = resp_json["retCode"].as_i64().unwrap_or(-1);
+        if ret_code != 0 {
+            anyhow::bail!("Order lookup failed: {:?}", resp_json["retMsg"]);
+        }
+
+        resp_json["result"]["list"].get(0).cloned()
+            .with_context(|| format!("No order with orderLinkId {}", order_link_id))
+    }
+
+    /// Bybit v5 body shared by `/v5/order/create` and `order.create`
+    /// `qty` must already be in the category's units (see `Category::order_qty`);
+    /// prices and quantities are rounded to the instrument's tick and lot step
+    pub fn build_order_body(spec: &InstrumentSpec, request: &OrderRequest) -> serde_json::Value {
+        let category = spec.category;
+
         let side_str = match request.side {
             OrderSide::Buy => "Buy",
             OrderSide::Sell => "Sell",
@@ -104,27 +212,46 @@ impl BybitClient {
         };
         
         let mut body = json!({
-            "category": "linear",
+            "category": category.as_str(),
             "symbol": request.symbol,
             "side": side_str,
             "orderType": order_type_str,
-            "qty": format!("{:.3}", request.qty),
-            "reduceOnly": request.reduce_only,
-            "closeOnTrigger": request.close_on_trigger,
+            "qty": spec.format_qty(request.qty),
         });
 
+        if category.has_positions() {
+            body["reduceOnly"] = json!(request.reduce_only);
+            body["closeOnTrigger"] = json!(request.close_on_trigger);
+        } else if request.order_type == OrderType::Market {
+            // Spot market buys are sized in the quote coin unless told otherwise
+            body["marketUnit"] = json!("baseCoin");
+        }
+
         if let Some(price) = request.price {
-            body["price"] = json!(price.to_string());
+            body["price"] = json!(spec.format_price(price));
+        }
+
+        // Spot only takes plain TP/SL prices, and only on limit orders
+        if !category.has_positions() {
+            if request.order_type == OrderType::Limit {
+                if let Some(sl) = request.stop_loss {
+                    body["stopLoss"] = json!(spec.format_price(sl));
+                }
+                if let Some(tp) = request.take_profit {
+                    body["takeProfit"] = json!(spec.format_price(tp));
+                }
+            }
+            return body;
         }
 
         // Add native SL/TP if provided
         if let Some(sl) = request.stop_loss {
-            body["stopLoss"] = json!(format!("{:.2}", sl));
+            body["stopLoss"] = json!(spec.format_price(sl));
             body["slOrderType"] = json!(request.sl_order_type.as_deref().unwrap_or("Market"));
             body["slTriggerBy"] = json!(request.sl_trigger_by.as_deref().unwrap_or("LastPrice"));
         }
         if let Some(tp) = request.take_profit {
-            body["takeProfit"] = json!(format!("{:.2}", tp));
+            body["takeProfit"] = json!(spec.format_price(tp));
             body["tpOrderType"] = json!(request.tp_order_type.as_deref().unwrap_or("Market"));
             body["tpTriggerBy"] = json!(request.tp_trigger_by.as_deref().unwrap_or("LastPrice"));
         }
@@ -132,11 +259,77 @@ impl BybitClient {
             body["tpslMode"] = json!(request.tpsl_mode.as_deref().unwrap_or("Full"));
         }
 
+        body
+    }
+
+    /// Bybit v5 body shared by `/v5/order/amend` and `order.amend`
+    pub fn build_amend_body(spec: &InstrumentSpec, request: &AmendRequest) -> serde_json::Value {
+        let mut body = json!({
+            "category": spec.category.as_str(),
+            "symbol": request.symbol,
+            "orderId": request.order_id,
+        });
+
+        if let Some(qty) = request.qty {
+            body["qty"] = json!(spec.format_qty(qty));
+        }
+        if let Some(price) = request.price {
+            body["price"] = json!(spec.format_price(price));
+        }
+        if let Some(sl) = request.stop_loss {
+            body["stopLoss"] = json!(spec.format_price(sl));
+        }
+        if let Some(tp) = request.take_profit {
+            body["takeProfit"] = json!(spec.format_price(tp));
+        }
+
+        body
+    }
+
+    pub async fn place_order(&self, request: OrderRequest) -> Result<OrderResponse> {
+        let url = format!("{}/v5/order/create", self.rest_url);
+        let spec = self.instrument(&request.symbol);
+
+        // Reject locally what Bybit would reject anyway
+        let qty = spec.round_qty(request.qty);
+        spec.check_order(qty, request.price.map(|price| spec.round_price(price)))
+            .with_context(|| format!("Order rejected for {}", request.symbol))?;
+
+        let mut body = Self::build_order_body(&spec, &request);
+        let link_id = order_link_id();
+        body["orderLinkId"] = json!(link_id);
+
+        if let Some(data) = self.try_ws("order.create", &body).await? {
+            return Ok(OrderResponse {
+                order_id: data["orderId"].as_str().unwrap_or("").to_string(),
+                symbol: request.symbol,
+                side: format!("{:?}", request.side),
+                order_type: format!("{:?}", request.order_type),
+                price: request.price.unwrap_or(0.0),
+                qty,
+                status: "Created".to_string(),
+            });
+        }
+
         let response = self_signed_post(&self.client, &url, &self.auth, body.clone(), self.recv_window).await?;
         
         let resp_json: serde_json::Value = response.json().await?;
         
         let ret_code = resp_json["retCode"].as_i64().unwrap_or(-1);
+        if ret_code == DUPLICATE_ORDER_LINK_ID {
+            // The WS attempt reached the exchange after all
+            let order = self.find_order_by_link_id(&request.symbol, &link_id).await?;
+            tracing::info!("Order {} was placed by the unacknowledged WS request", link_id);
+            return Ok(OrderResponse {
+                order_id: order["orderId"].as_str().unwrap_or("").to_string(),
+                symbol: request.symbol,
+                side: format!("{:?}", request.side),
+                order_type: format!("{:?}", request.order_type),
+                price: request.price.unwrap_or(0.0),
+                qty,
+                status: order["orderStatus"].as_str().unwrap_or("Created").to_string(),
+            });
+        }
         if ret_code != 0 {
             let error_msg = resp_json["retMsg"].as_str().unwrap_or("Unknown error");
             let error_detail = resp_json.get("result").map(|r| r.to_string()).unwrap_or_default();
@@ -158,7 +351,7 @@ impl BybitClient {
             side: format!("{:?}", request.side),
             order_type: format!("{:?}", request.order_type),
             price: request.price.unwrap_or(0.0),
-            qty: request.qty,
+            qty,
             status: "Created".to_string(),
         })
     }
@@ -167,11 +360,15 @@ impl BybitClient {
         let url = format!("{}/v5/order/cancel", self.rest_url);
         
         let body = json!({
-            "category": "linear",
+            "category": self.category.as_str(),
             "symbol": symbol,
             "orderId": order_id,
         });
 
+        if self.try_ws("order.cancel", &body).await?.is_some() {
+            return Ok(());
+        }
+
         let response = self_signed_post(&self.client, &url, &self.auth, body, self.recv_window).await?;
         
         let resp_json: serde_json::Value = response.json().await?;
@@ -184,11 +381,35 @@ impl BybitClient {
         Ok(())
     }
 
+    pub async fn amend_order(&self, request: &AmendRequest) -> Result<()> {
+        let url = format!("{}/v5/order/amend", self.rest_url);
+        let body = Self::build_amend_body(&self.instrument(&request.symbol), request);
+
+        if self.try_ws("order.amend", &body).await?.is_some() {
+            return Ok(());
+        }
+
+        let response = self_signed_post(&self.client, &url, &self.auth, body, self.recv_window).await?;
+
+        let resp_json: serde_json::Value = response.json().await?;
+
+        let ret_code = resp_json["retCode"].as_i64().unwrap_or(-1);
+        if ret_code != 0 {
+            anyhow::bail!("Amend failed: {:?}", resp_json["retMsg"]);
+        }
+
+        Ok(())
+    }
+
     pub async fn get_positions(&self, symbol: Option<&str>) -> Result<Vec<Position>> {
+        if !self.category.has_positions() {
+            anyhow::bail!("{} has no positions", self.category.as_str());
+        }
+
         let url = format!("{}/v5/position/closed-pnl", self.rest_url);
         
         let mut body = json!({
-            "category": "linear",
+            "category": self.category.as_str(),
         });
 
         if let Some(s) = symbol {
@@ -246,10 +467,14 @@ impl BybitClient {
     }
 
     pub async fn set_leverage(&self, symbol: &str, leverage: u32) -> Result<()> {
+        if !self.category.has_positions() {
+            anyhow::bail!("{} has no leverage setting", self.category.as_str());
+        }
+
         let url = format!("{}/v5/position/set-leverage", self.rest_url);
         
         let body = json!({
-            "category": "linear",
+            "category": self.category.as_str(),
             "symbol": symbol,
             "buyLeverage": leverage,
             "sellLeverage": leverage,
@@ -266,6 +491,111 @@ impl BybitClient {
 
         Ok(())
     }
+
+    /// Fetch closed klines, oldest first (public endpoint, no auth)
+    pub async fn get_klines(&self, symbol: &str, interval: &str, limit: u32) -> Result<Vec<Candle>> {
+        let url = format!("{}/v5/market/kline", self.rest_url);
+        let interval_ms = candles::interval_ms(interval)
+            .with_context(|| format!("Invalid kline interval: {}", interval))?;
+
+        let response = self.client
+            .get(&url)
+            .query(&[
+                ("category", self.category.as_str()),
+                ("symbol", symbol),
+                ("interval", interval),
+                ("limit", &limit.to_string()),
+            ])
+            .send()
+            .await
+            .context("HTTP request failed")?;
+
+        let resp_json: serde_json::Value = response.json().await?;
+
+        let ret_code = resp_json["retCode"].as_i64().unwrap_or(-1);
+        if ret_code != 0 {
+            anyhow::bail!("Get klines failed: {:?}", resp_json["retMsg"]);
+        }
+
+        // Rows are [start, open, high, low, close, volume, turnover], newest first
+        let rows: Vec<Vec<String>> = serde_json::from_value(
+            resp_json["result"]["list"].clone()
+        ).unwrap_or_default();
+
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        let mut candles: Vec<Candle> = rows.iter()
+            .filter_map(|row| {
+                let field = |i: usize| row.get(i).and_then(|v| v.parse::<f64>().ok());
+                let start_ms = row.first()?.parse::<u64>().ok()?;
+                Some(Candle {
+                    start_ms,
+                    end_ms: start_ms + interval_ms - 1,
+                    open: field(1)?,
+                    high: field(2)?,
+                    low: field(3)?,
+                    close: field(4)?,
+                    volume: field(5)?,
+                })
+            })
+            // The newest bar is still forming
+            .filter(|c| c.end_ms < now)
+            .collect();
+
+        candles.reverse();
+        Ok(candles)
+    }
+
+    /// Latest ticker for `symbol` (public endpoint, no auth), for when the stream is down
+    pub async fn get_ticker(&self, symbol: &str) -> Result<TickerState> {
+        let url = format!("{}/v5/market/tickers", self.rest_url);
+
+        let response = self.client
+            .get(&url)
+            .query(&[("category", self.category.as_str()), ("symbol", symbol)])
+            .send()
+            .await
+            .context("HTTP request failed")?;
+
+        let resp_json: serde_json::Value = response.json().await?;
+
+        let ret_code = resp_json["retCode"].as_i64().unwrap_or(-1);
+        if ret_code != 0 {
+            anyhow::bail!("Get ticker failed: {:?}", resp_json["retMsg"]);
+        }
+
+        let data: TickerData = serde_json::from_value(resp_json["result"]["list"][0].clone())
+            .with_context(|| format!("No ticker for {}", symbol))?;
+        let mut state = TickerState::default();
+        state.apply(&data);
+        Ok(state)
+    }
+
+    /// Fetch `symbol`'s trading rules and cache them for order building (public endpoint, no auth)
+    pub async fn load_instrument(&self, symbol: &str) -> Result<InstrumentSpec> {
+        let url = format!("{}/v5/market/instruments-info", self.rest_url);
+
+        let response = self.client
+            .get(&url)
+            .query(&[("category", self.category.as_str()), ("symbol", symbol)])
+            .send()
+            .await
+            .context("HTTP request failed")?;
+
+        let resp_json: serde_json::Value = response.json().await?;
+
+        let ret_code = resp_json["retCode"].as_i64().unwrap_or(-1);
+        if ret_code != 0 {
+            anyhow::bail!("Get instruments info failed: {:?}", resp_json["retMsg"]);
+        }
+
+        let spec = resp_json["result"]["list"].as_array()
+            .and_then(|list| list.iter().find(|entry| entry["symbol"] == symbol))
+            .and_then(|entry| InstrumentSpec::from_json(self.category, entry))
+            .with_context(|| format!("No {} instrument {}", self.category.as_str(), symbol))?;
+
+        self.instruments.insert(symbol.to_string(), spec.clone());
+        Ok(spec)
+    }
 }
 
 async fn self_signed_post(
@@ -318,16 +648,18 @@ async fn self_signed_get(
     params: serde_json::Value,
     recv_window: u64,
 ) -> Result<reqwest::Response> {
+    // Bybit signs GET requests over the exact query string that is sent
+    let query_string = encode_query(&params);
+    let url = format!("{}?{}", url, query_string);
+
     match auth {
         Some(auth) => {
             let timestamp = chrono::Utc::now().timestamp_millis();
-            let query_string = serde_json::to_string(&params)?;
             
             let sign = auth.generate_signature(timestamp as u64, &format!("{}{}", recv_window, &query_string));
             
             let response = client
-                .get(url)
-                .query(&serde_json::from_value::<serde_json::Value>(params)?)
+                .get(&url)
                 .header("X-BAPI-API-KEY", auth.get_api_key())
                 .header("X-BAPI-SIGN", sign)
                 .header("X-BAPI-SIGN-TYPE", "2")
@@ -341,8 +673,7 @@ async fn self_signed_get(
         }
         None => {
             let response = client
-                .get(url)
-                .query(&serde_json::from_value::<serde_json::Value>(params)?)
+                .get(&url)
                 .send()
                 .await
                 .context("HTTP request failed")?;
@@ -351,3 +682,84 @@ async fn self_signed_get(
         }
     }
 }
+
+/// `key=value&...` for a flat JSON object, strings unquoted
+fn encode_query(params: &serde_json::Value) -> String {
+    let mut query = url::form_urlencoded::Serializer::new(String::new());
+
+    if let Some(map) = params.as_object() {
+        for (key, value) in map {
+            match value {
+                serde_json::Value::String(s) => query.append_pair(key, s),
+                other => query.append_pair(key, &other.to_string()),
+            };
+        }
+    }
+
+    query.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn market_buy(qty: f64) -> OrderRequest {
+        OrderRequest {
+            symbol: "BTCUSDT".to_string(),
+            side: OrderSide::Buy,
+            order_type: OrderType::Market,
+            qty,
+            price: None,
+            reduce_only: false,
+            close_on_trigger: false,
+            stop_loss: Some(49_000.0),
+            take_profit: Some(52_000.0),
+            tpsl_mode: None,
+            tp_order_type: None,
+            sl_order_type: None,
+            tp_trigger_by: None,
+            sl_trigger_by: None,
+        }
+    }
+
+    #[test]
+    fn test_order_body_per_category() {
+        let linear = BybitClient::build_order_body(&InstrumentSpec::fallback("BTCUSDT", Category::Linear), &market_buy(0.0123));
+        assert_eq!(linear["category"], "linear");
+        assert_eq!(linear["qty"], "0.012");
+        assert_eq!(linear["reduceOnly"], false);
+        assert_eq!(linear["stopLoss"], "49000.00");
+
+        let inverse = BybitClient::build_order_body(&InstrumentSpec::fallback("BTCUSDT", Category::Inverse), &market_buy(1000.0));
+        assert_eq!(inverse["category"], "inverse");
+        assert_eq!(inverse["qty"], "1000");
+        assert_eq!(inverse["tpslMode"], "Full");
+
+        // Spot market orders are sized in base coin and carry no derivative-only fields
+        let spot = BybitClient::build_order_body(&InstrumentSpec::fallback("BTCUSDT", Category::Spot), &market_buy(0.0123));
+        assert_eq!(spot["category"], "spot");
+        assert_eq!(spot["qty"], "0.012300");
+        assert_eq!(spot["marketUnit"], "baseCoin");
+        assert!(spot.get("reduceOnly").is_none());
+        assert!(spot.get("stopLoss").is_none());
+
+        // Prices follow the instrument's tick, quantities round down to its lot step
+        let spec = InstrumentSpec { tick_size: 0.5, qty_step: 0.01, ..InstrumentSpec::fallback("BTCUSDT", Category::Linear) };
+        let body = BybitClient::build_order_body(&spec, &OrderRequest { price: Some(50_000.3), ..market_buy(0.0199) });
+        assert_eq!((body["qty"].as_str(), body["price"].as_str()), (Some("0.01"), Some("50000.5")));
+        assert_eq!(body["stopLoss"], "49000.0");
+    }
+
+    #[test]
+    fn test_encode_query_matches_signed_string() {
+        // Strings go out unquoted and escaped, other values as their JSON text
+        let query = encode_query(&serde_json::json!({
+            "category": "linear",
+            "limit": 50,
+            "orderLinkId": "ofb-1 2&x=y",
+            "symbol": "BTCUSDT",
+        }));
+        assert_eq!(query, "category=linear&limit=50&orderLinkId=ofb-1+2%26x%3Dy&symbol=BTCUSDT");
+        assert_eq!(encode_query(&serde_json::json!({})), "");
+    }
+}
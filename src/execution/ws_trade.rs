@@ -0,0 +1,339 @@
+use tokio::sync::{mpsc, oneshot};
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::Message;
+use futures::{SinkExt, StreamExt};
+use dashmap::{DashMap, DashSet};
+use serde::Deserialize;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn, error, debug};
+
+use crate::bybit::auth::BybitAuth;
+use crate::bybit::websocket::WsStream;
+
+#[derive(Debug, thiserror::Error)]
+pub enum WsTradeError {
+    /// The request never reached the socket; safe to retry over REST
+    #[error("trade socket is not connected")]
+    NotConnected,
+
+    /// The request was sent but no response arrived; the order state is unknown
+    #[error("no response for {0} within the timeout")]
+    Timeout(String),
+
+    /// The connection dropped after the request was sent; the order state is unknown
+    #[error("connection closed before {0} was acknowledged")]
+    Closed(String),
+
+    #[error("{op} rejected ({code}): {msg}")]
+    Rejected { op: String, code: i64, msg: String },
+}
+
+/// Response frame of the WS trade API
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TradeFrame {
+    req_id: Option<String>,
+    ret_code: i64,
+    #[serde(default)]
+    ret_msg: String,
+    op: String,
+    #[serde(default)]
+    data: serde_json::Value,
+}
+
+/// Authenticated session on Bybit's `/v5/trade` WebSocket API
+/// Requests are matched to responses by `reqId`
+pub struct WsTradeSession {
+    url: String,
+    auth: BybitAuth,
+    recv_window: u64,
+    request_timeout: Duration,
+    connected: AtomicBool,
+    next_req_id: AtomicU64,
+    pending: DashMap<String, oneshot::Sender<TradeFrame>>,
+    /// Requests whose frame was handed to the socket
+    written: DashSet<String>,
+    outbound_tx: mpsc::UnboundedSender<(String, String)>,
+    outbound_rx: tokio::sync::Mutex<mpsc::UnboundedReceiver<(String, String)>>,
+}
+
+impl WsTradeSession {
+    pub fn new(url: String, auth: BybitAuth) -> Self {
+        let (outbound_tx, outbound_rx) = mpsc::unbounded_channel();
+
+        Self {
+            url,
+            auth,
+            recv_window: 5000,
+            request_timeout: Duration::from_secs(5),
+            connected: AtomicBool::new(false),
+            next_req_id: AtomicU64::new(1),
+            pending: DashMap::new(),
+            written: DashSet::new(),
+            outbound_tx,
+            outbound_rx: tokio::sync::Mutex::new(outbound_rx),
+        }
+    }
+
+    /// Whether the session is authenticated and accepting requests
+    pub fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::Acquire)
+    }
+
+    /// Send `op` with a single argument object and wait for the matching response
+    /// Returns the response `data` on success
+    pub async fn request(&self, op: &str, args: serde_json::Value) -> Result<serde_json::Value, WsTradeError> {
+        if !self.is_connected() {
+            return Err(WsTradeError::NotConnected);
+        }
+
+        let req_id = format!("ofb-{}", self.next_req_id.fetch_add(1, Ordering::Relaxed));
+        let frame = serde_json::json!({
+            "reqId": req_id,
+            "header": {
+                "X-BAPI-TIMESTAMP": BybitAuth::get_timestamp().to_string(),
+                "X-BAPI-RECV-WINDOW": self.recv_window.to_string(),
+            },
+            "op": op,
+            "args": [args],
+        });
+
+        let (tx, rx) = oneshot::channel();
+        self.pending.insert(req_id.clone(), tx);
+
+        if self.outbound_tx.send((req_id.clone(), frame.to_string())).is_err() {
+            self.pending.remove(&req_id);
+            return Err(WsTradeError::NotConnected);
+        }
+
+        let result = tokio::time::timeout(self.request_timeout, rx).await;
+        // Removing the entry first keeps the writer from sending a frame we gave up on
+        self.pending.remove(&req_id);
+        let written = self.written.remove(&req_id).is_some();
+
+        let response = match result {
+            Ok(Ok(response)) => response,
+            // Frames still queued when the socket went down were never sent
+            _ if !written => return Err(WsTradeError::NotConnected),
+            Ok(Err(_)) => return Err(WsTradeError::Closed(op.to_string())),
+            Err(_) => return Err(WsTradeError::Timeout(op.to_string())),
+        };
+
+        if response.ret_code != 0 {
+            return Err(WsTradeError::Rejected {
+                op: response.op,
+                code: response.ret_code,
+                msg: response.ret_msg,
+            });
+        }
+
+        Ok(response.data)
+    }
+
+    pub async fn run(self: Arc<Self>) -> anyhow::Result<()> {
+        loop {
+            info!("Connecting to Bybit trade WebSocket: {}", self.url);
+
+            match connect_async(&self.url).await {
+                Ok((ws_stream, _)) => {
+                    if let Err(e) = self.handle_stream(ws_stream).await {
+                        error!("Trade WebSocket error: {}", e);
+                    }
+                    self.disconnect();
+                    warn!("Trade WebSocket disconnected, orders fall back to REST; reconnecting in 5s...");
+                }
+                Err(e) => {
+                    error!("Trade connection failed: {}", e);
+                }
+            }
+
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        }
+    }
+
+    async fn handle_stream(&self, ws_stream: WsStream) -> anyhow::Result<()> {
+        let (mut write, mut read) = ws_stream.split();
+
+        let (api_key, expires, signature) = self.auth.generate_ws_auth();
+        let auth_msg = serde_json::json!({
+            "op": "auth",
+            "args": [api_key, expires, signature]
+        });
+        write.send(Message::Text(auth_msg.to_string())).await?;
+
+        // Requests queued while disconnected were already failed
+        let mut outbound_rx = self.outbound_rx.lock().await;
+        while outbound_rx.try_recv().is_ok() {}
+
+        let mut ping = tokio::time::interval(Duration::from_secs(20));
+
+        loop {
+            tokio::select! {
+                msg = read.next() => {
+                    let Some(msg) = msg else { break };
+                    match msg? {
+                        Message::Text(text) => self.handle_frame(&text)?,
+                        Message::Close(_) => {
+                            warn!("Trade WebSocket closed by server");
+                            break;
+                        }
+                        _ => {}
+                    }
+                }
+                Some((req_id, frame)) = outbound_rx.recv() => {
+                    // The requester already gave up; sending now could duplicate a REST retry
+                    if !self.pending.contains_key(&req_id) {
+                        continue;
+                    }
+                    self.written.insert(req_id);
+                    write.send(Message::Text(frame)).await?;
+                }
+                _ = ping.tick() => {
+                    let ping = serde_json::json!({ "op": "ping" }).to_string();
+                    write.send(Message::Text(ping)).await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn handle_frame(&self, text: &str) -> anyhow::Result<()> {
+        let frame: TradeFrame = match serde_json::from_str(text) {
+            Ok(frame) => frame,
+            Err(e) => {
+                debug!("Unrecognised trade frame ({}): {}", e, text);
+                return Ok(());
+            }
+        };
+
+        match frame.op.as_str() {
+            "auth" => {
+                if frame.ret_code != 0 {
+                    anyhow::bail!("Trade auth rejected ({}): {}", frame.ret_code, frame.ret_msg);
+                }
+                self.connected.store(true, Ordering::Release);
+                info!("🔐 Trade WebSocket authenticated");
+            }
+            "pong" | "ping" => debug!("Trade pong received"),
+            _ => match frame.req_id.as_deref().and_then(|id| self.pending.remove(id)) {
+                Some((_, tx)) => {
+                    let _ = tx.send(frame);
+                }
+                None => debug!("Unmatched trade response: {} {:?}", frame.op, frame.req_id),
+            },
+        }
+
+        Ok(())
+    }
+
+    /// Mark the session down and fail all in-flight requests
+    fn disconnect(&self) {
+        self.connected.store(false, Ordering::Release);
+        // Dropping the senders resolves waiting requests; unsent ones report `NotConnected`
+        self.pending.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session() -> WsTradeSession {
+        WsTradeSession::new(
+            "wss://localhost/v5/trade".to_string(),
+            BybitAuth::new("key".to_string(), "secret".to_string()),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_not_connected_is_reported() {
+        let session = session();
+        let result = session.request("order.create", serde_json::json!({})).await;
+        assert!(matches!(result, Err(WsTradeError::NotConnected)));
+    }
+
+    #[tokio::test]
+    async fn test_response_matched_by_req_id() {
+        let session = Arc::new(session());
+        session.connected.store(true, Ordering::Release);
+
+        let requester = {
+            let session = session.clone();
+            tokio::spawn(async move {
+                session.request("order.create", serde_json::json!({"symbol": "BTCUSDT"})).await
+            })
+        };
+
+        // Read the outbound frame and answer it
+        let (req_id, sent) = session.outbound_rx.lock().await.recv().await.unwrap();
+        session.written.insert(req_id);
+        let sent: serde_json::Value = serde_json::from_str(&sent).unwrap();
+        assert_eq!(sent["op"], "order.create");
+        assert_eq!(sent["args"][0]["symbol"], "BTCUSDT");
+
+        let response = serde_json::json!({
+            "reqId": sent["reqId"],
+            "retCode": 0,
+            "retMsg": "OK",
+            "op": "order.create",
+            "data": {"orderId": "abc", "orderLinkId": ""},
+        });
+        session.handle_frame(&response.to_string()).unwrap();
+
+        let data = requester.await.unwrap().unwrap();
+        assert_eq!(data["orderId"], "abc");
+    }
+
+    #[tokio::test]
+    async fn test_rejection_surfaces_ret_msg() {
+        let session = Arc::new(session());
+        session.connected.store(true, Ordering::Release);
+
+        let requester = {
+            let session = session.clone();
+            tokio::spawn(async move {
+                session.request("order.cancel", serde_json::json!({})).await
+            })
+        };
+
+        let (req_id, sent) = session.outbound_rx.lock().await.recv().await.unwrap();
+        session.written.insert(req_id);
+        let sent: serde_json::Value = serde_json::from_str(&sent).unwrap();
+        let response = serde_json::json!({
+            "reqId": sent["reqId"],
+            "retCode": 110001,
+            "retMsg": "order not exists",
+            "op": "order.cancel",
+        });
+        session.handle_frame(&response.to_string()).unwrap();
+
+        match requester.await.unwrap() {
+            Err(WsTradeError::Rejected { code, .. }) => assert_eq!(code, 110001),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unsent_request_falls_back_on_disconnect() {
+        let session = Arc::new(session());
+        session.connected.store(true, Ordering::Release);
+
+        let requester = {
+            let session = session.clone();
+            tokio::spawn(async move {
+                session.request("order.create", serde_json::json!({})).await
+            })
+        };
+
+        // The socket drops while the frame is still queued
+        while session.pending.is_empty() {
+            tokio::task::yield_now().await;
+        }
+        session.disconnect();
+
+        assert!(matches!(requester.await.unwrap(), Err(WsTradeError::NotConnected)));
+    }
+}
@@ -8,6 +8,7 @@ pub mod risk;
 pub mod metrics;
 pub mod utils;
 pub mod telegram;
+pub mod capture;
 
 pub use config::Config;
 pub use bybit::{BybitWebSocket, BybitAuth};
@@ -1,16 +1,24 @@
 use anyhow::Result;
 use std::sync::Arc;
-use tracing::{info, warn, Level};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tracing::{debug, info, warn, Level};
 use tracing_subscriber::FmtSubscriber;
 
 use bybit_orderflow_bot::config::Config;
-use bybit_orderflow_bot::bybit::{BybitWebSocket, OrderbookData};
-use bybit_orderflow_bot::orderbook::{Orderbook, OrderbookValidator};
+use bybit_orderflow_bot::capture::{Pacing, Recorder, RecorderConfig, ReplayFeed, ReplaySteps};
+use bybit_orderflow_bot::bybit::{BybitEvent, BybitPrivateStream, BybitWebSocket, ConnectionState, HeartbeatPolicy, OverflowPolicy, PrivateEvent, ReconnectPolicy, SubscriptionError};
+use bybit_orderflow_bot::market_data::{candles, AggressorSide, CandleAggregator, CandleSource, CascadeConfig, LiquidationTracker, TickerTracker, TradeTape, TriggerPrice};
+use bybit_orderflow_bot::orderbook::{DepthMeasure, IcebergConfig, Orderbook, OrderbookValidator, ReferencePrice, SequenceStatus, UpdateKind};
 use bybit_orderflow_bot::TelegramNotifier;
-use bybit_orderflow_bot::strategy::{Strategy, PositionManager, TradingSide};
-use bybit_orderflow_bot::execution::BybitClient;
+use bybit_orderflow_bot::strategy::{ExitReason, Position, Strategy, PositionManager, TradingSide};
+use bybit_orderflow_bot::execution::{BybitClient, OrderRequest, OrderSide, OrderType, WsTradeSession};
 use bybit_orderflow_bot::bybit::auth::BybitAuth;
 use bybit_orderflow_bot::risk::VolatilityCalculator;
+use bybit_orderflow_bot::utils::{now_ms, Clock};
+
+/// Strategy evaluation interval
+const MONITOR_INTERVAL_MS: u64 = 5_000;
 
 #[tokio::main(flavor = "multi_thread", worker_threads = 4)]
 async fn main() -> Result<()> {
@@ -31,9 +39,26 @@ async fn main() -> Result<()> {
     info!("   Symbol: {}", config.trading.symbol);
     info!("   Testnet: {}", config.bybit.testnet);
     info!("   WebSocket URL: {}", config.bybit.ws_url);
+
+    // Replay mode: drive the handlers from capture files, fully offline and without trading.
+    // Trackers then run on the recorded exchange time and the strategy is stepped per slice of it
+    let (clock, replay_feed, replay_steps) = if config.replay.enabled {
+        let clock = Clock::event();
+        let (feed, steps) = ReplayFeed::open(&config.replay.path, Pacing::from_config(&config.replay.pacing, config.replay.speed))?
+            .with_clock(clock.clone())
+            .stepped(MONITOR_INTERVAL_MS);
+        info!("🎞️  Replay mode: {} ({:?}), network and order entry disabled", config.replay.path, feed.pacing());
+        (clock, Some(feed), Some(steps))
+    } else {
+        (Clock::wall(), None, None)
+    };
+    let replaying = replay_feed.is_some();
     
     // Initialize Telegram notifier
-    let tg = if config.telegram.enabled {
+    let tg = if replaying {
+        info!("📱 Telegram notifications disabled during replay");
+        None
+    } else if config.telegram.enabled {
         if let (Some(token), Some(chat_id)) = (&config.telegram.bot_token, &config.telegram.chat_id) {
             if !token.is_empty() {
                 info!("📱 Telegram notifications enabled");
@@ -66,34 +91,115 @@ async fn main() -> Result<()> {
     let tg_shutdown = tg.clone();
     
     // Initialize Bybit REST client for trading
-    let auth = if let (Some(api_key), Some(api_secret)) = (&config.bybit.api_key, &config.bybit.api_secret) {
+    let auth = if replaying {
+        None
+    } else if let (Some(api_key), Some(api_secret)) = (&config.bybit.api_key, &config.bybit.api_secret) {
         Some(BybitAuth::new(api_key.clone(), api_secret.clone()))
     } else {
         None
     };
     
-    let rest_client = BybitClient::new(config.bybit.rest_url.clone(), auth);
+    let private_auth = auth.clone();
+    let category = config.trading.category;
+    let mut rest_client = BybitClient::new(config.bybit.rest_url.clone(), auth.clone()).with_category(category);
     info!("✅ REST client initialized");
+
+    // Optional low-latency order entry over the WS trade API
+    let trade_ws_task = match (&auth, config.bybit.use_ws_trade) {
+        (Some(auth), true) => {
+            let session = Arc::new(WsTradeSession::new(config.bybit.trade_ws_url.clone(), auth.clone()));
+            rest_client = rest_client.with_ws_trade(session.clone());
+            info!("✅ WS trade session initialized ({}), REST as fallback", config.bybit.trade_ws_url);
+            Some(tokio::spawn(async move { session.run().await }))
+        }
+        _ => None,
+    };
     
+    // Tick size, lot step and order limits for the traded symbol. Orders are never sent on the
+    // cent-tick fallback, so with credentials a failed fetch stops startup
+    let instrument = if replaying {
+        rest_client.instrument(&config.trading.symbol)
+    } else {
+        match rest_client.load_instrument(&config.trading.symbol).await {
+            Ok(spec) => {
+                info!("✅ Instrument loaded: tick {} | lot step {} | min qty {} | min notional {}",
+                    spec.tick_size, spec.qty_step, spec.min_order_qty, spec.min_notional);
+                spec
+            }
+            Err(e) if auth.is_some() => {
+                anyhow::bail!("instrument info for {} unavailable, not trading without it: {}", config.trading.symbol, e);
+            }
+            Err(e) => {
+                warn!("⚠️  Instrument info unavailable, using default precision: {}", e);
+                rest_client.instrument(&config.trading.symbol)
+            }
+        }
+    };
+    
+    // Prices in logs and alerts follow the tick size, so sub-cent symbols keep their precision
+    let price_decimals = instrument.price_decimals();
+    let tg = tg.map(|notifier| notifier.with_price_decimals(price_decimals));
+
     // Initialize strategy with custom weights from config
     let strategy = Strategy::with_weights(
         20,                                      // min_score (was 40) - easier to trigger
         30.0,                                    // min_confidence (was 50.0)
         config.risk.max_spread_pct * 100.0,     // max_spread_pct
-        config.risk.min_liquidity_btc,          // min_liquidity_btc
+        config.risk.min_liquidity_usd,          // min_liquidity_usd
         config.risk.max_latency_ms,             // max_latency_ms
         config.strategy.imbalance_weight,
         config.strategy.volume_delta_weight,
         config.strategy.whale_weight,
         config.strategy.pressure_weight,
         config.strategy.depth_consistency_weight,
-    );
+    )
+    .with_ofi_weight(config.strategy.ofi_weight);
     info!("✅ Strategy initialized (multi-dimensional scoring)");
     
     // Initialize position manager
-    let position_manager = PositionManager::new();
+    let position_manager = PositionManager::new().with_category(category);
     info!("✅ Position manager initialized");
 
+    // Reconnect backoff shared by the public and private streams
+    let ws_config = &config.websocket;
+    let reconnect = ReconnectPolicy {
+        initial_delay: Duration::from_millis(ws_config.reconnect_initial_ms),
+        max_delay: Duration::from_millis(ws_config.reconnect_max_ms),
+        multiplier: ws_config.reconnect_multiplier,
+        jitter: ws_config.reconnect_jitter,
+    };
+    let heartbeat = HeartbeatPolicy {
+        ping_interval: Duration::from_millis(ws_config.ping_interval_ms),
+        pong_timeout: Duration::from_millis(ws_config.pong_timeout_ms),
+        degraded_after: Duration::from_millis(ws_config.degraded_after_ms),
+        stale_after: Duration::from_millis(ws_config.stale_after_ms),
+        resync_timeout: Duration::from_millis(ws_config.resync_timeout_ms),
+    };
+
+    // Private stream: fills, native SL/TP triggers and balance changes
+    let private_task = match private_auth {
+        Some(auth) => {
+            let private_stream = Arc::new(
+                BybitPrivateStream::new(config.bybit.private_ws_url.clone(), auth)
+                    .with_reconnect_policy(reconnect.clone())
+                    .with_heartbeat(heartbeat.clone()),
+            );
+            tokio::spawn(handle_private_events(
+                private_stream.events(),
+                config.clone(),
+                position_manager.clone(),
+                tg.clone(),
+                price_decimals,
+            ));
+            info!("✅ Private stream initialized ({})", config.bybit.private_ws_url);
+            Some(tokio::spawn(async move { private_stream.run().await }))
+        }
+        None => {
+            info!("🔐 No API credentials, private stream disabled");
+            None
+        }
+    };
+
     // Phase 3C: Initialize orderbook validator
     let validator_config = bybit_orderflow_bot::orderbook::validation::ValidationConfig {
         enabled: config.validation.enable_validation,
@@ -101,69 +207,322 @@ async fn main() -> Result<()> {
         min_liquidity_multiplier: config.validation.min_liquidity_multiplier,
         max_data_age_ms: config.validation.max_data_age_ms,
         min_depth_levels: config.validation.min_depth_levels,
+        liquidity_depth: DepthMeasure::from_config(&config.validation.liquidity_mode, config.validation.liquidity_depth),
     };
     let validator = OrderbookValidator::new(validator_config);
     info!("✅ Orderbook validator initialized");
 
     // Phase 3B: Initialize volatility calculator
-    let volatility_calc = VolatilityCalculator::new(config.risk.atr_period);
-    info!("✅ Volatility calculator initialized (ATR period: {})", config.risk.atr_period);
+    let mut volatility_calc = VolatilityCalculator::new(config.risk.atr_period);
+    let candle_source = CandleSource::from_config(&config.risk.atr_source);
+    let bar_interval_ms = candles::interval_ms(&config.risk.atr_interval)
+        .ok_or_else(|| anyhow::anyhow!("Invalid risk.atr_interval: {}", config.risk.atr_interval))?;
+    let candle_aggregator = Arc::new(CandleAggregator::new(bar_interval_ms));
+
+    // Warm up the ATR with recent closed bars (replays build bars from the capture)
+    if !replaying {
+        match rest_client.get_klines(&config.trading.symbol, &config.risk.atr_interval, config.risk.atr_period as u32 + 2).await {
+            Ok(bars) => {
+                for bar in &bars {
+                    volatility_calc.add_candle(bar);
+                }
+                info!("✅ ATR warmed up with {} bars", bars.len());
+            }
+            Err(e) => warn!("⚠️  ATR warmup failed, building bars from live data: {}", e),
+        }
+    }
+    info!("✅ Volatility calculator initialized (ATR period: {} x {}m bars, source: {:?})",
+        config.risk.atr_period, bar_interval_ms / 60_000, candle_source);
     
-    // Initialize orderbook
-    let orderbook = Arc::new(Orderbook::new(config.trading.symbol.clone()));
-    info!("✅ Orderbook initialized");
+    // Initialize orderbook; a separate BBO feed is pointless when the book is already depth 1
+    let depth = config.performance.orderbook_depth;
+    let bbo_feed = config.performance.bbo_feed && depth > 1;
+    let orderbook = Orderbook::new(config.trading.symbol.clone())
+        .with_clock(clock.clone())
+        .with_instrument(instrument.clone())
+        .with_min_whale_notional(config.strategy.min_whale_notional_usd)
+        .with_ofi_horizon(config.strategy.ofi_window_ms)
+        .with_iceberg_config(IcebergConfig {
+            window_ms: config.market_data.iceberg_window_ms,
+            exec_ratio: config.market_data.iceberg_exec_ratio,
+            min_refills: config.market_data.iceberg_min_refills,
+            refill_window_ms: config.market_data.iceberg_refill_window_ms,
+            refill_ratio: config.market_data.iceberg_refill_ratio,
+            alert_cooldown_ms: config.market_data.iceberg_alert_cooldown_ms,
+        });
+    let orderbook = if bbo_feed {
+        Arc::new(orderbook.with_bbo_feed())
+    } else {
+        Arc::new(orderbook)
+    };
+    info!("✅ Orderbook initialized (depth {}, BBO feed: {})", depth, bbo_feed);
+
+    // Initialize trade tape (aggressor-side order flow)
+    let trade_tape = Arc::new(TradeTape::new(config.market_data.trade_tape_capacity).with_clock(clock.clone()));
+    info!("✅ Trade tape initialized (capacity: {})", config.market_data.trade_tape_capacity);
+
+    // Initialize liquidation tracker
+    let liquidations = Arc::new(LiquidationTracker::new(CascadeConfig {
+        short_window_ms: config.market_data.liquidation_short_window_ms,
+        baseline_window_ms: config.market_data.liquidation_baseline_window_ms,
+        spike_multiplier: config.market_data.cascade_spike_multiplier,
+        min_notional: config.market_data.cascade_min_notional,
+        hold_ms: config.market_data.cascade_hold_ms,
+    }).with_clock(clock.clone()).with_instrument(instrument));
+    info!("✅ Liquidation tracker initialized");
+
+    // Initialize ticker state (mark/index price, funding, open interest)
+    let ticker = Arc::new(TickerTracker::new().with_clock(clock.clone()));
     
     // Setup WebSocket
-    let mut ws = BybitWebSocket::new(config.bybit.ws_url.clone());
+    let public_ws_url = category.public_ws_url(&config.bybit.ws_url);
+    info!("   Category: {} ({})", category.as_str(), public_ws_url);
+    let mut ws = BybitWebSocket::new(public_ws_url)
+        .with_dispatch(
+            ws_config.queue_capacity,
+            OverflowPolicy::from_config(&ws_config.overflow_policy),
+        )
+        .with_reconnect_policy(reconnect)
+        .with_heartbeat(heartbeat);
+    // Optional raw frame capture
+    let recorder = if config.capture.enabled {
+        let recorder = Arc::new(Recorder::start(RecorderConfig {
+            dir: config.capture.dir.clone().into(),
+            prefix: "public".to_string(),
+            topics: config.capture.topics.clone(),
+            max_file_bytes: config.capture.max_file_mb * 1024 * 1024,
+            max_file_age: Duration::from_secs(config.capture.max_file_secs),
+            queue_capacity: config.capture.queue_capacity,
+        })?);
+        ws = ws.with_recorder(recorder.clone());
+        info!("✅ Market-data capture enabled ({})", config.capture.dir);
+        Some(recorder)
+    } else {
+        None
+    };
+
+    for (prefix, policy) in &config.websocket.overflow_overrides {
+        ws = ws.with_overflow_override(prefix.clone(), OverflowPolicy::from_config(policy));
+    }
     
     // Subscribe to orderbook updates
     let orderbook_clone = orderbook.clone();
     let symbol = config.trading.symbol.clone();
+    let orderbook_topic = format!("orderbook.{}.{}", depth, symbol);
+    // Deltas missed while disconnected make the book stale until the restored subscription's snapshot
+    let invalidate_book = orderbook.clone();
+    ws = ws.with_snapshot_topic(orderbook_topic.clone(), Arc::new(move || invalidate_book.mark_out_of_sync()));
+    let resync = ws.resync_handle();
+    let resync_topic = orderbook_topic.clone();
+    let bbo_tolerance = Duration::from_millis(config.performance.bbo_mismatch_tolerance_ms);
     
     ws.subscribe(
-        format!("orderbook.50.{}", symbol),
-        Arc::new(move |data| {
-            // Parse orderbook data
-            match serde_json::from_value::<OrderbookData>(data) {
-                Ok(ob_data) => {
-                    let (bids, asks) = ob_data.parse_levels();
-                    
-                    // Apply snapshot or delta based on message type
-                    if ob_data.update_id > 0 {
-                        orderbook_clone.apply_snapshot(bids, asks);
-                    } else {
-                        orderbook_clone.apply_delta(bids, asks);
-                    }
-                    
-                    Ok(())
+        orderbook_topic,
+        Arc::new(move |event: BybitEvent| {
+            let BybitEvent::Orderbook(update) = event else {
+                return Ok(());
+            };
+            let kind = if update.snapshot { UpdateKind::Snapshot } else { UpdateKind::Delta };
+            let ob_data = update.data;
+
+            // Apply snapshot or delta based on envelope type, checking u/seq ordering
+            match orderbook_clone.apply_update(kind, ob_data.bids, ob_data.asks, ob_data.update_id, ob_data.seq) {
+                SequenceStatus::Gap { expected, received } => {
+                    warn!("⚠️  Orderbook gap (expected u={}, got u={}) - resyncing", expected, received);
+                    resync.request(resync_topic.clone());
                 }
-                Err(e) => {
-                    warn!("Failed to parse orderbook data: {}", e);
-                    Ok(())
+                SequenceStatus::Stale { last_update_id, update_id } => {
+                    debug!("Dropping stale orderbook frame u={} (last u={})", update_id, last_update_id);
                 }
+                SequenceStatus::Applied | SequenceStatus::AwaitingSnapshot => {}
             }
+
+            if let Some(mismatch) = orderbook_clone.check_bbo(bbo_tolerance) {
+                warn!("⚠️  BBO feed disagrees with orderbook for {:?} | BBO: {:?} | Book: {:?}",
+                    mismatch.duration, mismatch.bbo, mismatch.book);
+            }
+
+            Ok(())
+        })
+    );
+
+    // Low-latency top of book; the deep book above keeps serving depth metrics
+    if bbo_feed {
+        let bbo_book = orderbook.clone();
+        ws.subscribe(
+            format!("orderbook.1.{}", symbol),
+            Arc::new(move |event: BybitEvent| {
+                let BybitEvent::Orderbook(update) = event else {
+                    return Ok(());
+                };
+                let kind = if update.snapshot { UpdateKind::Snapshot } else { UpdateKind::Delta };
+                let data = update.data;
+
+                if let SequenceStatus::Stale { last_update_id, update_id } = bbo_book.apply_bbo(kind, data.bids, data.asks, data.update_id) {
+                    debug!("Dropping stale BBO frame u={} (last u={})", update_id, last_update_id);
+                }
+
+                if let Some(mismatch) = bbo_book.check_bbo(bbo_tolerance) {
+                    warn!("⚠️  BBO feed disagrees with orderbook for {:?} | BBO: {:?} | Book: {:?}",
+                        mismatch.duration, mismatch.bbo, mismatch.book);
+                }
+
+                Ok(())
+            })
+        );
+    }
+    
+    // Subscribe to public trades
+    let tape_clone = trade_tape.clone();
+    let trade_book = orderbook.clone();
+    let trade_candles = candle_source.uses_trades().then(|| candle_aggregator.clone());
+    ws.subscribe(
+        format!("publicTrade.{}", symbol),
+        Arc::new(move |event: BybitEvent| {
+            let BybitEvent::Trades(update) = event else {
+                return Ok(());
+            };
+
+            let trades = tape_clone.ingest(&update.data);
+            trade_book.record_trades(&trades);
+            if let Some(aggregator) = &trade_candles {
+                aggregator.ingest_trades(&update.data);
+            }
+            Ok(())
+        })
+    );
+    
+    // Subscribe to forced liquidations (derivatives only)
+    if category.has_liquidations() {
+        let liquidations_clone = liquidations.clone();
+        ws.subscribe(
+            format!("allLiquidation.{}", symbol),
+            Arc::new(move |event: BybitEvent| {
+                let BybitEvent::Liquidations(update) = event else {
+                    return Ok(());
+                };
+
+                for cascade in liquidations_clone.ingest(&update.data) {
+                    warn!("🌊 Liquidation cascade | {:?} | ${:.0} in window | Rate: ${:.0}/s (baseline ${:.0}/s)",
+                        cascade.side, cascade.notional, cascade.rate, cascade.baseline_rate);
+                }
+                Ok(())
+            })
+        );
+    }
+    
+    // Subscribe to ticker updates
+    let ticker_clone = ticker.clone();
+    ws.subscribe(
+        format!("tickers.{}", symbol),
+        Arc::new(move |event: BybitEvent| {
+            let BybitEvent::Ticker(update) = event else {
+                return Ok(());
+            };
+
+            ticker_clone.update(&update.data);
+            Ok(())
         })
     );
     
+    // Subscribe to exchange klines for ATR bars
+    if candle_source.uses_kline() {
+        let kline_candles = candle_aggregator.clone();
+        ws.subscribe(
+            format!("kline.{}.{}", config.risk.atr_interval, symbol),
+            Arc::new(move |event: BybitEvent| {
+                let BybitEvent::Klines(update) = event else {
+                    return Ok(());
+                };
+
+                for kline in &update.data {
+                    kline_candles.apply_kline(kline);
+                }
+                Ok(())
+            })
+        );
+    }
+
     info!("✅ WebSocket subscriptions configured");
+
+    // Alert on market-data outages and on topics the exchange refused after a reconnect
+    let connection_task = tokio::spawn(handle_connection_events(
+        ws.connection_events(),
+        config.clone(),
+        tg.clone(),
+    ));
+    tokio::spawn(handle_subscription_failures(
+        ws.subscription_failures(),
+        config.clone(),
+        tg.clone(),
+    ));
     
     // Start WebSocket in background
     let ws = Arc::new(ws);
     let ws_task = {
         let ws_clone = ws.clone();
         tokio::spawn(async move {
-            ws_clone.run().await
+            match replay_feed {
+                Some(feed) => {
+                    let stats = ws_clone.replay(feed).await?;
+                    info!("🎞️  Replayed {} frames ({} skipped) spanning {:.1}s",
+                        stats.frames, stats.skipped, stats.last_recv_ns.saturating_sub(stats.first_recv_ns) as f64 / 1e9);
+                    Ok(())
+                }
+                None => ws_clone.run().await,
+            }
         })
     };
     
+    // Report dispatch queue health
+    let stats_task = {
+        let ws_clone = ws.clone();
+        let recorder = recorder.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(30));
+            let mut last_lost: std::collections::HashMap<String, u64> = std::collections::HashMap::new();
+
+            loop {
+                interval.tick().await;
+                for stats in ws_clone.dispatch_stats() {
+                    let lost = stats.dropped + stats.coalesced;
+                    let previous = last_lost.insert(stats.topic.clone(), lost).unwrap_or(0);
+
+                    if lost > previous || stats.depth * 2 > stats.capacity {
+                        warn!("📮 Dispatch {} | Depth: {}/{} | Dropped: {} | Coalesced: {} | Policy: {:?}",
+                            stats.topic, stats.depth, stats.capacity, stats.dropped, stats.coalesced, stats.policy);
+                    } else {
+                        debug!("📮 Dispatch {} | Depth: {}/{} | Handled: {}",
+                            stats.topic, stats.depth, stats.capacity, stats.dispatched);
+                    }
+                }
+
+                for stats in ws_clone.decode_stats() {
+                    debug!("🧮 Decode {} | Frames: {} | Avg: {}ns | Max: {}ns",
+                        stats.topic, stats.frames, stats.avg_ns, stats.max_ns);
+                }
+
+                if let Some(recorder) = &recorder {
+                    let stats = recorder.stats();
+                    info!("📼 Capture | Written: {} | Dropped: {} | Files: {}", stats.written, stats.dropped, stats.files);
+                }
+            }
+        })
+    };
+
     // Start monitoring loop
     let monitor_task = {
+        let link = ws.clone();
         let ob = orderbook.clone();
+        let tape = trade_tape.clone();
+        let liqs = liquidations.clone();
+        let tick = ticker.clone();
+        let bars = candle_aggregator.clone();
         let cfg = config.clone();
         let tg = tg.clone();
 
         tokio::spawn(async move {
-            monitor_orderbook(ob, cfg, tg, strategy, position_manager, rest_client, validator, volatility_calc).await
+            monitor_orderbook(link, ob, tape, liqs, tick, bars, cfg, tg, strategy, position_manager, rest_client, validator, volatility_calc, clock, replay_steps).await
         })
     };
     
@@ -187,6 +546,17 @@ async fn main() -> Result<()> {
         }
     }
     
+    stats_task.abort();
+    connection_task.abort();
+
+    // Finish the current capture file so it stays readable
+    if let Some(recorder) = &recorder {
+        recorder.close();
+    }
+    for task in [private_task, trade_ws_task].into_iter().flatten() {
+        task.abort();
+    }
+
     // Send shutdown notification
     if let Some(tg) = tg_shutdown {
         let symbol = config.trading.symbol.clone();
@@ -199,8 +569,14 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn monitor_orderbook(
+    ws: Arc<BybitWebSocket>,
     orderbook: Arc<Orderbook>,
+    trade_tape: Arc<TradeTape>,
+    liquidations: Arc<LiquidationTracker>,
+    ticker: Arc<TickerTracker>,
+    candle_aggregator: Arc<CandleAggregator>,
     config: Arc<Config>,
     tg: Option<TelegramNotifier>,
     strategy: Strategy,
@@ -208,35 +584,144 @@ async fn monitor_orderbook(
     rest_client: BybitClient,
     mut validator: OrderbookValidator,
     mut volatility_calc: VolatilityCalculator,
+    clock: Clock,
+    mut replay_steps: Option<ReplaySteps>,
 ) -> Result<()> {
     let mut interval = tokio::time::interval(
-        tokio::time::Duration::from_secs(5)
+        tokio::time::Duration::from_millis(MONITOR_INTERVAL_MS)
     );
     
+    // Decimals for prices in the logs
+    let px = rest_client.instrument(&config.trading.symbol).price_decimals();
     let mut summary_counter: u32 = 0;
     let summary_interval: u32 = 60; // 5 minutes (60 * 5 seconds)
     let mut trade_cooldown: u64 = 0;
     let min_time_between_trades = config.trading.min_time_between_trades_ms / 1000 / 5;
+    let trigger_by = TriggerPrice::from_config(&config.risk.sltp_trigger_by);
+    let reference = ReferencePrice::from_config(
+        &config.trading.reference_price,
+        config.trading.fair_value_depth,
+        config.trading.fair_value_band_bps,
+    );
+    let depth_measures = DepthMeasure::list_from_config(
+        &config.strategy.depth_mode,
+        &config.strategy.depth_levels,
+        &config.strategy.depth_bands_bps,
+    );
+    let imbalance_measure = DepthMeasure::from_config(&config.strategy.depth_mode, config.strategy.imbalance_depth);
     
     loop {
-        interval.tick().await;
-        
+        // A replay hands over slices of recorded time instead of waiting on the wall clock
+        match replay_steps.as_mut() {
+            Some(steps) => {
+                if steps.next().await.is_none() {
+                    return Ok(());
+                }
+            }
+            None => {
+                interval.tick().await;
+            }
+        }
+
+        let link = ws.connection_state();
         let (bid, ask) = orderbook.best_bid_ask();
+        let book_ready = bid > 0.0 && ask > 0.0 && ask != f64::from_bits(u64::MAX);
+
+        // Check exit conditions (software monitoring) before any gate that only protects entries.
+        // Spot entries carry no native SL/TP, so there the software exit is the only one
+        let ticker_state = ticker.snapshot();
+        let has_position = position_manager.has_position().await;
+        let native_sltp = config.risk.use_native_sltp && config.trading.category.has_positions();
+        if has_position && (config.risk.keep_software_monitoring || !native_sltp) {
+            // Evaluate against the same price source the exchange uses for SL/TP;
+            // the book is only a fallback while it is live, in sync and agrees with the
+            // BBO feed, and REST covers the cases where neither stream can be trusted
+            let book_trusted = link.is_live() && book_ready && orderbook.is_synced() && !orderbook.bbo_diverged();
+            let fallback_price = if book_trusted {
+                Some(orderbook.fair_value(reference))
+            } else if !ticker_state.is_fresh(clock.now_ms(), config.risk.ticker_max_age_ms) {
+                match rest_client.get_ticker(&config.trading.symbol).await {
+                    Ok(rest_ticker) => rest_ticker.trigger_price(trigger_by),
+                    Err(e) => {
+                        warn!("⚠️  No price for exit checks: {}", e);
+                        None
+                    }
+                }
+            } else {
+                None
+            };
+            if let Some((exit_reason, exit_price)) = position_manager.check_exit_with_ticker(&ticker_state, trigger_by, clock.now_ms(), config.risk.ticker_max_age_ms, fallback_price).await {
+                let exit_reason_str = exit_reason.label();
+
+                // Log warning if native SL/TP is enabled (it should have triggered first)
+                if native_sltp {
+                    warn!("⚠️  Software monitoring triggered (Native SL/TP should have executed): {:?}", exit_reason);
+                }
+
+                info!("🔄 Exiting position: {:?} at ${:.*} ({:?})", exit_reason, px, exit_price, trigger_by);
+                
+                if let Some(pos) = position_manager.get_position_details().await {
+                    let side_str = if pos.side == TradingSide::Buy { "Buy" } else { "Sell" };
+
+                    // Close on the exchange first; the position stays tracked (and is retried
+                    // next tick) until the closing order is accepted
+                    match rest_client.place_order(exit_order(&config, &pos)).await {
+                        Ok(order) => {
+                            info!("📤 Exit order placed: {} | {} {}", order.order_id, order.side, order.qty);
+
+                            // Native SL/TP may have closed it in the meantime and already reported it
+                            if position_manager.close_position().await.is_some() {
+                                let (pnl, pnl_pct) = pos.pnl_at(exit_price);
+                                if let Some(ref notifier) = tg {
+                                    let _ = notifier.notify_position_closed(&config.trading.symbol, side_str, pos.entry_price, exit_price, pos.size, &pos.format_pnl(pnl), pnl_pct, exit_reason_str).await;
+                                }
+                            }
+                        }
+                        Err(e) => {
+                            warn!("❌ Exit order failed, position still open: {}", e);
+                            if let Some(ref notifier) = tg {
+                                let _ = notifier.notify_order_error(&config.trading.symbol, side_str, &e.to_string()).await;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        
+        // Pause entries while market data is not live
+        if !link.is_live() {
+            warn!("⚠️  Market data {:?} - skipping tick", link);
+            continue;
+        }
 
         // Skip if orderbook not initialized
-        if bid == 0.0 || ask == 0.0 || ask == f64::from_bits(u64::MAX) {
+        if !book_ready {
+            continue;
+        }
+
+        // Block trading while the book is rebuilding after a sequence gap
+        if !orderbook.is_synced() {
+            warn!("⚠️  Orderbook out of sync - waiting for snapshot, skipping tick");
             continue;
         }
 
-        // Phase 3B: Update volatility calculator
-        volatility_calc.add_price(bid, ask);
+        // Best prices and depth would come from feeds that disagree; exits were handled above
+        if orderbook.bbo_diverged() {
+            warn!("⚠️  BBO feed and orderbook disagree - skipping entries");
+            continue;
+        }
+
+        // Phase 3B: Update volatility calculator with bars closed since the last tick
+        for candle in candle_aggregator.drain_closed() {
+            volatility_calc.add_candle(&candle);
+        }
         let atr = volatility_calc.get_atr();
         let atr_pct = volatility_calc.get_atr_pct((bid + ask) / 2.0);
         let vol_regime = volatility_calc.get_volatility_regime((bid + ask) / 2.0);
 
         // Phase 2: Update orderbook metrics
         orderbook.update_metrics(
-            &config.strategy.depth_levels,
+            &depth_measures,
             config.strategy.whale_threshold_multiplier,
         );
 
@@ -248,35 +733,92 @@ async fn monitor_orderbook(
         }
 
         let mid = orderbook.mid_price();
+        // Reference price for sizing, entries and exit checks
+        let fair = orderbook.fair_value(reference);
         let spread_pct = orderbook.spread_pct() * 100.0;
-        let imbalance = orderbook.imbalance(10);
-        let liquidity = orderbook.liquidity_depth(10);
+        let imbalance = orderbook.imbalance_by(imbalance_measure);
+        let liquidity = orderbook.liquidity_notional(DepthMeasure::Levels(10));
         let latency = orderbook.latency_ms();
         let updates = orderbook.update_count();
 
         // Phase 2: Get advanced metrics (in scope to release lock before await)
-        let (volume_delta_1s, volume_delta_5s, whale_score, pressure_score, depth_consistency) = {
+        let (whale_score, pressure_score, depth_consistency, ofi_score, ofi_by_depth, spoof, layering, iceberg_score) = {
             let metrics = orderbook.get_metrics();
-            let vd1 = metrics.calculate_volume_delta(1000);
-            let vd5 = metrics.calculate_volume_delta(5000);
             let ws = metrics.whale_score(10000); // Last 10s
+            let spoof = metrics.spoof_scores(60_000);
+            let layering = metrics.layering(60_000);
+            let iceberg_score = metrics.iceberg_score();
             let ps = metrics.pressure_score();
             let dc = metrics.depth_consistency();
-            (vd1, vd5, ws, ps, dc)
+            let ofi = metrics.ofi_score(config.strategy.ofi_window_ms, &config.strategy.ofi_depths);
+            let ofi_levels = metrics.ofi_by_depth(config.strategy.ofi_window_ms, &config.strategy.ofi_depths);
+            (ws, ps, dc, ofi, ofi_levels, spoof, layering, iceberg_score)
         }; // Lock released here
 
+        // Order flow from the trade tape: CVD normalised by traded volume over the
+        // first two configured windows (short-term momentum, medium-term trend)
+        let delta_windows = &config.strategy.delta_windows;
+        let volume_delta_1s = trade_tape.cvd_ratio(delta_windows[0]);
+        let volume_delta_5s = trade_tape.cvd_ratio(delta_windows[1]);
+        let cvd_by_window = trade_tape.cvd_windows(delta_windows);
+        let cvd_windows = delta_windows.iter()
+            .map(|w| format!("{}s: {:+.3}", w / 1000, cvd_by_window[w]))
+            .collect::<Vec<_>>()
+            .join(" | ");
+
         info!(
-            "📊 {} | Bid: ${:.2} | Ask: ${:.2} | Mid: ${:.2} | Spread: {:.4}% | Imb: {:.3} | Liq: {:.2} BTC | Lat: {}ms",
-            config.trading.symbol, bid, ask, mid, spread_pct, imbalance, liquidity, latency
+            "📊 {} | Bid: ${:.*} | Ask: ${:.*} | Mid: ${:.*} | Spread: {:.4}% | Imb: {:.3} | Liq: ${:.0} | Lat: {}ms",
+            config.trading.symbol, px, bid, px, ask, px, mid, spread_pct, imbalance, liquidity, latency
         );
+        if reference != ReferencePrice::Mid {
+            info!("⚖️  Fair value ({:?}): ${:.*} | {:+.*} vs mid", reference, px, fair, px, fair - mid);
+        }
+
+        info!("🧾 CVD | {} | Session: {:+.3}", cvd_windows, trade_tape.cumulative_delta());
+
+        let (long_liqs, short_liqs) = liquidations.notional_by_side(60_000);
+        let active_cascade = liquidations.active_cascade();
+        if long_liqs + short_liqs > 0.0 {
+            info!("💥 Liquidations 60s | Longs: ${:.0} | Shorts: ${:.0} | Cascade: {:?}",
+                long_liqs, short_liqs, active_cascade.as_ref().map(|c| c.side));
+        }
 
         info!(
-            "📈 Advanced | VolΔ1s: {:.2} | VolΔ5s: {:.2} | Whale: {:.0} | Pressure: {:.0} | DepthCons: {:.2} | ATR: ${:.2} ({:.3}%) | Vol: {:?}",
-            volume_delta_1s, volume_delta_5s, whale_score, pressure_score, depth_consistency, atr, atr_pct * 100.0, vol_regime
+            "📈 Advanced | CVD{}s: {:.2} | CVD{}s: {:.2} | Whale: {:.0} | Pressure: {:.0} | DepthCons: {:.2} | ATR: ${:.*} ({:.3}%) | Vol: {:?}",
+            delta_windows[0] / 1000, volume_delta_1s, delta_windows[1] / 1000, volume_delta_5s, whale_score, pressure_score, depth_consistency, px, atr, atr_pct * 100.0, vol_regime
         );
 
+        if spoof.0 > 0.0 || spoof.1 > 0.0 {
+            info!("🎭 Spoofing 60s | Bid walls: {:.0} | Ask walls: {:.0} | Layering: {} bid / {} ask",
+                spoof.0, spoof.1, layering.0, layering.1);
+        }
+
+        for iceberg in orderbook.take_new_icebergs() {
+            let notional = |qty: f64| orderbook.notional(qty, iceberg.price);
+            info!("🧊 Iceberg {:?} @ ${:.*} | Executed: ${:.0} | Displayed: ${:.0} | Refills: {} | Hidden: ${:.0} | Signal: {:+.2}",
+                iceberg.side, px, iceberg.price, notional(iceberg.executed), notional(iceberg.displayed),
+                iceberg.refills, notional(iceberg.hidden_size), iceberg_score);
+            if config.market_data.iceberg_alerts {
+                if let Some(ref notifier) = tg {
+                    let _ = notifier.notify_iceberg(&config.trading.symbol, &iceberg, notional).await;
+                }
+            }
+        }
+
+        let ofi_levels = ofi_by_depth.iter()
+            .map(|(depth, ofi)| format!("L{}: {:+.2}", depth, ofi))
+            .collect::<Vec<_>>()
+            .join(" | ");
+        info!("🔀 OFI {}s | {} | Score: {:+.2}", config.strategy.ofi_window_ms / 1000, ofi_levels, ofi_score);
+
+        if ticker_state.is_initialized() {
+            info!("🏷️  Ticker | Last: ${:.*} | Mark: ${:.*} | Index: ${:.*} | Funding: {:.4}% | OI: {:.2}",
+                px, ticker_state.last_price, px, ticker_state.mark_price, px, ticker_state.index_price,
+                ticker_state.funding_rate * 100.0, ticker_state.open_interest);
+        }
+
         // Phase 3A: Analyze with enhanced multi-dimensional scoring
-        let signal = strategy.analyze_enhanced(
+        let mut signal = strategy.analyze_enhanced(
             imbalance,
             spread_pct,
             liquidity,
@@ -286,40 +828,88 @@ async fn monitor_orderbook(
             whale_score,
             pressure_score,
             depth_consistency,
+            ofi_score,
         );
+        strategy.apply_ticker(&mut signal, &ticker_state);
         
         // Check cooldown
-        if trade_cooldown > 0 {
-            trade_cooldown -= 1;
-        }
+        trade_cooldown = trade_cooldown.saturating_sub(1);
         
         // Check if we should trade
-        let has_position = position_manager.has_position().await;
-        
-        if !has_position && trade_cooldown == 0 && strategy.should_trade(&signal, config.trading.min_time_between_trades_ms as u64) {
-            if let Some(side) = signal.bias.side() {
+        if !has_position && trade_cooldown == 0 && strategy.should_trade(&signal) {
+            // Spot can only sell coin it holds, so short signals are not tradable there
+            let side = signal.bias.side().filter(|&side| {
+                let unshortable = side == TradingSide::Sell && !config.trading.category.has_positions();
+                if unshortable {
+                    info!("🚫 Skipping Sell signal: no shorts on spot");
+                }
+                !unshortable
+            });
+
+            // Don't fade an active liquidation cascade
+            let side = side.filter(|&side| {
+                let fades = config.market_data.block_fading_cascades
+                    && active_cascade.as_ref().is_some_and(|c| strategy.fades_cascade(side, c));
+                if fades {
+                    info!("🌊 Skipping {:?} signal: would fade active liquidation cascade", side);
+                }
+                !fades
+            });
+
+            if let Some(side) = side {
+                if config.replay.enabled {
+                    info!("🎞️  Replay SIGNAL | {:?} | Score: {} | Conf: {:.1}% - not sent to the exchange",
+                        signal.bias, signal.score, signal.confidence);
+                    // Pause as a live run would after placing the order
+                    trade_cooldown = min_time_between_trades;
+                    continue;
+                }
+
                 info!("🎯 SIGNAL | {:?} | Score: {} | Conf: {:.1}% | Momentum: {:.2} | Whale: {:.0} | Depth: {:.2}",
                     signal.bias, signal.score, signal.confidence, signal.momentum_score, signal.whale_score, signal.depth_consistency);
 
-                // Fixed $1000 USDT per order
-                let price = mid;
-                let usd_amount = 1000.0;
-                let qty = usd_amount / price; // BTC quantity for $1000
-                let qty = (qty * 1000.0).round() / 1000.0; // Round to 3 decimal places (Bybit step size)
+                // Fixed order value from config, in base coin
+                let price = fair;
+                let qty = config.risk.order_usd / price;
 
                 // Phase 3B: Apply volatility-based position sizing
                 let vol_multiplier = volatility_calc.position_size_multiplier(price);
-                let qty = qty * vol_multiplier;
-                let qty = qty.max(0.001).min(1.0); // Min 0.001, Max 1 BTC
+                let qty = (qty * vol_multiplier).min(config.risk.max_order_usd / price);
+
+                // Inverse contracts are sized in USD, linear and spot in base coin;
+                // round down to the lot step and skip, rather than enlarge, orders below the
+                // minimum size or value
+                let category = config.trading.category;
+                let spec = rest_client.instrument(&config.trading.symbol);
+                let order_qty = spec.round_qty(category.order_qty(qty, price));
+                if let Err(e) = spec.check_order(order_qty, Some(price)) {
+                    warn!("⚠️  Skipping {:?} signal: {}", side, e);
+                    continue;
+                }
 
-                info!("💰 Placing order: {} {} @ ${:.2} (qty: {:.3}, vol_adj: {:.2}x)",
+                // Walk the book for the expected fill and skip entries that would move it too far
+                let aggressor = if side == TradingSide::Buy { AggressorSide::Buy } else { AggressorSide::Sell };
+                let Some(impact) = orderbook.estimate_impact(aggressor, order_qty) else {
+                    continue;
+                };
+                info!("📐 Impact | VWAP: ${:.*} | Worst: ${:.*} | Slippage: {:.2} bps | Levels: {}",
+                    px, impact.vwap, px, impact.worst_price, impact.slippage_bps, impact.levels_consumed);
+                if impact.unfilled_qty > 0.0 || impact.slippage_bps > config.risk.max_slippage_bps {
+                    warn!("⚠️  Skipping {:?} signal: expected slippage {:.2} bps (max {:.2}), unfilled {}",
+                        side, impact.slippage_bps, config.risk.max_slippage_bps, spec.format_qty(impact.unfilled_qty));
+                    continue;
+                }
+                let entry_price = impact.vwap;
+                let qty = category.base_qty(order_qty, entry_price);
+
+                info!("💰 Placing order: {} {} @ ${:.*} (expected fill ${:.*}, qty: {:.6}, order qty: {} {}, vol_adj: {:.2}x)",
                     if side == TradingSide::Buy { "BUY" } else { "SELL" },
-                    config.trading.symbol, price, qty, vol_multiplier);
+                    config.trading.symbol, px, price, px, entry_price, qty, spec.format_qty(order_qty), category.as_str(), vol_multiplier);
 
                 // Phase 3B: Calculate risk params for native SL/TP (synchronous calculation)
                 let risk_params_for_order = bybit_orderflow_bot::risk::DynamicRiskParams::calculate(
                     &volatility_calc,
-                    price,
+                    entry_price,
                     side,
                     config.risk.base_sl_pct,
                     config.risk.base_tp_pct,
@@ -342,7 +932,7 @@ async fn monitor_orderbook(
                         bybit_orderflow_bot::execution::OrderSide::Sell
                     },
                     order_type: bybit_orderflow_bot::execution::OrderType::Market,
-                    qty,
+                    qty: order_qty,
                     price: None,
                     reduce_only: false,
                     close_on_trigger: false,
@@ -356,35 +946,37 @@ async fn monitor_orderbook(
                     sl_trigger_by: Some(config.risk.sltp_trigger_by.clone()),
                 };
 
+                let sent_at_ms = now_ms();
                 match rest_client.place_order(order_request).await {
                     Ok(order) => {
-                        info!("📤 Order placed: {} | {} @ ${:.2}", order.order_id, order.side, order.price);
+                        info!("📤 Order placed: {} | {} @ ${:.*}", order.order_id, order.side, px, order.price);
 
                         // Phase 3B: Open position with dynamic risk management
                         let risk_params = position_manager.open_position_dynamic(
                             side,
-                            price,
+                            entry_price,
                             qty,
                             &volatility_calc,
                             config.risk.base_sl_pct,
                             config.risk.base_tp_pct,
                             config.risk.volatility_multiplier,
                         ).await;
+                        position_manager.set_opened_at(sent_at_ms).await;
 
-                        info!("🛡️  Dynamic Risk | SL: {:.2}% (${:.2}) | TP: {:.2}% (${:.2}) | ATR: ${:.2} | Vol: {:?}",
+                        info!("🛡️  Dynamic Risk | SL: {:.2}% (${:.*}) | TP: {:.2}% (${:.*}) | ATR: ${:.*} | Vol: {:?}",
                             risk_params.stop_loss_pct * 100.0,
-                            risk_params.stop_loss_price,
+                            px, risk_params.stop_loss_price,
                             risk_params.take_profit_pct * 100.0,
-                            risk_params.take_profit_price,
-                            risk_params.atr_value,
+                            px, risk_params.take_profit_price,
+                            px, risk_params.atr_value,
                             risk_params.volatility_regime
                         );
 
                         // Log native SL/TP if enabled
                         if config.risk.use_native_sltp {
-                            info!("🔗 Native SL/TP | SL @ ${:.2} | TP @ ${:.2} | Type: {} | Trigger: {}",
-                                risk_params.stop_loss_price,
-                                risk_params.take_profit_price,
+                            info!("🔗 Native SL/TP | SL @ ${:.*} | TP @ ${:.*} | Type: {} | Trigger: {}",
+                                px, risk_params.stop_loss_price,
+                                px, risk_params.take_profit_price,
                                 config.risk.sltp_order_type,
                                 config.risk.sltp_trigger_by
                             );
@@ -396,11 +988,11 @@ async fn monitor_orderbook(
                         // Send notification
                         if let Some(ref notifier) = tg {
                             let side_str = if side == TradingSide::Buy { "Buy" } else { "Sell" };
-                            let _ = notifier.notify_order_placed(&config.trading.symbol, side_str, "Market", qty, price, &order.order_id).await;
+                            let _ = notifier.notify_order_placed(&config.trading.symbol, side_str, "Market", qty, entry_price, &order.order_id).await;
                             let _ = notifier.notify_position_opened(
                                 &config.trading.symbol,
                                 side_str,
-                                price,
+                                entry_price,
                                 qty,
                                 risk_params.stop_loss_price,
                                 risk_params.take_profit_price
@@ -418,56 +1010,17 @@ async fn monitor_orderbook(
             }
         }
         
-        // Check exit conditions (software monitoring)
-        if has_position && config.risk.keep_software_monitoring {
-            if let Some(exit_reason) = position_manager.check_exit(mid).await {
-                let exit_reason_str = match exit_reason {
-                    bybit_orderflow_bot::strategy::ExitReason::StopLoss => "Stop Loss",
-                    bybit_orderflow_bot::strategy::ExitReason::TakeProfit => "Take Profit",
-                    bybit_orderflow_bot::strategy::ExitReason::SignalReversal => "Signal Reversal",
-                    bybit_orderflow_bot::strategy::ExitReason::Manual => "Manual",
-                };
-
-                // Log warning if native SL/TP is enabled (it should have triggered first)
-                if config.risk.use_native_sltp {
-                    warn!("⚠️  Software monitoring triggered (Native SL/TP should have executed): {:?}", exit_reason);
-                }
-
-                info!("🔄 Exiting position: {:?} at ${:.2}", exit_reason, mid);
-                
-                // Get position details before closing
-                if let Some(pos) = position_manager.get_position_details().await {
-                    let pnl_pct = match pos.side {
-                        TradingSide::Buy => (mid - pos.entry_price) / pos.entry_price * 100.0,
-                        TradingSide::Sell => (pos.entry_price - mid) / pos.entry_price * 100.0,
-                    };
-                    let pnl = pos.size * (mid - pos.entry_price);
-                    let side_str = if pos.side == TradingSide::Buy { "Buy" } else { "Sell" };
-                    
-                    // Send notification
-                    if let Some(ref notifier) = tg {
-                        let _ = notifier.notify_position_closed(&config.trading.symbol, side_str, pos.entry_price, mid, pos.size, pnl, pnl_pct, exit_reason_str).await;
-                    }
-                }
-                
-                position_manager.close_position().await;
-            }
-        }
-        
         // Send summary every 5 minutes with wallet info
         summary_counter += 1;
         if summary_counter >= summary_interval {
             summary_counter = 0;
             if let Some(ref notifier) = tg {
                 // Get wallet balance
-                match rest_client.get_wallet().await {
-                    Ok(wallet) => {
-                        let _ = notifier.notify_wallet(wallet.total_margin_balance, wallet.total_available_balance, wallet.total_perpetual_unrealised_pnl).await;
-                    }
-                    Err(_) => {}
+                if let Ok(wallet) = rest_client.get_wallet().await {
+                    let _ = notifier.notify_wallet(wallet.total_margin_balance, wallet.total_available_balance, wallet.total_perpetual_unrealised_pnl).await;
                 }
                 let symbol = config.trading.symbol.clone();
-                if let Err(e) = notifier.notify_summary(&symbol, bid, ask, spread_pct, imbalance, liquidity, latency, updates).await {
+                if let Err(e) = notifier.notify_summary(&symbol, bid, ask, spread_pct, imbalance, liquidity, latency, updates, Some(&ticker_state)).await {
                     warn!("Failed to send summary notification: {}", e);
                 }
             }
@@ -484,8 +1037,207 @@ async fn monitor_orderbook(
         }
         
         // Alert on low liquidity
-        if liquidity < config.risk.min_liquidity_btc {
-            warn!("⚠️  Low liquidity detected: {:.2} BTC", liquidity);
+        if liquidity < config.risk.min_liquidity_usd {
+            warn!("⚠️  Low liquidity detected: ${:.0}", liquidity);
+        }
+    }
+}
+
+/// Market order that flattens `pos`: reduce-only on derivatives, a plain sell of the coin on spot
+fn exit_order(config: &Config, pos: &Position) -> OrderRequest {
+    let category = config.trading.category;
+
+    OrderRequest {
+        symbol: config.trading.symbol.clone(),
+        side: match pos.side {
+            TradingSide::Buy => OrderSide::Sell,
+            TradingSide::Sell => OrderSide::Buy,
+        },
+        order_type: OrderType::Market,
+        qty: category.order_qty(pos.size, pos.entry_price),
+        price: None,
+        reduce_only: category.has_positions(),
+        close_on_trigger: false,
+        stop_loss: None,
+        take_profit: None,
+        tpsl_mode: None,
+        tp_order_type: None,
+        sl_order_type: None,
+        tp_trigger_by: None,
+        sl_trigger_by: None,
+    }
+}
+
+async fn handle_connection_events(
+    mut events: broadcast::Receiver<ConnectionState>,
+    config: Arc<Config>,
+    tg: Option<TelegramNotifier>,
+) {
+    let symbol = config.trading.symbol.as_str();
+
+    // Start of the current outage, if any
+    let mut down_since: Option<std::time::Instant> = None;
+
+    loop {
+        let state = match events.recv().await {
+            Ok(state) => state,
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                warn!("⚠️  Connection event consumer lagged, {} events skipped", skipped);
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+
+        match state {
+            ConnectionState::Connecting { attempt } => debug!("🔌 Connecting (attempt {})", attempt),
+            ConnectionState::Degraded { reason } => warn!("⚠️  Market data degraded: {}", reason),
+            ConnectionState::Down { reason, retry_in } => {
+                warn!("🔌 Market data down: {} (retry in {:?})", reason, retry_in);
+
+                // Alert once per outage, not on every failed retry
+                if down_since.is_none() {
+                    down_since = Some(std::time::Instant::now());
+                    if let Some(tg) = &tg {
+                        if let Err(e) = tg.notify_connection_down(symbol, &reason).await {
+                            warn!("Failed to send connection alert: {}", e);
+                        }
+                    }
+                }
+            }
+            ConnectionState::Live => {
+                let Some(since) = down_since.take() else {
+                    continue;
+                };
+
+                info!("✅ Market data restored after {:?}", since.elapsed());
+                if let Some(tg) = &tg {
+                    if let Err(e) = tg.notify_connection_restored(symbol, since.elapsed().as_secs()).await {
+                        warn!("Failed to send connection alert: {}", e);
+                    }
+                }
+            }
+        }
+    }
+}
+
+async fn handle_subscription_failures(
+    mut failures: broadcast::Receiver<SubscriptionError>,
+    config: Arc<Config>,
+    tg: Option<TelegramNotifier>,
+) {
+    loop {
+        let failure = match failures.recv().await {
+            Ok(failure) => failure,
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                warn!("⚠️  Subscription failure consumer lagged, {} events skipped", skipped);
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+
+        warn!("❌ Topic dropped: {}", failure);
+        if let Some(tg) = &tg {
+            if let Err(e) = tg.notify_subscription_failed(&config.trading.symbol, &failure.to_string()).await {
+                warn!("Failed to send subscription alert: {}", e);
+            }
+        }
+    }
+}
+
+async fn handle_private_events(
+    mut events: broadcast::Receiver<PrivateEvent>,
+    config: Arc<Config>,
+    position_manager: PositionManager,
+    tg: Option<TelegramNotifier>,
+    price_decimals: usize,
+) {
+    let symbol = config.trading.symbol.as_str();
+
+    // Reason and price of the last fill that reduced the position
+    let mut last_close: Option<(ExitReason, f64)> = None;
+    // Highest position sequence seen, to drop updates delivered out of order
+    let mut last_position_seq: i64 = 0;
+
+    loop {
+        let event = match events.recv().await {
+            Ok(event) => event,
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                warn!("⚠️  Private event consumer lagged, {} events skipped", skipped);
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+
+        match event {
+            PrivateEvent::Order(orders) => {
+                for order in orders.iter().filter(|o| o.symbol == symbol) {
+                    match order.order_status.as_str() {
+                        "Rejected" => warn!("❌ Order rejected: {} | {}", order.order_id, order.reject_reason),
+                        "Cancelled" | "Deactivated" => info!("🚫 Order {}: {} ({})", order.order_status, order.order_id, order.stop_order_type),
+                        status => debug!("Order {} | {} {} {} @ {}", status, order.order_id, order.side, order.qty, order.price),
+                    }
+                }
+            }
+            PrivateEvent::Execution(fills) => {
+                for fill in fills.iter().filter(|f| f.symbol == symbol && f.exec_type == "Trade") {
+                    info!("✅ Fill | {} {} @ {} | Fee: {} | Maker: {} | {}",
+                        fill.side, fill.exec_qty, fill.exec_price, fill.exec_fee, fill.is_maker, fill.stop_order_type);
+
+                    let closed = fill.closed_size.parse::<f64>().unwrap_or(0.0);
+                    if let (true, Ok(price)) = (closed > 0.0, fill.exec_price.parse::<f64>()) {
+                        last_close = Some((ExitReason::from_stop_order_type(&fill.stop_order_type), price));
+                    }
+                }
+            }
+            PrivateEvent::Position(positions) => {
+                for update in positions.iter().filter(|p| p.symbol == symbol) {
+                    if update.seq < last_position_seq {
+                        debug!("Position | dropping out-of-order update (seq {} < {})", update.seq, last_position_seq);
+                        continue;
+                    }
+                    last_position_seq = update.seq;
+
+                    let size = update.size.parse::<f64>().unwrap_or(0.0);
+                    if size > 0.0 {
+                        debug!("Position | {} {} @ {} | UPnL: {}", update.side, update.size, update.entry_price, update.unrealised_pnl);
+                        continue;
+                    }
+
+                    // Flat on the exchange: the position was closed by SL/TP or externally
+                    let Some(pos) = position_manager.get_position_details().await else {
+                        continue;
+                    };
+                    let side_str = if pos.side == TradingSide::Buy { "Buy" } else { "Sell" };
+                    if !update.closes(side_str, pos.opened_at_ms) {
+                        debug!("Position | ignoring size-0 update (idx {}, side {:?}, updated {}) for the tracked {} position",
+                            update.position_idx, update.side, update.updated_time, side_str);
+                        continue;
+                    }
+
+                    let (exit_reason, exit_price) = last_close.take().unwrap_or_else(|| {
+                        (ExitReason::Manual, update.mark_price.parse().unwrap_or(pos.entry_price))
+                    });
+                    let (pnl, pnl_pct) = pos.pnl_at(exit_price);
+
+                    info!("🏁 Position closed on exchange: {:?} at ${:.*} | PnL: {} ({:.2}%)",
+                        exit_reason, price_decimals, exit_price, pos.format_pnl(pnl), pnl_pct);
+
+                    // Skip the report if a software exit got there first
+                    if position_manager.close_position().await.is_none() {
+                        continue;
+                    }
+
+                    if let Some(ref notifier) = tg {
+                        let _ = notifier.notify_position_closed(symbol, side_str, pos.entry_price, exit_price, pos.size, &pos.format_pnl(pnl), pnl_pct, exit_reason.label()).await;
+                    }
+                }
+            }
+            PrivateEvent::Wallet(wallets) => {
+                for wallet in &wallets {
+                    info!("💼 Wallet | {} | Equity: {} | Available: {} | UPnL: {}",
+                        wallet.account_type, wallet.total_equity, wallet.total_available_balance, wallet.total_perp_upl);
+                }
+            }
         }
     }
 }
@@ -0,0 +1,273 @@
+use parking_lot::Mutex;
+use std::collections::VecDeque;
+
+use crate::bybit::{KlineData, TradeData};
+
+/// Where closed candles come from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CandleSource {
+    /// Build bars locally from the public trade stream
+    Trades,
+    /// Use confirmed bars from the `kline` stream
+    Kline,
+    /// Use whichever closes a bar first
+    Both,
+}
+
+impl CandleSource {
+    /// Values accepted by `from_config`
+    pub const CONFIG_VALUES: &'static [&'static str] = &["trades", "kline", "both"];
+
+    pub fn from_config(value: &str) -> Self {
+        match value {
+            "kline" => CandleSource::Kline,
+            "both" => CandleSource::Both,
+            _ => CandleSource::Trades,
+        }
+    }
+
+    pub fn uses_trades(&self) -> bool {
+        matches!(self, CandleSource::Trades | CandleSource::Both)
+    }
+
+    pub fn uses_kline(&self) -> bool {
+        matches!(self, CandleSource::Kline | CandleSource::Both)
+    }
+}
+
+/// OHLC bar
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candle {
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+}
+
+impl Candle {
+    fn open_at(start_ms: u64, interval_ms: u64, price: f64, size: f64) -> Self {
+        Self {
+            start_ms,
+            end_ms: start_ms + interval_ms - 1,
+            open: price,
+            high: price,
+            low: price,
+            close: price,
+            volume: size,
+        }
+    }
+
+    /// Parse a kline entry, `None` if any price is malformed
+    pub fn from_kline(kline: &KlineData) -> Option<Self> {
+        Some(Self {
+            start_ms: kline.start,
+            end_ms: kline.end,
+            open: kline.open.parse().ok()?,
+            high: kline.high.parse().ok()?,
+            low: kline.low.parse().ok()?,
+            close: kline.close.parse().ok()?,
+            volume: kline.volume.parse().ok()?,
+        })
+    }
+}
+
+/// Convert a Bybit kline interval ("1", "5", "60", "D", ...) to milliseconds
+pub fn interval_ms(interval: &str) -> Option<u64> {
+    const MINUTE: u64 = 60_000;
+    match interval {
+        "D" => Some(1440 * MINUTE),
+        "W" => Some(7 * 1440 * MINUTE),
+        minutes => minutes.parse::<u64>().ok().filter(|m| *m > 0).map(|m| m * MINUTE),
+    }
+}
+
+struct AggregatorState {
+    /// Bar currently being built from trades
+    current: Option<Candle>,
+
+    /// Closed bars not yet drained, oldest first
+    closed: VecDeque<Candle>,
+
+    /// Start time of the last closed bar, to avoid emitting a bar twice
+    last_closed_start: Option<u64>,
+}
+
+/// Time-bar aggregator fed by trades and/or the kline stream
+pub struct CandleAggregator {
+    interval_ms: u64,
+    state: Mutex<AggregatorState>,
+}
+
+impl CandleAggregator {
+    /// Maximum number of closed bars buffered between drains
+    const MAX_PENDING: usize = 500;
+
+    pub fn new(interval_ms: u64) -> Self {
+        Self {
+            interval_ms,
+            state: Mutex::new(AggregatorState {
+                current: None,
+                closed: VecDeque::new(),
+                last_closed_start: None,
+            }),
+        }
+    }
+
+    pub fn interval_ms(&self) -> u64 {
+        self.interval_ms
+    }
+
+    /// Add a batch of public trades
+    pub fn ingest_trades(&self, batch: &[TradeData]) {
+        for trade in batch {
+            if let (Ok(price), Ok(size)) = (trade.price.parse::<f64>(), trade.size.parse::<f64>()) {
+                self.add_trade(trade.timestamp, price, size);
+            }
+        }
+    }
+
+    /// Add a single trade, closing the current bar when the trade falls past its end
+    pub fn add_trade(&self, timestamp_ms: u64, price: f64, size: f64) {
+        let start = timestamp_ms - timestamp_ms % self.interval_ms;
+        let mut state = self.state.lock();
+
+        match state.current.as_mut() {
+            Some(bar) if bar.start_ms == start => {
+                bar.high = bar.high.max(price);
+                bar.low = bar.low.min(price);
+                bar.close = price;
+                bar.volume += size;
+                return;
+            }
+            // Late trade for an already closed bar
+            Some(bar) if start < bar.start_ms => return,
+            _ => {}
+        }
+
+        if let Some(bar) = state.current.take() {
+            // Flat bars for intervals without trades keep the ATR time-consistent
+            let mut gap_start = bar.start_ms + self.interval_ms;
+            let close = bar.close;
+            Self::close_bar(&mut state, bar);
+
+            while gap_start < start && state.closed.len() < Self::MAX_PENDING {
+                Self::close_bar(&mut state, Candle::open_at(gap_start, self.interval_ms, close, 0.0));
+                gap_start += self.interval_ms;
+            }
+        }
+
+        state.current = Some(Candle::open_at(start, self.interval_ms, price, size));
+    }
+
+    /// Apply a kline entry; confirmed bars are closed
+    pub fn apply_kline(&self, kline: &KlineData) {
+        if !kline.confirm {
+            return;
+        }
+
+        if let Some(candle) = Candle::from_kline(kline) {
+            let mut state = self.state.lock();
+            Self::close_bar(&mut state, candle);
+        }
+    }
+
+    fn close_bar(state: &mut AggregatorState, candle: Candle) {
+        if state.last_closed_start.is_some_and(|last| candle.start_ms <= last) {
+            return;
+        }
+
+        state.last_closed_start = Some(candle.start_ms);
+        state.closed.push_back(candle);
+
+        while state.closed.len() > Self::MAX_PENDING {
+            state.closed.pop_front();
+        }
+    }
+
+    /// Take all bars closed since the last call, oldest first
+    pub fn drain_closed(&self) -> Vec<Candle> {
+        self.state.lock().closed.drain(..).collect()
+    }
+
+    /// Bar currently being built from trades
+    pub fn current(&self) -> Option<Candle> {
+        self.state.lock().current.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trades_build_ohlc() {
+        let agg = CandleAggregator::new(60_000);
+
+        agg.add_trade(0, 100.0, 1.0);
+        agg.add_trade(10_000, 105.0, 1.0);
+        agg.add_trade(20_000, 95.0, 1.0);
+        agg.add_trade(59_999, 101.0, 1.0);
+        assert!(agg.drain_closed().is_empty());
+
+        agg.add_trade(60_000, 102.0, 1.0);
+        let closed = agg.drain_closed();
+        assert_eq!(closed.len(), 1);
+        assert_eq!(closed[0].open, 100.0);
+        assert_eq!(closed[0].high, 105.0);
+        assert_eq!(closed[0].low, 95.0);
+        assert_eq!(closed[0].close, 101.0);
+        assert_eq!(closed[0].volume, 4.0);
+    }
+
+    #[test]
+    fn test_gaps_are_filled_with_flat_bars() {
+        let agg = CandleAggregator::new(60_000);
+
+        agg.add_trade(0, 100.0, 1.0);
+        agg.add_trade(180_000, 110.0, 1.0);
+
+        let closed = agg.drain_closed();
+        assert_eq!(closed.len(), 3);
+        assert_eq!(closed[1].high, 100.0);
+        assert_eq!(closed[2].start_ms, 120_000);
+    }
+
+    #[test]
+    fn test_kline_and_trades_do_not_duplicate() {
+        let agg = CandleAggregator::new(60_000);
+
+        agg.add_trade(0, 100.0, 1.0);
+        agg.apply_kline(&KlineData {
+            start: 0,
+            end: 59_999,
+            interval: "1".to_string(),
+            open: "100".to_string(),
+            close: "101".to_string(),
+            high: "102".to_string(),
+            low: "99".to_string(),
+            volume: "3".to_string(),
+            turnover: "300".to_string(),
+            confirm: true,
+            timestamp: 60_000,
+        });
+        agg.add_trade(60_000, 101.0, 1.0);
+
+        let closed = agg.drain_closed();
+        assert_eq!(closed.len(), 1);
+        assert_eq!(closed[0].high, 102.0);
+    }
+
+    #[test]
+    fn test_interval_parsing() {
+        assert_eq!(interval_ms("1"), Some(60_000));
+        assert_eq!(interval_ms("60"), Some(3_600_000));
+        assert_eq!(interval_ms("D"), Some(86_400_000));
+        assert_eq!(interval_ms("x"), None);
+
+        let sources: Vec<_> = CandleSource::CONFIG_VALUES.iter().map(|value| CandleSource::from_config(value)).collect();
+        assert_eq!(sources, vec![CandleSource::Trades, CandleSource::Kline, CandleSource::Both]);
+    }
+}
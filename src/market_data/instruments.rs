@@ -0,0 +1,211 @@
+use serde_json::Value;
+
+use crate::bybit::Category;
+
+/// Order rejected locally because it breaks the instrument's trading rules
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum SpecViolation {
+    #[error("qty {qty} is below the minimum order qty {min}")]
+    QtyTooSmall { qty: f64, min: f64 },
+    #[error("qty {qty} is above the maximum order qty {max}")]
+    QtyTooLarge { qty: f64, max: f64 },
+    #[error("order value {notional:.4} is below the minimum notional {min}")]
+    NotionalTooSmall { notional: f64, min: f64 },
+    #[error("price {price} is outside the allowed range {min}..{max}")]
+    PriceOutOfRange { price: f64, min: f64, max: f64 },
+}
+
+/// Trading rules of one symbol from `/v5/market/instruments-info`
+/// Quantities are in the category's order units (see `Category::order_qty`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstrumentSpec {
+    pub symbol: String,
+    pub category: Category,
+    pub tick_size: f64,
+    pub qty_step: f64,
+    pub min_order_qty: f64,
+    pub max_order_qty: f64,
+    /// USD per contract for inverse; linear and spot quantities are already in the priced unit
+    pub contract_size: f64,
+    /// Minimum order value in the quote coin; 0 where Bybit sets none (inverse)
+    pub min_notional: f64,
+    pub min_price: f64,
+    pub max_price: f64,
+}
+
+impl InstrumentSpec {
+    /// Rules assumed when instrument metadata is unavailable (replays, failed fetch)
+    /// Matches the formatting used before metadata was fetched: cent prices, category lot steps
+    pub fn fallback(symbol: &str, category: Category) -> Self {
+        let qty_step = match category {
+            Category::Linear => 0.001,
+            Category::Inverse => 1.0,
+            Category::Spot => 0.000001,
+        };
+        Self {
+            symbol: symbol.to_string(),
+            category,
+            tick_size: 0.01,
+            qty_step,
+            min_order_qty: qty_step,
+            max_order_qty: f64::MAX,
+            contract_size: 1.0,
+            min_notional: 0.0,
+            min_price: 0.0,
+            max_price: f64::MAX,
+        }
+    }
+
+    /// Parse one entry of the instruments-info `list`
+    /// Spot has no `qtyStep` (its step is `basePrecision`) and names the minimum value `minOrderAmt`
+    pub fn from_json(category: Category, entry: &Value) -> Option<Self> {
+        let num = |value: &Value| value.as_str().and_then(|s| s.parse::<f64>().ok());
+        let price_filter = &entry["priceFilter"];
+        let lot = &entry["lotSizeFilter"];
+
+        let qty_step = num(&lot["qtyStep"]).or_else(|| num(&lot["basePrecision"]))?;
+        Some(Self {
+            symbol: entry["symbol"].as_str()?.to_string(),
+            category,
+            tick_size: num(&price_filter["tickSize"])?,
+            qty_step,
+            min_order_qty: num(&lot["minOrderQty"]).unwrap_or(qty_step),
+            max_order_qty: num(&lot["maxOrderQty"]).unwrap_or(f64::MAX),
+            contract_size: num(&entry["contractSize"]).unwrap_or(1.0),
+            min_notional: num(&lot["minNotionalValue"]).or_else(|| num(&lot["minOrderAmt"])).unwrap_or(0.0),
+            min_price: num(&price_filter["minPrice"]).unwrap_or(0.0),
+            max_price: num(&price_filter["maxPrice"]).unwrap_or(f64::MAX),
+        })
+    }
+
+    /// Nearest valid price
+    pub fn round_price(&self, price: f64) -> f64 {
+        round_to_step(price, self.tick_size, f64::round)
+    }
+
+    /// Largest valid quantity not above `qty`
+    pub fn round_qty(&self, qty: f64) -> f64 {
+        round_to_step(qty, self.qty_step, f64::floor)
+    }
+
+    /// Price as sent to Bybit, rounded to the tick
+    pub fn format_price(&self, price: f64) -> String {
+        format!("{:.*}", step_decimals(self.tick_size), self.round_price(price))
+    }
+
+    /// Decimals needed to show a price on the tick grid
+    pub fn price_decimals(&self) -> usize {
+        step_decimals(self.tick_size)
+    }
+
+    /// Quantity as sent to Bybit, rounded down to the lot step
+    pub fn format_qty(&self, qty: f64) -> String {
+        format!("{:.*}", step_decimals(self.qty_step), self.round_qty(qty))
+    }
+
+    /// Order value in the quote coin; inverse quantities already are USD
+    pub fn notional(&self, qty: f64, price: f64) -> f64 {
+        match self.category {
+            Category::Inverse => qty * self.contract_size,
+            Category::Linear | Category::Spot => qty * price,
+        }
+    }
+
+    /// Check an already rounded order against the instrument's limits
+    /// `price` is the limit or reference price; without one only the quantity limits are checked
+    pub fn check_order(&self, qty: f64, price: Option<f64>) -> Result<(), SpecViolation> {
+        // Tolerate float noise left over from rounding to the step
+        let epsilon = self.qty_step * 1e-6;
+
+        if qty + epsilon < self.min_order_qty {
+            return Err(SpecViolation::QtyTooSmall { qty, min: self.min_order_qty });
+        }
+        if qty - epsilon > self.max_order_qty {
+            return Err(SpecViolation::QtyTooLarge { qty, max: self.max_order_qty });
+        }
+
+        let Some(price) = price else {
+            return Ok(());
+        };
+
+        let notional = self.notional(qty, price);
+        if notional < self.min_notional {
+            return Err(SpecViolation::NotionalTooSmall { notional, min: self.min_notional });
+        }
+
+        if price < self.min_price || price > self.max_price {
+            return Err(SpecViolation::PriceOutOfRange { price, min: self.min_price, max: self.max_price });
+        }
+
+        Ok(())
+    }
+}
+
+fn round_to_step(value: f64, step: f64, round: fn(f64) -> f64) -> f64 {
+    if step <= 0.0 {
+        return value;
+    }
+    // Nudge by a fraction of a step so 0.3 / 0.1 = 2.9999999999999996 floors to 3
+    let steps = round(value / step + 1e-9);
+    let decimals = step_decimals(step) as i32;
+    let scale = 10f64.powi(decimals);
+    (steps * step * scale).round() / scale
+}
+
+/// Decimal places needed to print multiples of `step` exactly
+fn step_decimals(step: f64) -> usize {
+    (0..10)
+        .find(|&d| {
+            let scaled = step * 10f64.powi(d as i32);
+            (scaled - scaled.round()).abs() < 1e-9
+        })
+        .unwrap_or(10)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parses_and_rounds_per_category() {
+        let linear = InstrumentSpec::from_json(Category::Linear, &json!({
+            "symbol": "ETHUSDT",
+            "priceFilter": { "minPrice": "0.05", "maxPrice": "99999.90", "tickSize": "0.05" },
+            "lotSizeFilter": { "maxOrderQty": "7240.00", "minOrderQty": "0.01", "qtyStep": "0.01", "minNotionalValue": "5" }
+        })).unwrap();
+
+        assert_eq!(linear.format_price(2500.123), "2500.10");
+        assert_eq!(linear.format_price(2500.08), "2500.10");
+        assert_eq!(linear.format_qty(0.3), "0.30");
+        assert_eq!(linear.format_qty(1.239), "1.23");
+        assert_eq!(linear.check_order(0.01, Some(2500.0)), Ok(()));
+        assert_eq!(linear.check_order(0.001, None), Err(SpecViolation::QtyTooSmall { qty: 0.001, min: 0.01 }));
+        assert!(matches!(linear.check_order(0.01, Some(100.0)), Err(SpecViolation::NotionalTooSmall { .. })));
+        // Sizes that round down to nothing are rejected, not bumped up to the minimum
+        assert_eq!(linear.check_order(linear.round_qty(0.0099), Some(2500.0)), Err(SpecViolation::QtyTooSmall { qty: 0.0, min: 0.01 }));
+
+        // Sub-cent linear: prices keep the tick's decimals, value is qty × price per 1000 PEPE
+        let pepe = InstrumentSpec::from_json(Category::Linear, &json!({
+            "symbol": "1000PEPEUSDT",
+            "priceFilter": { "tickSize": "0.0000001" },
+            "lotSizeFilter": { "qtyStep": "100", "minOrderQty": "100", "minNotionalValue": "5" }
+        })).unwrap();
+        assert_eq!(pepe.price_decimals(), 7);
+        assert_eq!(pepe.notional(10_000.0, 0.0123456), 123.456);
+
+        // Spot: step from basePrecision, minimum value from minOrderAmt
+        let spot = InstrumentSpec::from_json(Category::Spot, &json!({
+            "symbol": "BTCUSDT",
+            "priceFilter": { "tickSize": "0.01" },
+            "lotSizeFilter": { "basePrecision": "0.000001", "minOrderQty": "0.000048", "maxOrderQty": "71.73956243", "minOrderAmt": "1" }
+        })).unwrap();
+        assert_eq!(spot.format_qty(0.0123456789), "0.012345");
+        assert_eq!(spot.min_notional, 1.0);
+
+        // Inverse sizes are USD contracts, so the order value is the quantity
+        let inverse = InstrumentSpec::fallback("BTCUSD", Category::Inverse);
+        assert_eq!(inverse.format_qty(1000.7), "1000");
+        assert_eq!(inverse.notional(1000.0, 50_000.0), 1000.0);
+    }
+}
@@ -0,0 +1,299 @@
+use parking_lot::RwLock;
+use std::collections::VecDeque;
+
+use crate::bybit::AllLiquidationData;
+use crate::utils::Clock;
+use super::InstrumentSpec;
+
+/// Side of the position that was force-closed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LiquidatedSide {
+    /// Long liquidated (forced sell, pushes price down)
+    Long,
+    /// Short liquidated (forced buy, pushes price up)
+    Short,
+}
+
+/// Forced liquidation from the exchange
+#[derive(Debug, Clone)]
+pub struct Liquidation {
+    pub timestamp_ms: u64,
+    pub price: f64,
+    pub size: f64,
+    pub notional: f64,
+    pub side: LiquidatedSide,
+}
+
+/// Liquidation rate spike on one side of the market
+#[derive(Debug, Clone)]
+pub struct CascadeEvent {
+    pub side: LiquidatedSide,
+    /// Liquidated notional per second over the short window
+    pub rate: f64,
+    /// Liquidated notional per second over the baseline window
+    pub baseline_rate: f64,
+    /// Liquidated notional in the short window
+    pub notional: f64,
+    pub detected_at_ms: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct CascadeConfig {
+    /// Window used to measure the current liquidation rate
+    pub short_window_ms: u64,
+
+    /// Window used to measure the normal liquidation rate
+    pub baseline_window_ms: u64,
+
+    /// Short rate must exceed baseline rate by this factor
+    pub spike_multiplier: f64,
+
+    /// Minimum notional in the short window to call a cascade
+    pub min_notional: f64,
+
+    /// How long a cascade stays active after the last spike
+    pub hold_ms: u64,
+}
+
+impl Default for CascadeConfig {
+    fn default() -> Self {
+        Self {
+            short_window_ms: 5_000,
+            baseline_window_ms: 300_000,
+            spike_multiplier: 5.0,
+            min_notional: 250_000.0,
+            hold_ms: 30_000,
+        }
+    }
+}
+
+/// Rolling aggregation of forced liquidations with cascade detection
+pub struct LiquidationTracker {
+    /// Liquidations within the baseline window, oldest first
+    events: RwLock<VecDeque<Liquidation>>,
+
+    /// Most recent cascade per side (long, short)
+    cascades: RwLock<(Option<CascadeEvent>, Option<CascadeEvent>)>,
+
+    config: CascadeConfig,
+
+    /// "Now" for the rolling windows and cascade hold
+    clock: Clock,
+
+    /// Contract rules for valuing sizes; without them sizes are taken as base coin
+    instrument: Option<InstrumentSpec>,
+}
+
+impl LiquidationTracker {
+    pub fn new(config: CascadeConfig) -> Self {
+        Self {
+            events: RwLock::new(VecDeque::new()),
+            cascades: RwLock::new((None, None)),
+            config,
+            clock: Clock::wall(),
+            instrument: None,
+        }
+    }
+
+    /// Value liquidations through `spec`, so inverse sizes (USD contracts) are not multiplied by price
+    pub fn with_instrument(mut self, spec: InstrumentSpec) -> Self {
+        self.instrument = Some(spec);
+        self
+    }
+
+    /// Measure windows against `clock` instead of the wall clock (replays)
+    pub fn with_clock(mut self, clock: Clock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Parse an `allLiquidation` batch
+    /// Returns any cascades that started with this batch
+    pub fn ingest(&self, batch: &[AllLiquidationData]) -> Vec<CascadeEvent> {
+        batch.iter()
+            .filter_map(|data| {
+                let price = data.price.parse::<f64>().ok()?;
+                let size = data.size.parse::<f64>().ok()?;
+                let side = match data.side.as_str() {
+                    "Buy" => LiquidatedSide::Long,
+                    "Sell" => LiquidatedSide::Short,
+                    _ => return None,
+                };
+
+                self.record(Liquidation {
+                    timestamp_ms: data.timestamp,
+                    price,
+                    size,
+                    notional: self.instrument.as_ref().map_or(price * size, |spec| spec.notional(size, price)),
+                    side,
+                })
+            })
+            .collect()
+    }
+
+    /// Record a liquidation and check for a cascade on its side
+    /// Returns an event only when a new cascade starts
+    pub fn record(&self, liquidation: Liquidation) -> Option<CascadeEvent> {
+        let side = liquidation.side;
+        let now = self.clock.now_ms();
+
+        {
+            let mut events = self.events.write();
+            events.push_back(liquidation);
+
+            let cutoff = now.saturating_sub(self.config.baseline_window_ms);
+            while events.front().is_some_and(|e| e.timestamp_ms < cutoff) {
+                events.pop_front();
+            }
+        }
+
+        let notional = self.notional(self.config.short_window_ms, side);
+        let rate = notional / (self.config.short_window_ms as f64 / 1000.0);
+        let baseline_rate = self.notional(self.config.baseline_window_ms, side)
+            / (self.config.baseline_window_ms as f64 / 1000.0);
+
+        if notional < self.config.min_notional || rate < baseline_rate * self.config.spike_multiplier {
+            return None;
+        }
+
+        let event = CascadeEvent {
+            side,
+            rate,
+            baseline_rate,
+            notional,
+            detected_at_ms: now,
+        };
+
+        let mut cascades = self.cascades.write();
+        let slot = match side {
+            LiquidatedSide::Long => &mut cascades.0,
+            LiquidatedSide::Short => &mut cascades.1,
+        };
+
+        let already_active = slot.as_ref()
+            .is_some_and(|c| now.saturating_sub(c.detected_at_ms) <= self.config.hold_ms);
+        *slot = Some(event.clone());
+
+        if already_active { None } else { Some(event) }
+    }
+
+    /// Liquidated notional on one side over the last `window_ms`
+    pub fn notional(&self, window_ms: u64, side: LiquidatedSide) -> f64 {
+        let cutoff = self.clock.now_ms().saturating_sub(window_ms);
+        self.events.read().iter()
+            .rev()
+            .take_while(|e| e.timestamp_ms >= cutoff)
+            .filter(|e| e.side == side)
+            .map(|e| e.notional)
+            .sum()
+    }
+
+    /// Liquidated notional (longs, shorts) over the last `window_ms`
+    pub fn notional_by_side(&self, window_ms: u64) -> (f64, f64) {
+        (
+            self.notional(window_ms, LiquidatedSide::Long),
+            self.notional(window_ms, LiquidatedSide::Short),
+        )
+    }
+
+    /// Liquidation imbalance (-1.0 to 1.0)
+    /// Positive when shorts are being liquidated (forced buying), negative for longs
+    pub fn imbalance(&self, window_ms: u64) -> f64 {
+        let (longs, shorts) = self.notional_by_side(window_ms);
+        let total = longs + shorts;
+        if total > 0.0 {
+            (shorts - longs) / total
+        } else {
+            0.0
+        }
+    }
+
+    /// Currently active cascade, the larger one if both sides are cascading
+    pub fn active_cascade(&self) -> Option<CascadeEvent> {
+        let now = self.clock.now_ms();
+        let cascades = self.cascades.read();
+
+        [&cascades.0, &cascades.1].into_iter()
+            .flatten()
+            .filter(|c| now.saturating_sub(c.detected_at_ms) <= self.config.hold_ms)
+            .max_by(|a, b| a.rate.total_cmp(&b.rate))
+            .cloned()
+    }
+}
+
+impl Default for LiquidationTracker {
+    fn default() -> Self {
+        Self::new(CascadeConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bybit::Category;
+    use crate::utils::now_ms;
+
+    fn liquidation(side: &str, price: &str, size: &str, ts: u64) -> AllLiquidationData {
+        AllLiquidationData {
+            timestamp: ts,
+            symbol: "BTCUSDT".to_string(),
+            side: side.to_string(),
+            size: size.to_string(),
+            price: price.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_notional_by_side() {
+        let tracker = LiquidationTracker::default();
+        let now = now_ms();
+
+        tracker.ingest(&[
+            liquidation("Buy", "50000", "1.0", now),
+            liquidation("Sell", "50000", "0.5", now),
+        ]);
+
+        let (longs, shorts) = tracker.notional_by_side(5000);
+        assert_eq!(longs, 50000.0);
+        assert_eq!(shorts, 25000.0);
+        assert!(tracker.imbalance(5000) < 0.0);
+    }
+
+    #[test]
+    fn test_inverse_sizes_are_usd() {
+        let tracker = LiquidationTracker::default()
+            .with_instrument(InstrumentSpec::fallback("BTCUSD", Category::Inverse));
+        let now = now_ms();
+
+        tracker.ingest(&[liquidation("Buy", "50000", "12000", now)]);
+
+        assert_eq!(tracker.notional_by_side(5000), (12_000.0, 0.0));
+    }
+
+    #[test]
+    fn test_cascade_detected_once() {
+        let tracker = LiquidationTracker::new(CascadeConfig {
+            min_notional: 100_000.0,
+            ..CascadeConfig::default()
+        });
+        let now = now_ms();
+
+        // Background flow well below the threshold
+        let events = tracker.ingest(&[liquidation("Buy", "50000", "0.1", now - 200_000)]);
+        assert!(events.is_empty());
+
+        // Burst of long liquidations
+        let events = tracker.ingest(&[
+            liquidation("Buy", "50000", "2.0", now),
+            liquidation("Buy", "49900", "3.0", now),
+        ]);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].side, LiquidatedSide::Long);
+
+        // Still the same cascade
+        let events = tracker.ingest(&[liquidation("Buy", "49800", "1.0", now)]);
+        assert!(events.is_empty());
+
+        assert_eq!(tracker.active_cascade().unwrap().side, LiquidatedSide::Long);
+    }
+}
@@ -1 +1,11 @@
-// Market data module - to be implemented in Phase 2
+pub mod trades;
+pub mod liquidations;
+pub mod ticker;
+pub mod candles;
+pub mod instruments;
+
+pub use trades::{Trade, TradeTape, AggressorSide};
+pub use liquidations::{Liquidation, LiquidationTracker, LiquidatedSide, CascadeEvent, CascadeConfig};
+pub use ticker::{TickerState, TickerTracker, TriggerPrice};
+pub use candles::{Candle, CandleAggregator, CandleSource};
+pub use instruments::{InstrumentSpec, SpecViolation};
@@ -0,0 +1,212 @@
+use parking_lot::RwLock;
+
+use crate::bybit::TickerData;
+use crate::utils::{now_ms, Clock};
+
+/// Price source used by the exchange to trigger SL/TP orders
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerPrice {
+    LastPrice,
+    MarkPrice,
+    IndexPrice,
+}
+
+impl TriggerPrice {
+    /// Values accepted by `from_config`, spelled as Bybit's `triggerBy`
+    pub const CONFIG_VALUES: &'static [&'static str] = &["LastPrice", "MarkPrice", "IndexPrice"];
+
+    /// Parse Bybit's `triggerBy` value, defaulting to LastPrice
+    pub fn from_config(value: &str) -> Self {
+        match value {
+            "MarkPrice" => TriggerPrice::MarkPrice,
+            "IndexPrice" => TriggerPrice::IndexPrice,
+            _ => TriggerPrice::LastPrice,
+        }
+    }
+}
+
+/// Latest ticker values for a symbol
+#[derive(Debug, Clone, Default)]
+pub struct TickerState {
+    pub symbol: String,
+    pub last_price: f64,
+    pub mark_price: f64,
+    pub index_price: f64,
+    pub funding_rate: f64,
+    pub next_funding_time_ms: u64,
+    pub open_interest: f64,
+    pub open_interest_value: f64,
+    pub high_24h: f64,
+    pub low_24h: f64,
+    pub volume_24h: f64,
+    pub turnover_24h: f64,
+    pub price_change_24h_pct: f64,
+    pub updated_at_ms: u64,
+}
+
+impl TickerState {
+    /// Merge a snapshot or delta into the state (absent fields are kept)
+    pub fn apply(&mut self, data: &TickerData) {
+        fn merge<T: std::str::FromStr>(target: &mut T, value: &Option<String>) {
+            if let Some(parsed) = value.as_deref().and_then(|v| v.parse().ok()) {
+                *target = parsed;
+            }
+        }
+
+        if !data.symbol.is_empty() {
+            self.symbol = data.symbol.clone();
+        }
+        merge(&mut self.last_price, &data.last_price);
+        merge(&mut self.mark_price, &data.mark_price);
+        merge(&mut self.index_price, &data.index_price);
+        merge(&mut self.funding_rate, &data.funding_rate);
+        merge(&mut self.next_funding_time_ms, &data.next_funding_time);
+        merge(&mut self.open_interest, &data.open_interest);
+        merge(&mut self.open_interest_value, &data.open_interest_value);
+        merge(&mut self.high_24h, &data.high_price_24h);
+        merge(&mut self.low_24h, &data.low_price_24h);
+        merge(&mut self.volume_24h, &data.volume_24h);
+        merge(&mut self.turnover_24h, &data.turnover_24h);
+        merge(&mut self.price_change_24h_pct, &data.price_24h_pcnt);
+
+        self.updated_at_ms = now_ms();
+    }
+
+    /// Whether a ticker snapshot has been received
+    pub fn is_initialized(&self) -> bool {
+        self.updated_at_ms > 0 && self.last_price > 0.0
+    }
+
+    /// Whether the last update is at most `max_age_ms` old at `now_ms`
+    pub fn is_fresh(&self, now_ms: u64, max_age_ms: u64) -> bool {
+        self.is_initialized() && now_ms.saturating_sub(self.updated_at_ms) <= max_age_ms
+    }
+
+    /// Price for the given trigger source, if known
+    pub fn trigger_price(&self, trigger: TriggerPrice) -> Option<f64> {
+        let price = match trigger {
+            TriggerPrice::LastPrice => self.last_price,
+            TriggerPrice::MarkPrice => self.mark_price,
+            TriggerPrice::IndexPrice => self.index_price,
+        };
+
+        (price > 0.0).then_some(price)
+    }
+
+    /// Mark premium over index as a fraction of index
+    pub fn basis_pct(&self) -> f64 {
+        if self.index_price > 0.0 && self.mark_price > 0.0 {
+            (self.mark_price - self.index_price) / self.index_price
+        } else {
+            0.0
+        }
+    }
+
+    /// Milliseconds until the next funding settlement
+    pub fn time_to_funding_ms(&self) -> u64 {
+        self.next_funding_time_ms.saturating_sub(now_ms())
+    }
+}
+
+/// Shared ticker state updated from the `tickers` stream
+#[derive(Default)]
+pub struct TickerTracker {
+    state: RwLock<TickerState>,
+    /// Stamps `updated_at_ms`
+    clock: Clock,
+}
+
+impl TickerTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stamp updates with `clock` instead of the wall clock (replays)
+    pub fn with_clock(mut self, clock: Clock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Apply a ticker frame
+    pub fn update(&self, data: &TickerData) {
+        let mut state = self.state.write();
+        state.apply(data);
+        state.updated_at_ms = self.clock.now_ms();
+    }
+
+    /// Copy of the current state
+    pub fn snapshot(&self) -> TickerState {
+        self.state.read().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delta_keeps_missing_fields() {
+        let tracker = TickerTracker::new();
+
+        tracker.update(&TickerData {
+            symbol: "BTCUSDT".to_string(),
+            last_price: Some("50000".to_string()),
+            mark_price: Some("50010".to_string()),
+            index_price: Some("50005".to_string()),
+            funding_rate: Some("0.0001".to_string()),
+            ..Default::default()
+        });
+
+        tracker.update(&TickerData {
+            symbol: "BTCUSDT".to_string(),
+            mark_price: Some("50020".to_string()),
+            ..Default::default()
+        });
+
+        let state = tracker.snapshot();
+        assert!(state.is_initialized());
+        assert_eq!(state.last_price, 50000.0);
+        assert_eq!(state.mark_price, 50020.0);
+        assert_eq!(state.funding_rate, 0.0001);
+        assert!(state.basis_pct() > 0.0);
+    }
+
+    #[test]
+    fn test_trigger_price() {
+        let state = TickerState {
+            last_price: 100.0,
+            mark_price: 101.0,
+            ..Default::default()
+        };
+
+        assert_eq!(state.trigger_price(TriggerPrice::from_config("MarkPrice")), Some(101.0));
+        assert_eq!(state.trigger_price(TriggerPrice::from_config("LastPrice")), Some(100.0));
+        assert_eq!(state.trigger_price(TriggerPrice::IndexPrice), None);
+        for value in TriggerPrice::CONFIG_VALUES {
+            assert_eq!(format!("{:?}", TriggerPrice::from_config(value)), *value);
+        }
+    }
+
+    #[test]
+    fn test_freshness() {
+        let mut state = TickerState {
+            last_price: 100.0,
+            updated_at_ms: now_ms(),
+            ..Default::default()
+        };
+        assert!(state.is_fresh(now_ms(), 5_000));
+
+        state.updated_at_ms -= 10_000;
+        assert!(!state.is_fresh(now_ms(), 5_000));
+        assert!(!TickerState::default().is_fresh(now_ms(), 5_000));
+
+        // Replayed updates are stamped and judged in event time
+        let clock = Clock::event();
+        let tracker = TickerTracker::new().with_clock(clock.clone());
+        clock.advance(1_000);
+        tracker.update(&TickerData { last_price: Some("100".to_string()), ..Default::default() });
+        assert!(tracker.snapshot().is_fresh(clock.now_ms(), 5_000));
+        clock.advance(10_000);
+        assert!(!tracker.snapshot().is_fresh(clock.now_ms(), 5_000));
+    }
+}
@@ -0,0 +1,305 @@
+use parking_lot::RwLock;
+use std::collections::{HashMap, VecDeque};
+
+use crate::bybit::TradeData;
+use crate::utils::Clock;
+
+/// Side that crossed the spread (the taker)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AggressorSide {
+    Buy,
+    Sell,
+}
+
+/// Executed trade from the public trade stream
+#[derive(Debug, Clone)]
+pub struct Trade {
+    pub timestamp_ms: u64,
+    pub price: f64,
+    pub size: f64,
+    pub side: AggressorSide,
+    pub trade_id: String,
+}
+
+impl Trade {
+    /// Signed size: positive for aggressive buys, negative for aggressive sells
+    #[inline]
+    pub fn signed_size(&self) -> f64 {
+        match self.side {
+            AggressorSide::Buy => self.size,
+            AggressorSide::Sell => -self.size,
+        }
+    }
+}
+
+/// Rolling tape of public trades with aggressor-side volume statistics
+pub struct TradeTape {
+    /// Most recent trades, oldest first
+    trades: RwLock<VecDeque<Trade>>,
+
+    /// Maximum number of trades kept in the ring buffer
+    capacity: usize,
+
+    /// Net aggressor volume since startup
+    cumulative_delta: RwLock<f64>,
+
+    /// "Now" for the rolling windows
+    clock: Clock,
+}
+
+impl TradeTape {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            trades: RwLock::new(VecDeque::with_capacity(capacity)),
+            capacity,
+            cumulative_delta: RwLock::new(0.0),
+            clock: Clock::wall(),
+        }
+    }
+
+    /// Measure windows against `clock` instead of the wall clock (replays)
+    pub fn with_clock(mut self, clock: Clock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Parse a `publicTrade` batch and append it to the tape
+    /// Returns the trades recorded
+    pub fn ingest(&self, batch: &[TradeData]) -> Vec<Trade> {
+        let mut recorded = Vec::with_capacity(batch.len());
+
+        for data in batch {
+            let (Ok(price), Ok(size)) = (data.price.parse::<f64>(), data.size.parse::<f64>()) else {
+                continue;
+            };
+
+            let side = match self.classify(data, price) {
+                Some(side) => side,
+                None => continue,
+            };
+
+            let trade = Trade {
+                timestamp_ms: data.timestamp,
+                price,
+                size,
+                side,
+                trade_id: data.trade_id.clone(),
+            };
+            self.record(trade.clone());
+            recorded.push(trade);
+        }
+
+        recorded
+    }
+
+    /// Append a trade, evicting the oldest one when the buffer is full
+    pub fn record(&self, trade: Trade) {
+        *self.cumulative_delta.write() += trade.signed_size();
+
+        let mut trades = self.trades.write();
+        if trades.len() >= self.capacity {
+            trades.pop_front();
+        }
+        trades.push_back(trade);
+    }
+
+    /// Determine the aggressor side
+    /// Bybit reports the taker side in `S`; fall back to the tick direction
+    /// and finally to a tick rule against the previous trade
+    fn classify(&self, data: &TradeData, price: f64) -> Option<AggressorSide> {
+        match data.side.as_str() {
+            "Buy" => return Some(AggressorSide::Buy),
+            "Sell" => return Some(AggressorSide::Sell),
+            _ => {}
+        }
+
+        match data.tick_direction.as_deref() {
+            Some("PlusTick") | Some("ZeroPlusTick") => return Some(AggressorSide::Buy),
+            Some("MinusTick") | Some("ZeroMinusTick") => return Some(AggressorSide::Sell),
+            _ => {}
+        }
+
+        let trades = self.trades.read();
+        let last = trades.back()?;
+        if price > last.price {
+            Some(AggressorSide::Buy)
+        } else if price < last.price {
+            Some(AggressorSide::Sell)
+        } else {
+            Some(last.side)
+        }
+    }
+
+    /// Aggressive buy and sell volume over the last `window_ms`
+    pub fn volumes(&self, window_ms: u64) -> (f64, f64) {
+        let cutoff = self.clock.now_ms().saturating_sub(window_ms);
+        let trades = self.trades.read();
+
+        trades.iter()
+            .rev()
+            .take_while(|t| t.timestamp_ms >= cutoff)
+            .fold((0.0, 0.0), |(buy, sell), t| match t.side {
+                AggressorSide::Buy => (buy + t.size, sell),
+                AggressorSide::Sell => (buy, sell + t.size),
+            })
+    }
+
+    /// Cumulative volume delta (buy - sell aggressor volume) over the last `window_ms`
+    pub fn cvd(&self, window_ms: u64) -> f64 {
+        let (buy, sell) = self.volumes(window_ms);
+        buy - sell
+    }
+
+    /// CVD normalised by traded volume, in the range -1.0 to 1.0
+    pub fn cvd_ratio(&self, window_ms: u64) -> f64 {
+        let (buy, sell) = self.volumes(window_ms);
+        let total = buy + sell;
+        if total > 0.0 {
+            (buy - sell) / total
+        } else {
+            0.0
+        }
+    }
+
+    /// Get CVD for multiple windows
+    pub fn cvd_windows(&self, windows: &[u64]) -> HashMap<u64, f64> {
+        windows.iter()
+            .map(|&window| (window, self.cvd(window)))
+            .collect()
+    }
+
+    /// Net aggressor volume since startup
+    pub fn cumulative_delta(&self) -> f64 {
+        *self.cumulative_delta.read()
+    }
+
+    /// Trades executed in the last `window_ms`, oldest first
+    pub fn recent_trades(&self, window_ms: u64) -> Vec<Trade> {
+        let cutoff = self.clock.now_ms().saturating_sub(window_ms);
+        let trades = self.trades.read();
+        let start = trades.partition_point(|t| t.timestamp_ms < cutoff);
+        trades.range(start..).cloned().collect()
+    }
+
+    /// Most recent trade
+    pub fn last_trade(&self) -> Option<Trade> {
+        self.trades.read().back().cloned()
+    }
+
+    /// Number of trades currently held
+    pub fn len(&self) -> usize {
+        self.trades.read().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.trades.read().is_empty()
+    }
+}
+
+impl Default for TradeTape {
+    fn default() -> Self {
+        Self::new(10_000)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::now_ms;
+
+    fn trade_data(side: &str, price: &str, size: &str, ts: u64) -> TradeData {
+        TradeData {
+            timestamp: ts,
+            symbol: "BTCUSDT".to_string(),
+            side: side.to_string(),
+            size: size.to_string(),
+            price: price.to_string(),
+            trade_id: format!("{}-{}", ts, price),
+            tick_direction: None,
+        }
+    }
+
+    #[test]
+    fn test_cvd_uses_aggressor_side() {
+        let tape = TradeTape::new(100);
+        let now = now_ms();
+
+        tape.ingest(&[
+            trade_data("Buy", "50000", "2.0", now),
+            trade_data("Sell", "49999", "0.5", now),
+            trade_data("Buy", "50001", "1.0", now),
+        ]);
+
+        assert_eq!(tape.len(), 3);
+        assert!((tape.cvd(1000) - 2.5).abs() < 1e-9);
+        assert!((tape.cvd_ratio(1000) - 2.5 / 3.5).abs() < 1e-9);
+        assert!((tape.cumulative_delta() - 2.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_window_excludes_old_trades() {
+        let tape = TradeTape::new(100);
+        let now = now_ms();
+
+        tape.ingest(&[
+            trade_data("Sell", "50000", "5.0", now - 10_000),
+            trade_data("Buy", "50000", "1.0", now),
+        ]);
+
+        assert!((tape.cvd(1000) - 1.0).abs() < 1e-9);
+        assert!((tape.cvd(30_000) + 4.0).abs() < 1e-9);
+        assert_eq!(tape.recent_trades(1000).len(), 1);
+
+        let by_window = tape.cvd_windows(&[1000, 30_000]);
+        assert!((by_window[&1000] - 1.0).abs() < 1e-9);
+        assert!((by_window[&30_000] + 4.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_windows_follow_event_clock() {
+        let clock = Clock::event();
+        let tape = TradeTape::new(100).with_clock(clock.clone());
+
+        // Replayed trades from an hour ago still fall in the window
+        let then = now_ms() - 3_600_000;
+        tape.ingest(&[
+            trade_data("Buy", "50000", "3.0", then - 2_000),
+            trade_data("Sell", "50000", "1.0", then),
+        ]);
+        clock.advance(then);
+
+        assert!((tape.cvd(1000) + 1.0).abs() < 1e-9);
+        assert!((tape.cvd(5000) - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_ring_buffer_capacity() {
+        let tape = TradeTape::new(2);
+        let now = now_ms();
+
+        tape.ingest(&[
+            trade_data("Buy", "1", "1", now),
+            trade_data("Buy", "2", "1", now),
+            trade_data("Sell", "3", "1", now),
+        ]);
+
+        assert_eq!(tape.len(), 2);
+        assert_eq!(tape.last_trade().unwrap().side, AggressorSide::Sell);
+    }
+
+    #[test]
+    fn test_tick_rule_fallback() {
+        let tape = TradeTape::new(10);
+        let now = now_ms();
+
+        tape.ingest(&[
+            trade_data("Buy", "100", "1", now),
+            trade_data("", "101", "1", now),
+            trade_data("", "100.5", "1", now),
+        ]);
+
+        let trades = tape.recent_trades(1000);
+        assert_eq!(trades[1].side, AggressorSide::Buy);
+        assert_eq!(trades[2].side, AggressorSide::Sell);
+    }
+}
@@ -0,0 +1,292 @@
+use std::collections::HashMap;
+
+use ordered_float::OrderedFloat;
+
+use crate::market_data::{AggressorSide, Trade};
+use super::manager::{BookEvent, BookEventKind};
+use super::metrics::OrderSide;
+
+#[derive(Debug, Clone)]
+pub struct IcebergConfig {
+    /// A level is forgotten this long after its last trade
+    pub window_ms: u64,
+
+    /// Iceberg once executed volume reaches this multiple of the largest size ever shown
+    pub exec_ratio: f64,
+
+    /// ...or once the level has been topped up this many times right after trading
+    pub min_refills: u32,
+
+    /// Size added within this long of a trade at the level counts as a refill
+    pub refill_window_ms: u64,
+
+    /// ...if it brings the level back to at least this fraction of its largest displayed size
+    pub refill_ratio: f64,
+
+    /// `take_new` reports a price level at most once per this long
+    pub alert_cooldown_ms: u64,
+}
+
+impl Default for IcebergConfig {
+    fn default() -> Self {
+        Self {
+            window_ms: 30_000,
+            exec_ratio: 2.0,
+            min_refills: 3,
+            refill_window_ms: 1_000,
+            refill_ratio: 0.8,
+            alert_cooldown_ms: 300_000,
+        }
+    }
+}
+
+/// Hidden liquidity detected at one price
+#[derive(Debug, Clone, PartialEq)]
+pub struct Iceberg {
+    /// Resting side: bids absorb aggressive sells, asks absorb aggressive buys
+    pub side: OrderSide,
+    pub price: f64,
+    /// Volume executed against the level since it was first traded
+    pub executed: f64,
+    /// Size currently shown
+    pub displayed: f64,
+    /// Largest size shown at once
+    pub peak_displayed: f64,
+    /// Times the level was topped up right after trading
+    pub refills: u32,
+    /// Average refill, i.e. the visible tranche
+    pub clip_size: f64,
+    /// Size that was never on display: executed plus shown now, less the most ever shown
+    /// A lower bound, as whatever is still hidden is unknown
+    pub hidden_size: f64,
+    pub first_trade_ms: u64,
+    pub last_trade_ms: u64,
+}
+
+/// Trade and book history of one price level
+#[derive(Debug, Clone)]
+struct LevelFlow {
+    executed: f64,
+    displayed: f64,
+    peak_displayed: f64,
+    refills: u32,
+    refilled: f64,
+    first_trade_ms: u64,
+    last_trade_ms: u64,
+    /// Already returned by `take_new`
+    reported: bool,
+}
+
+/// Finds levels that trade far more than they show, or keep refilling after trades
+/// Fed with trades (plus the size on display at their price) and per-level book events
+pub struct IcebergDetector {
+    config: IcebergConfig,
+    levels: HashMap<(OrderSide, OrderedFloat<f64>), LevelFlow>,
+    /// Last time each level was returned by `take_new`, kept past the level itself for the cooldown
+    last_reported: HashMap<(OrderSide, OrderedFloat<f64>), u64>,
+}
+
+impl IcebergDetector {
+    pub fn new(config: IcebergConfig) -> Self {
+        Self {
+            config,
+            levels: HashMap::new(),
+            last_reported: HashMap::new(),
+        }
+    }
+
+    /// Record a trade and the size on display at its price when it arrived
+    pub fn record_trade(&mut self, now_ms: u64, trade: &Trade, displayed: f64) {
+        // Prune first so a level traded again after the window starts from scratch
+        self.prune(now_ms);
+
+        let side = match trade.side {
+            AggressorSide::Buy => OrderSide::Ask,
+            AggressorSide::Sell => OrderSide::Bid,
+        };
+        let flow = self.levels.entry((side, OrderedFloat(trade.price))).or_insert(LevelFlow {
+            executed: 0.0,
+            displayed,
+            peak_displayed: 0.0,
+            refills: 0,
+            refilled: 0.0,
+            first_trade_ms: now_ms,
+            last_trade_ms: now_ms,
+            reported: false,
+        });
+
+        // The delta for this trade may or may not have landed yet, so the level
+        // showed at most what is left plus what just traded
+        flow.executed += trade.size;
+        flow.displayed = displayed;
+        flow.peak_displayed = flow.peak_displayed.max(displayed + trade.size);
+        flow.last_trade_ms = now_ms;
+    }
+
+    /// Follow displayed size on traded levels and count size added straight after a trade as refills
+    /// when it restores most of the size the level used to show
+    pub fn record_book_events(&mut self, now_ms: u64, events: &[BookEvent]) {
+        for event in events {
+            let Some(flow) = self.levels.get_mut(&(event.side, OrderedFloat(event.price))) else {
+                continue;
+            };
+            match event.kind {
+                BookEventKind::Add { qty } => {
+                    let after_trade = now_ms.saturating_sub(flow.last_trade_ms) <= self.config.refill_window_ms;
+                    if after_trade && event.size >= flow.peak_displayed * self.config.refill_ratio {
+                        flow.refills += 1;
+                        flow.refilled += qty;
+                    } else {
+                        flow.peak_displayed = flow.peak_displayed.max(event.size);
+                    }
+                }
+                BookEventKind::Reduce { .. } | BookEventKind::Remove { .. } => {}
+                BookEventKind::BestPrice { .. } => continue,
+            }
+            flow.displayed = event.size;
+        }
+    }
+
+    /// Levels currently detected as icebergs, largest hidden size first
+    pub fn icebergs(&self, now_ms: u64) -> Vec<Iceberg> {
+        let cutoff = now_ms.saturating_sub(self.config.window_ms);
+        let mut icebergs: Vec<_> = self.levels.iter()
+            .filter(|(_, flow)| flow.last_trade_ms >= cutoff && is_iceberg(&self.config, flow))
+            .map(|(key, flow)| to_iceberg(key, flow))
+            .collect();
+        icebergs.sort_by(|a, b| b.hidden_size.total_cmp(&a.hidden_size));
+        icebergs
+    }
+
+    /// Icebergs detected since the last call, skipping levels reported within the cooldown
+    pub fn take_new(&mut self, now_ms: u64) -> Vec<Iceberg> {
+        let cutoff = now_ms.saturating_sub(self.config.window_ms);
+        let mut new = Vec::new();
+        for (key, flow) in self.levels.iter_mut() {
+            if flow.reported || flow.last_trade_ms < cutoff || !is_iceberg(&self.config, flow) {
+                continue;
+            }
+            flow.reported = true;
+            let cooling = self.last_reported.get(key)
+                .is_some_and(|&at| now_ms.saturating_sub(at) < self.config.alert_cooldown_ms);
+            if !cooling {
+                self.last_reported.insert(*key, now_ms);
+                new.push(to_iceberg(key, flow));
+            }
+        }
+        new
+    }
+
+    /// Hidden bid size against hidden ask size, -1 (hidden sellers) to 1 (hidden buyers)
+    pub fn hidden_imbalance(&self, now_ms: u64) -> f64 {
+        let (bid, ask) = self.icebergs(now_ms).iter().fold((0.0, 0.0), |(bid, ask), iceberg| match iceberg.side {
+            OrderSide::Bid => (bid + iceberg.hidden_size, ask),
+            OrderSide::Ask => (bid, ask + iceberg.hidden_size),
+        });
+        let total = bid + ask;
+        if total > 0.0 { (bid - ask) / total } else { 0.0 }
+    }
+
+    fn prune(&mut self, now_ms: u64) {
+        let cutoff = now_ms.saturating_sub(self.config.window_ms);
+        self.levels.retain(|_, flow| flow.last_trade_ms >= cutoff);
+        let cooldown = self.config.alert_cooldown_ms;
+        self.last_reported.retain(|_, at| now_ms.saturating_sub(*at) < cooldown);
+    }
+}
+
+fn is_iceberg(config: &IcebergConfig, flow: &LevelFlow) -> bool {
+    flow.executed > 0.0
+        && (flow.executed >= flow.peak_displayed * config.exec_ratio || flow.refills >= config.min_refills)
+}
+
+fn to_iceberg(key: &(OrderSide, OrderedFloat<f64>), flow: &LevelFlow) -> Iceberg {
+    Iceberg {
+        side: key.0,
+        price: key.1 .0,
+        executed: flow.executed,
+        displayed: flow.displayed,
+        peak_displayed: flow.peak_displayed,
+        refills: flow.refills,
+        clip_size: if flow.refills > 0 { flow.refilled / flow.refills as f64 } else { flow.peak_displayed },
+        hidden_size: (flow.executed + flow.displayed - flow.peak_displayed).max(0.0),
+        first_trade_ms: flow.first_trade_ms,
+        last_trade_ms: flow.last_trade_ms,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sell(price: f64, size: f64) -> Trade {
+        Trade { timestamp_ms: 0, price, size, side: AggressorSide::Sell, trade_id: String::new() }
+    }
+
+    fn event(price: f64, size: f64, kind: BookEventKind) -> BookEvent {
+        BookEvent { side: OrderSide::Bid, price, level: 0, size, kind }
+    }
+
+    #[test]
+    fn test_refilling_bid_is_flagged_with_hidden_size() {
+        let mut detector = IcebergDetector::new(IcebergConfig::default());
+
+        // A plain level traded down once is not an iceberg
+        detector.record_trade(1_000, &sell(49_990.0, 1.0), 4.0);
+        assert!(detector.icebergs(1_000).is_empty());
+
+        // 50000 shows 1.0 and tops back up to 1.0 after every hit
+        for i in 0..4u64 {
+            let now = 2_000 + i * 500;
+            detector.record_book_events(now, &[event(50_000.0, 0.0, BookEventKind::Remove { qty: 1.0 })]);
+            detector.record_trade(now, &sell(50_000.0, 1.0), 0.0);
+            detector.record_book_events(now + 100, &[event(50_000.0, 1.0, BookEventKind::Add { qty: 1.0 })]);
+        }
+
+        let icebergs = detector.icebergs(4_000);
+        assert_eq!(icebergs.len(), 1);
+        let iceberg = &icebergs[0];
+        assert_eq!((iceberg.side, iceberg.price), (OrderSide::Bid, 50_000.0));
+        assert_eq!((iceberg.executed, iceberg.peak_displayed, iceberg.refills), (4.0, 1.0, 4));
+        assert_eq!(iceberg.clip_size, 1.0);
+        assert_eq!(iceberg.hidden_size, 4.0);
+        assert_eq!(detector.hidden_imbalance(4_000), 1.0);
+
+        // Reported once, then forgotten after the window
+        assert_eq!(detector.take_new(4_000).len(), 1);
+        assert!(detector.take_new(4_000).is_empty());
+        detector.record_trade(40_000, &sell(49_000.0, 0.1), 5.0);
+        assert!(detector.icebergs(40_000).is_empty());
+    }
+
+    #[test]
+    fn test_small_top_ups_and_repeat_reports_are_ignored() {
+        let config = IcebergConfig { exec_ratio: 100.0, ..IcebergConfig::default() };
+        let mut detector = IcebergDetector::new(config);
+
+        // Dust added after each hit on a 1.0 level is ordinary flow, not a refill
+        for i in 0..4u64 {
+            let now = 1_000 + i * 500;
+            detector.record_trade(now, &sell(50_000.0, 1.0), 0.0);
+            detector.record_book_events(now + 100, &[event(50_000.0, 0.1, BookEventKind::Add { qty: 0.1 })]);
+        }
+        assert!(detector.icebergs(3_000).is_empty());
+
+        // A level that keeps refilling is reported once, and not again when it comes back
+        // within the cooldown after being forgotten
+        let refill = |detector: &mut IcebergDetector, start: u64| {
+            for i in 0..3u64 {
+                let now = start + i * 500;
+                detector.record_trade(now, &sell(49_990.0, 1.0), 0.0);
+                detector.record_book_events(now + 100, &[event(49_990.0, 1.0, BookEventKind::Add { qty: 1.0 })]);
+            }
+        };
+        refill(&mut detector, 10_000);
+        assert_eq!(detector.take_new(12_000).len(), 1);
+        refill(&mut detector, 60_000);
+        assert_eq!(detector.icebergs(62_000).len(), 1);
+        assert!(detector.take_new(62_000).is_empty());
+        refill(&mut detector, 400_000);
+        assert_eq!(detector.take_new(402_000).len(), 1);
+    }
+}
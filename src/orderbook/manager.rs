@@ -1,11 +1,18 @@
-use dashmap::DashMap;
 use ordered_float::OrderedFloat;
 use std::sync::Arc;
-use std::sync::atomic::{AtomicU64, Ordering};
-use parking_lot::RwLock;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use parking_lot::{Mutex, RwLock};
+
+use crate::bybit::Category;
+use crate::market_data::{AggressorSide, InstrumentSpec, Trade};
+use crate::utils::Clock;
+use super::metrics::{DepthMeasure, OrderSide};
 
 pub type Price = OrderedFloat<f64>;
 pub type Quantity = OrderedFloat<f64>;
+/// (price, qty) levels, best first
+pub type LevelList = Vec<(f64, f64)>;
 
 #[derive(Debug, Clone)]
 pub struct OrderbookLevel {
@@ -14,25 +21,299 @@ pub struct OrderbookLevel {
     pub timestamp: u64,
 }
 
+/// Kind of orderbook frame, taken from the WebSocket envelope `type`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpdateKind {
+    Snapshot,
+    Delta,
+}
+
+/// Outcome of a sequence-checked orderbook update
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SequenceStatus {
+    /// Update was in order and has been applied
+    Applied,
+    /// Duplicate or out-of-order frame, ignored
+    Stale { last_update_id: u64, update_id: u64 },
+    /// Missed one or more deltas, book is now out of sync and needs a snapshot
+    Gap { expected: u64, received: u64 },
+    /// Delta dropped because the book is waiting for a fresh snapshot
+    AwaitingSnapshot,
+}
+
+/// Last applied `u`/`seq` for the book
+#[derive(Debug, Default)]
+struct SequenceState {
+    last_update_id: u64,
+    last_seq: u64,
+}
+
+/// Top of book from the `orderbook.1` stream
+#[derive(Debug, Default)]
+struct BboState {
+    bid: Option<(f64, f64)>,
+    ask: Option<(f64, f64)>,
+    last_update_id: u64,
+    /// When the BBO feed and the deep book started disagreeing
+    mismatch_since: Option<Instant>,
+    /// Disagreement outlasted the tolerance and was reported
+    flagged: bool,
+}
+
+/// BBO feed and deep book disagree on the top of book
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BboMismatch {
+    /// (bid, ask) from `orderbook.1`
+    pub bbo: (f64, f64),
+    /// (bid, ask) from the deep book
+    pub book: (f64, f64),
+    pub duration: Duration,
+}
+
+/// Price used as "the" price for sizing, entries and exit checks
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReferencePrice {
+    /// (best bid + best ask) / 2
+    Mid,
+    /// Top-of-book prices weighted by the opposite side's size
+    Microprice,
+    /// Mean of the bid and ask VWAPs over the top `depth` levels
+    WeightedMid { depth: usize },
+    /// Mean of the bid and ask VWAPs over levels within `bps` of mid
+    WeightedMidBand { bps: f64 },
+    /// Mid shifted towards the thin side by half the spread times the `depth`-level imbalance
+    ImbalanceMicroprice { depth: usize },
+}
+
+impl ReferencePrice {
+    /// Accepted `trading.reference_price` values
+    pub const CONFIG_VALUES: &'static [&'static str] = &["mid", "microprice", "weighted_mid", "imbalance_microprice"];
+
+    /// Parse `trading.reference_price`, defaulting to Mid
+    /// `band_bps > 0` switches the weighted mid from levels to a price band
+    pub fn from_config(value: &str, depth: usize, band_bps: f64) -> Self {
+        match value {
+            "microprice" => ReferencePrice::Microprice,
+            "weighted_mid" if band_bps > 0.0 => ReferencePrice::WeightedMidBand { bps: band_bps },
+            "weighted_mid" => ReferencePrice::WeightedMid { depth },
+            "imbalance_microprice" => ReferencePrice::ImbalanceMicroprice { depth },
+            _ => ReferencePrice::Mid,
+        }
+    }
+}
+
+/// Expected fill of a market order walked through the visible book
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ImpactEstimate {
+    /// Size-weighted average fill price
+    pub vwap: f64,
+    /// Furthest price reached
+    pub worst_price: f64,
+    /// VWAP against mid, in bps; positive is a cost
+    pub slippage_bps: f64,
+    /// Levels touched, counting a partly taken last level
+    pub levels_consumed: usize,
+    pub filled_qty: f64,
+    /// Quantity the visible book could not absorb
+    pub unfilled_qty: f64,
+}
+
+/// Change to one price level, emitted by `Orderbook::apply_delta`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BookEvent {
+    pub side: OrderSide,
+    pub price: f64,
+    /// Distance from the top when the change happened (0 = best)
+    pub level: usize,
+    /// Level size after the change (0 once removed)
+    pub size: f64,
+    pub kind: BookEventKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BookEventKind {
+    /// New level, or more size on an existing one; `qty` is the size added
+    Add { qty: f64 },
+    /// Size pulled or filled without emptying the level; `qty` is the size removed
+    Reduce { qty: f64 },
+    /// Level emptied; `qty` is the size it held
+    Remove { qty: f64 },
+    /// Best price moved to `price` from `previous`
+    BestPrice { previous: f64 },
+}
+
+/// One side of the book as a price-sorted array with the best level last
+/// Most updates land at or near the top, so inserts and removals shift only a few entries,
+/// and top-N reads walk a contiguous slice backwards
+#[derive(Debug)]
+struct Side {
+    levels: Vec<(f64, f64)>,
+    /// Bids ascend towards the best price, asks descend towards it
+    is_bid: bool,
+}
+
+impl Side {
+    fn new(is_bid: bool) -> Self {
+        Self { levels: Vec::with_capacity(512), is_bid }
+    }
+
+    fn order_side(&self) -> OrderSide {
+        if self.is_bid { OrderSide::Bid } else { OrderSide::Ask }
+    }
+
+    #[inline]
+    fn find(&self, price: f64) -> Result<usize, usize> {
+        if self.is_bid {
+            self.levels.binary_search_by(|(p, _)| p.total_cmp(&price))
+        } else {
+            self.levels.binary_search_by(|(p, _)| price.total_cmp(p))
+        }
+    }
+
+    /// Insert or resize a level, or remove it on a zero size, recording what changed
+    #[inline]
+    fn set(&mut self, price: f64, qty: f64, events: &mut Vec<BookEvent>) {
+        let len = self.levels.len();
+        let (level, kind) = match self.find(price) {
+            Ok(i) => {
+                let previous = self.levels[i].1;
+                let kind = if qty <= 0.0 {
+                    self.levels.remove(i);
+                    BookEventKind::Remove { qty: previous }
+                } else if qty > previous {
+                    self.levels[i].1 = qty;
+                    BookEventKind::Add { qty: qty - previous }
+                } else if qty < previous {
+                    self.levels[i].1 = qty;
+                    BookEventKind::Reduce { qty: previous - qty }
+                } else {
+                    return;
+                };
+                (len - 1 - i, kind)
+            }
+            Err(i) if qty > 0.0 => {
+                self.levels.insert(i, (price, qty));
+                (len - i, BookEventKind::Add { qty })
+            }
+            Err(_) => return,
+        };
+        events.push(BookEvent { side: self.order_side(), price, level, size: qty.max(0.0), kind });
+    }
+
+    /// Apply one side of a delta, then record a best-price change if there was one
+    fn apply(&mut self, updates: Vec<(f64, f64)>, events: &mut Vec<BookEvent>) {
+        let previous = self.best();
+        for (price, qty) in updates {
+            self.set(price, qty, events);
+        }
+        if let (Some(previous), Some(&(best, size))) = (previous, self.levels.last()) {
+            if previous != best {
+                events.push(BookEvent {
+                    side: self.order_side(),
+                    price: best,
+                    level: 0,
+                    size,
+                    kind: BookEventKind::BestPrice { previous },
+                });
+            }
+        }
+    }
+
+    /// Replace every level; snapshots arrive best-first, so the sort only reverses them
+    fn replace(&mut self, levels: Vec<(f64, f64)>) {
+        self.levels.clear();
+        self.levels.extend(levels.into_iter().filter(|(_, qty)| *qty > 0.0));
+        if self.is_bid {
+            self.levels.sort_unstable_by(|a, b| a.0.total_cmp(&b.0));
+        } else {
+            self.levels.sort_unstable_by(|a, b| b.0.total_cmp(&a.0));
+        }
+        self.levels.dedup_by(|a, b| a.0 == b.0);
+    }
+
+    #[inline]
+    fn size_at(&self, price: f64) -> f64 {
+        self.find(price).map_or(0.0, |i| self.levels[i].1)
+    }
+
+    #[inline]
+    fn best(&self) -> Option<f64> {
+        self.levels.last().map(|(price, _)| *price)
+    }
+
+    /// Top `depth` levels, best first
+    #[inline]
+    fn top(&self, depth: usize) -> impl Iterator<Item = (f64, f64)> + '_ {
+        let from = self.levels.len().saturating_sub(depth);
+        self.levels[from..].iter().rev().copied()
+    }
+
+    fn volume(&self, depth: usize) -> f64 {
+        self.top(depth).map(|(_, qty)| qty).sum()
+    }
+
+    /// Size-weighted average price of `levels`
+    fn vwap(levels: impl Iterator<Item = (f64, f64)>) -> Option<f64> {
+        let (notional, size) = levels.fold((0.0, 0.0), |(n, s), (price, qty)| (n + price * qty, s + qty));
+        (size > 0.0).then(|| notional / size)
+    }
+
+    /// Levels from the top while `keep` holds, always including the best one
+    fn top_while<'a>(&'a self, keep: impl Fn(f64) -> bool + 'a) -> impl Iterator<Item = (f64, f64)> + 'a {
+        self.top(usize::MAX)
+            .enumerate()
+            .take_while(move |(i, (price, _))| *i == 0 || keep(*price))
+            .map(|(_, level)| level)
+    }
+}
+
+/// Both sides of the book
+#[derive(Debug)]
+struct Levels {
+    bids: Side,
+    asks: Side,
+}
+
+impl Levels {
+    fn mid(&self) -> Option<f64> {
+        Some((self.bids.best()? + self.asks.best()?) / 2.0)
+    }
+}
+
+impl Default for Levels {
+    fn default() -> Self {
+        Self { bids: Side::new(true), asks: Side::new(false) }
+    }
+}
+
 pub struct Orderbook {
     pub symbol: String,
 
-    // Lock-free concurrent hashmaps
-    bids: Arc<DashMap<Price, Quantity>>,
-    asks: Arc<DashMap<Price, Quantity>>,
+    // Tick size and lot rules, when instrument metadata is available
+    instrument: Option<InstrumentSpec>,
 
-    // Sorted views (updated periodically)
-    sorted_bids: Arc<RwLock<Vec<OrderbookLevel>>>,
-    sorted_asks: Arc<RwLock<Vec<OrderbookLevel>>>,
+    // Price levels, ordered; one lock so readers see both sides of the same update
+    levels: Arc<RwLock<Levels>>,
 
-    // Best bid/ask (atomic)
+    // Best bid/ask (atomic); from the BBO feed when one is attached
     best_bid: Arc<AtomicU64>,
     best_ask: Arc<AtomicU64>,
 
+    // Top of the deep book, kept separately for cross-checking the BBO feed
+    book_best_bid: Arc<AtomicU64>,
+    book_best_ask: Arc<AtomicU64>,
+    bbo: Option<Arc<Mutex<BboState>>>,
+    bbo_mismatches: Arc<AtomicU64>,
+
     // Metrics
+    clock: Clock,
     last_update_time: Arc<AtomicU64>,
     update_count: Arc<AtomicU64>,
 
+    // Sequence tracking (held for the whole update so frames apply in order)
+    sequence: Arc<Mutex<SequenceState>>,
+    synced: Arc<AtomicBool>,
+
     // Advanced metrics (Phase 2)
     metrics: Arc<RwLock<super::metrics::OrderbookMetrics>>,
 }
@@ -41,155 +322,287 @@ impl Orderbook {
     pub fn new(symbol: String) -> Self {
         Self {
             symbol,
-            bids: Arc::new(DashMap::new()),
-            asks: Arc::new(DashMap::new()),
-            sorted_bids: Arc::new(RwLock::new(Vec::new())),
-            sorted_asks: Arc::new(RwLock::new(Vec::new())),
+            instrument: None,
+            levels: Arc::new(RwLock::new(Levels::default())),
             best_bid: Arc::new(AtomicU64::new(0)),
             best_ask: Arc::new(AtomicU64::new(u64::MAX)),
+            book_best_bid: Arc::new(AtomicU64::new(0)),
+            book_best_ask: Arc::new(AtomicU64::new(u64::MAX)),
+            bbo: None,
+            bbo_mismatches: Arc::new(AtomicU64::new(0)),
+            clock: Clock::wall(),
             last_update_time: Arc::new(AtomicU64::new(0)),
             update_count: Arc::new(AtomicU64::new(0)),
+            sequence: Arc::new(Mutex::new(SequenceState::default())),
+            synced: Arc::new(AtomicBool::new(false)),
             metrics: Arc::new(RwLock::new(super::metrics::OrderbookMetrics::new())),
         }
     }
+
+    /// Take best bid/ask from `apply_bbo` instead of the deep book
+    /// The deep book still serves imbalance, liquidity and the other depth metrics
+    pub fn with_bbo_feed(mut self) -> Self {
+        self.bbo = Some(Arc::new(Mutex::new(BboState::default())));
+        self
+    }
     
+    /// Iceberg detection thresholds
+    pub fn with_iceberg_config(self, config: super::iceberg::IcebergConfig) -> Self {
+        self.metrics.write().set_iceberg_config(config);
+        self
+    }
+
+    /// Time updates and metric windows with `clock` instead of the wall clock (replays)
+    pub fn with_clock(mut self, clock: Clock) -> Self {
+        self.metrics.write().set_clock(clock.clone());
+        self.clock = clock;
+        self
+    }
+
+    /// Keep enough OFI history for a `window_ms` scoring window
+    pub fn with_ofi_horizon(self, window_ms: u64) -> Self {
+        self.metrics.write().set_ofi_horizon(window_ms);
+        self
+    }
+
+    /// Attach the symbol's trading rules
+    pub fn with_instrument(mut self, spec: InstrumentSpec) -> Self {
+        self.metrics.write().set_instrument(spec.clone());
+        self.instrument = Some(spec);
+        self
+    }
+
+    /// Smallest level value, in the quote currency, that can count as a whale
+    pub fn with_min_whale_notional(self, min_notional: f64) -> Self {
+        self.metrics.write().set_min_whale_notional(min_notional);
+        self
+    }
+
+    pub fn instrument(&self) -> Option<&InstrumentSpec> {
+        self.instrument.as_ref()
+    }
+
+    /// Spread in ticks; `None` without instrument metadata or a two-sided book
+    pub fn spread_ticks(&self) -> Option<f64> {
+        let tick = self.instrument.as_ref()?.tick_size;
+        let (bid, ask) = self.best_bid_ask();
+        if bid <= 0.0 || !ask.is_finite() || tick <= 0.0 {
+            return None;
+        }
+        Some(((ask - bid) / tick).round())
+    }
+
+    /// Apply a snapshot or delta after checking Bybit's `u`/`seq` ordering
+    ///
+    /// Deltas must carry `u == last_u + 1`. Anything older is dropped as stale;
+    /// anything newer means frames were lost, so the book is marked out of sync
+    /// and further deltas are ignored until the next snapshot arrives.
+    pub fn apply_update(
+        &self,
+        kind: UpdateKind,
+        bids: Vec<(f64, f64)>,
+        asks: Vec<(f64, f64)>,
+        update_id: u64,
+        seq: u64,
+    ) -> SequenceStatus {
+        let mut sequence = self.sequence.lock();
+
+        if kind == UpdateKind::Snapshot {
+            self.apply_snapshot(bids, asks);
+            sequence.last_update_id = update_id;
+            sequence.last_seq = seq;
+            return SequenceStatus::Applied;
+        }
+
+        if !self.is_synced() {
+            return SequenceStatus::AwaitingSnapshot;
+        }
+
+        if update_id <= sequence.last_update_id || seq < sequence.last_seq {
+            return SequenceStatus::Stale {
+                last_update_id: sequence.last_update_id,
+                update_id,
+            };
+        }
+
+        let expected = sequence.last_update_id + 1;
+        if update_id != expected {
+            self.mark_out_of_sync();
+            return SequenceStatus::Gap { expected, received: update_id };
+        }
+
+        self.apply_delta(bids, asks);
+        sequence.last_update_id = update_id;
+        sequence.last_seq = seq;
+        SequenceStatus::Applied
+    }
+
+    /// Whether the book reflects a consistent snapshot + delta chain
+    #[inline]
+    pub fn is_synced(&self) -> bool {
+        self.synced.load(Ordering::Acquire)
+    }
+
+    /// Flag the book as inconsistent until the next snapshot
+    pub fn mark_out_of_sync(&self) {
+        self.synced.store(false, Ordering::Release);
+    }
+
+    /// Last applied update id (`u`)
+    pub fn last_update_id(&self) -> u64 {
+        self.sequence.lock().last_update_id
+    }
+
+    /// Apply an `orderbook.1` frame to the BBO feed
+    /// Each frame replaces the top level; a zero size at the current price empties that side
+    pub fn apply_bbo(&self, kind: UpdateKind, bids: Vec<(f64, f64)>, asks: Vec<(f64, f64)>, update_id: u64) -> SequenceStatus {
+        let Some(bbo) = &self.bbo else {
+            return SequenceStatus::AwaitingSnapshot;
+        };
+        let mut state = bbo.lock();
+
+        if kind == UpdateKind::Delta && update_id <= state.last_update_id {
+            return SequenceStatus::Stale {
+                last_update_id: state.last_update_id,
+                update_id,
+            };
+        }
+
+        if kind == UpdateKind::Snapshot {
+            state.bid = None;
+            state.ask = None;
+        }
+        apply_top_level(&mut state.bid, bids);
+        apply_top_level(&mut state.ask, asks);
+        state.last_update_id = update_id;
+
+        if let Some((price, _)) = state.bid {
+            self.best_bid.store(price.to_bits(), Ordering::Relaxed);
+        }
+        if let Some((price, _)) = state.ask {
+            self.best_ask.store(price.to_bits(), Ordering::Relaxed);
+        }
+
+        SequenceStatus::Applied
+    }
+
+    /// Compare the BBO feed with the deep book's top of book
+    /// Returns the mismatch once, when the feeds have disagreed for longer than `tolerance`
+    pub fn check_bbo(&self, tolerance: Duration) -> Option<BboMismatch> {
+        let bbo = self.bbo.as_ref()?;
+        let mut state = bbo.lock();
+
+        let book = (
+            f64::from_bits(self.book_best_bid.load(Ordering::Relaxed)),
+            f64::from_bits(self.book_best_ask.load(Ordering::Relaxed)),
+        );
+        let (Some((bid, _)), Some((ask, _))) = (state.bid, state.ask) else {
+            return None;
+        };
+
+        // Nothing to compare while the deep book is rebuilding
+        if !self.is_synced() || (bid, ask) == book {
+            if state.flagged {
+                tracing::info!("✅ BBO feed and orderbook agree again");
+            }
+            state.mismatch_since = None;
+            state.flagged = false;
+            return None;
+        }
+
+        let since = *state.mismatch_since.get_or_insert_with(Instant::now);
+        if state.flagged || since.elapsed() < tolerance {
+            return None;
+        }
+
+        state.flagged = true;
+        self.bbo_mismatches.fetch_add(1, Ordering::Relaxed);
+        Some(BboMismatch { bbo: (bid, ask), book, duration: since.elapsed() })
+    }
+
+    /// Whether a reported BBO mismatch is still ongoing
+    pub fn bbo_diverged(&self) -> bool {
+        self.bbo.as_ref().is_some_and(|bbo| bbo.lock().flagged)
+    }
+
+    /// Mismatches reported by `check_bbo` so far
+    pub fn bbo_mismatches(&self) -> u64 {
+        self.bbo_mismatches.load(Ordering::Relaxed)
+    }
+
+    /// Best prices come from the BBO feed once it has both sides
+    fn bbo_live(&self) -> bool {
+        self.bbo.as_ref().is_some_and(|bbo| {
+            let state = bbo.lock();
+            state.bid.is_some() && state.ask.is_some()
+        })
+    }
+
     /// Process orderbook snapshot (full replace)
     pub fn apply_snapshot(&self, bids: Vec<(f64, f64)>, asks: Vec<(f64, f64)>) {
         let start = std::time::Instant::now();
         
-        // Clear existing
-        self.bids.clear();
-        self.asks.clear();
-        
-        // Insert bids
-        for (price, qty) in bids {
-            if qty > 0.0 {
-                self.bids.insert(Price::from(price), Quantity::from(qty));
-            }
-        }
-        
-        // Insert asks
-        for (price, qty) in asks {
-            if qty > 0.0 {
-                self.asks.insert(Price::from(price), Quantity::from(qty));
-            }
+        {
+            let mut levels = self.levels.write();
+            levels.bids.replace(bids);
+            levels.asks.replace(asks);
+
+            self.publish_best(&levels);
         }
         
-        // Update sorted views and best prices
-        self.update_sorted_views();
-        
         let elapsed = start.elapsed().as_micros();
         if elapsed > 100 {
             tracing::warn!("Slow snapshot processing: {}μs", elapsed);
         }
         
-        self.last_update_time.store(
-            chrono::Utc::now().timestamp_millis() as u64,
-            Ordering::Relaxed
-        );
+        self.last_update_time.store(self.clock.now_ms(), Ordering::Relaxed);
         self.update_count.fetch_add(1, Ordering::Relaxed);
+        self.synced.store(true, Ordering::Release);
     }
     
     /// Process orderbook delta (incremental update)
-    pub fn apply_delta(&self, bids: Vec<(f64, f64)>, asks: Vec<(f64, f64)>) {
+    /// Levels are updated in place; the best prices are read straight off the end of each side.
+    /// Returns the per-level changes, which also feed the order-flow metrics
+    pub fn apply_delta(&self, bids: Vec<(f64, f64)>, asks: Vec<(f64, f64)>) -> Vec<BookEvent> {
         let start = std::time::Instant::now();
+        let mut events = Vec::with_capacity(bids.len() + asks.len() + 2);
         
-        let mut best_bid_changed = false;
-        let mut best_ask_changed = false;
-        
-        // Update bids
-        for (price, qty) in bids {
-            let price = Price::from(price);
-            
-            if qty == 0.0 {
-                self.bids.remove(&price);
-                best_bid_changed = true;
-            } else {
-                self.bids.insert(price, Quantity::from(qty));
-                
-                // Check if this is new best bid
-                let current_best = f64::from_bits(
-                    self.best_bid.load(Ordering::Relaxed)
-                );
-                if price.0 > current_best {
-                    best_bid_changed = true;
-                }
-            }
-        }
-        
-        // Update asks
-        for (price, qty) in asks {
-            let price = Price::from(price);
-            
-            if qty == 0.0 {
-                self.asks.remove(&price);
-                best_ask_changed = true;
-            } else {
-                self.asks.insert(price, Quantity::from(qty));
-                
-                let current_best = f64::from_bits(
-                    self.best_ask.load(Ordering::Relaxed)
-                );
-                if price.0 < current_best {
-                    best_ask_changed = true;
-                }
-            }
-        }
-        
-        // Only update sorted views if best prices changed
-        if best_bid_changed || best_ask_changed {
-            self.update_sorted_views();
+        {
+            let mut levels = self.levels.write();
+            levels.bids.apply(bids, &mut events);
+            levels.asks.apply(asks, &mut events);
+            self.publish_best(&levels);
         }
+
+        self.metrics.write().record_book_events(&events);
         
         let elapsed = start.elapsed().as_micros();
         if elapsed > 50 {
             tracing::warn!("Slow delta processing: {}μs", elapsed);
         }
         
-        self.last_update_time.store(
-            chrono::Utc::now().timestamp_millis() as u64,
-            Ordering::Relaxed
-        );
+        self.last_update_time.store(self.clock.now_ms(), Ordering::Relaxed);
+
+        events
     }
     
-    fn update_sorted_views(&self) {
-        let now = chrono::Utc::now().timestamp_millis() as u64;
-        
-        // Bids (highest to lowest)
-        let mut bids: Vec<_> = self.bids.iter()
-            .map(|entry| OrderbookLevel {
-                price: *entry.key(),
-                quantity: *entry.value(),
-                timestamp: now,
-            })
-            .collect();
-        
-        bids.sort_by(|a, b| b.price.cmp(&a.price));
-        
-        // Update best bid (atomic)
-        if let Some(best) = bids.first() {
-            self.best_bid.store(best.price.0.to_bits(), Ordering::Relaxed);
+    /// Store the top of the deep book in the best-price atomics
+    fn publish_best(&self, levels: &Levels) {
+        let publish_best = !self.bbo_live();
+
+        if let Some(price) = levels.bids.best() {
+            self.book_best_bid.store(price.to_bits(), Ordering::Relaxed);
+            if publish_best {
+                self.best_bid.store(price.to_bits(), Ordering::Relaxed);
+            }
         }
-        
-        *self.sorted_bids.write() = bids;
-        
-        // Asks (lowest to highest)
-        let mut asks: Vec<_> = self.asks.iter()
-            .map(|entry| OrderbookLevel {
-                price: *entry.key(),
-                quantity: *entry.value(),
-                timestamp: now,
-            })
-            .collect();
-        
-        asks.sort_by(|a, b| a.price.cmp(&b.price));
-        
-        // Update best ask (atomic)
-        if let Some(best) = asks.first() {
-            self.best_ask.store(best.price.0.to_bits(), Ordering::Relaxed);
+
+        if let Some(price) = levels.asks.best() {
+            self.book_best_ask.store(price.to_bits(), Ordering::Relaxed);
+            if publish_best {
+                self.best_ask.store(price.to_bits(), Ordering::Relaxed);
+            }
         }
-        
-        *self.sorted_asks.write() = asks;
     }
     
     /// Get best bid/ask (lock-free read)
@@ -216,18 +629,8 @@ impl Orderbook {
     
     /// Calculate imbalance (top N levels)
     pub fn imbalance(&self, depth: usize) -> f64 {
-        let bids = self.sorted_bids.read();
-        let asks = self.sorted_asks.read();
-        
-        let bid_volume: f64 = bids.iter()
-            .take(depth)
-            .map(|level| level.quantity.0)
-            .sum();
-        
-        let ask_volume: f64 = asks.iter()
-            .take(depth)
-            .map(|level| level.quantity.0)
-            .sum();
+        let levels = self.levels.read();
+        let (bid_volume, ask_volume) = (levels.bids.volume(depth), levels.asks.volume(depth));
         
         if bid_volume + ask_volume == 0.0 {
             return 0.0;
@@ -236,29 +639,161 @@ impl Orderbook {
         (bid_volume - ask_volume) / (bid_volume + ask_volume)
     }
     
+    /// Size-weighted microprice: `(bid * ask_size + ask * bid_size) / (bid_size + ask_size)`
+    /// Leans towards the side with less size, where the next trade more likely moves the price
+    pub fn microprice(&self) -> Option<f64> {
+        let levels = self.levels.read();
+        let (bid, bid_qty) = levels.bids.top(1).next()?;
+        let (ask, ask_qty) = levels.asks.top(1).next()?;
+        Some((bid * ask_qty + ask * bid_qty) / (bid_qty + ask_qty))
+    }
+
+    /// Mean of the bid and ask VWAPs over the top `depth` levels
+    pub fn weighted_mid(&self, depth: usize) -> Option<f64> {
+        let levels = self.levels.read();
+        let bid = Side::vwap(levels.bids.top(depth.max(1)))?;
+        let ask = Side::vwap(levels.asks.top(depth.max(1)))?;
+        Some((bid + ask) / 2.0)
+    }
+
+    /// Mean of the bid and ask VWAPs over levels within `bps` of mid (at least the best level)
+    pub fn weighted_mid_bps(&self, bps: f64) -> Option<f64> {
+        let levels = self.levels.read();
+        let mid = (levels.bids.best()? + levels.asks.best()?) / 2.0;
+        let band = mid * bps / 10_000.0;
+        let bid = Side::vwap(levels.bids.top_while(|price| price >= mid - band))?;
+        let ask = Side::vwap(levels.asks.top_while(|price| price <= mid + band))?;
+        Some((bid + ask) / 2.0)
+    }
+
+    /// Mid plus half the spread times the `depth`-level imbalance
+    /// Equals the microprice at depth 1 and uses more of the book beyond that
+    pub fn imbalance_microprice(&self, depth: usize) -> Option<f64> {
+        let levels = self.levels.read();
+        let (bid, ask) = (levels.bids.best()?, levels.asks.best()?);
+        let (bid_volume, ask_volume) = (levels.bids.volume(depth.max(1)), levels.asks.volume(depth.max(1)));
+        let imbalance = (bid_volume - ask_volume) / (bid_volume + ask_volume);
+        Some((bid + ask) / 2.0 + (ask - bid) / 2.0 * imbalance)
+    }
+
+    /// Price selected by `reference`, falling back to mid while the book is one-sided
+    pub fn fair_value(&self, reference: ReferencePrice) -> f64 {
+        let estimate = match reference {
+            ReferencePrice::Mid => None,
+            ReferencePrice::Microprice => self.microprice(),
+            ReferencePrice::WeightedMid { depth } => self.weighted_mid(depth),
+            ReferencePrice::WeightedMidBand { bps } => self.weighted_mid_bps(bps),
+            ReferencePrice::ImbalanceMicroprice { depth } => self.imbalance_microprice(depth),
+        };
+        estimate.unwrap_or_else(|| self.mid_price())
+    }
+    
+    /// Walk the opposite side of the book for a market order of `qty` (book size units)
+    /// Inverse books quote USD contracts, so their VWAP is the harmonic mean of level prices.
+    /// `None` while either side of the book is empty
+    pub fn estimate_impact(&self, side: AggressorSide, qty: f64) -> Option<ImpactEstimate> {
+        let levels = self.levels.read();
+        let mid = (levels.bids.best()? + levels.asks.best()?) / 2.0;
+        let book = match side {
+            AggressorSide::Buy => &levels.asks,
+            AggressorSide::Sell => &levels.bids,
+        };
+        let inverse = self.instrument.as_ref().is_some_and(|spec| spec.category == Category::Inverse);
+
+        let mut remaining = qty;
+        let mut weighted = 0.0;
+        let mut worst_price = mid;
+        let mut levels_consumed = 0;
+        for (price, size) in book.top(usize::MAX) {
+            if remaining <= 0.0 {
+                break;
+            }
+            let take = size.min(remaining);
+            weighted += if inverse { take / price } else { take * price };
+            remaining -= take;
+            worst_price = price;
+            levels_consumed += 1;
+        }
+
+        let filled_qty = qty - remaining;
+        let vwap = match (filled_qty > 0.0, inverse) {
+            (false, _) => mid,
+            (true, true) => filled_qty / weighted,
+            (true, false) => weighted / filled_qty,
+        };
+        let slippage_bps = match side {
+            AggressorSide::Buy => (vwap - mid) / mid * 10_000.0,
+            AggressorSide::Sell => (mid - vwap) / mid * 10_000.0,
+        };
+
+        Some(ImpactEstimate {
+            vwap,
+            worst_price,
+            slippage_bps,
+            levels_consumed,
+            filled_qty,
+            unfilled_qty: remaining.max(0.0),
+        })
+    }
+
+    /// Bid and ask size counted by `measure` over the whole book
+    pub fn depth(&self, measure: DepthMeasure) -> (f64, f64) {
+        let levels = self.levels.read();
+        let mid = levels.mid();
+        (
+            measure.volume(levels.bids.top(usize::MAX), mid),
+            measure.volume(levels.asks.top(usize::MAX), mid),
+        )
+    }
+
+    /// Total liquidity counted by `measure`; comparable across symbols and tick sizes for bands and decay
+    pub fn liquidity(&self, measure: DepthMeasure) -> f64 {
+        let (bid_volume, ask_volume) = self.depth(measure);
+        bid_volume + ask_volume
+    }
+
+    /// Imbalance counted by `measure`, -1 (asks) to 1 (bids)
+    pub fn imbalance_by(&self, measure: DepthMeasure) -> f64 {
+        let (bid_volume, ask_volume) = self.depth(measure);
+        if bid_volume + ask_volume == 0.0 {
+            return 0.0;
+        }
+        (bid_volume - ask_volume) / (bid_volume + ask_volume)
+    }
+
+    /// Quote-currency value of `qty` at `price`; sizes are taken as base coin without instrument metadata
+    pub fn notional(&self, qty: f64, price: f64) -> f64 {
+        self.instrument.as_ref().map_or(qty * price, |spec| spec.notional(qty, price))
+    }
+
+    /// Bid and ask depth counted by `measure`, valued in the quote currency level by level
+    pub fn depth_notional(&self, measure: DepthMeasure) -> (f64, f64) {
+        let levels = self.levels.read();
+        let mid = levels.mid();
+        let valued = |(price, qty): (f64, f64)| (price, self.notional(qty, price));
+        (
+            measure.volume(levels.bids.top(usize::MAX).map(valued), mid),
+            measure.volume(levels.asks.top(usize::MAX).map(valued), mid),
+        )
+    }
+
+    /// Total liquidity counted by `measure` in the quote currency, comparable across symbols
+    pub fn liquidity_notional(&self, measure: DepthMeasure) -> f64 {
+        let (bid_notional, ask_notional) = self.depth_notional(measure);
+        bid_notional + ask_notional
+    }
+
     /// Get total liquidity in top N levels
     pub fn liquidity_depth(&self, depth: usize) -> f64 {
-        let bids = self.sorted_bids.read();
-        let asks = self.sorted_asks.read();
-        
-        let bid_volume: f64 = bids.iter()
-            .take(depth)
-            .map(|level| level.quantity.0)
-            .sum();
-        
-        let ask_volume: f64 = asks.iter()
-            .take(depth)
-            .map(|level| level.quantity.0)
-            .sum();
-        
+        let levels = self.levels.read();
+        let (bid_volume, ask_volume) = (levels.bids.volume(depth), levels.asks.volume(depth));
         bid_volume + ask_volume
     }
     
     /// Get latency since last update
     pub fn latency_ms(&self) -> u64 {
         let last_update = self.last_update_time.load(Ordering::Relaxed);
-        let now = chrono::Utc::now().timestamp_millis() as u64;
-        now.saturating_sub(last_update)
+        self.clock.now_ms().saturating_sub(last_update)
     }
     
     /// Get update count
@@ -267,30 +802,34 @@ impl Orderbook {
     }
 
     /// Get sorted bid and ask levels for advanced metrics
-    pub fn get_sorted_levels(&self, depth: usize) -> (Vec<(f64, f64)>, Vec<(f64, f64)>) {
-        let bids = self.sorted_bids.read();
-        let asks = self.sorted_asks.read();
-
-        let bid_levels: Vec<(f64, f64)> = bids.iter()
-            .take(depth)
-            .map(|level| (level.price.0, level.quantity.0))
-            .collect();
-
-        let ask_levels: Vec<(f64, f64)> = asks.iter()
-            .take(depth)
-            .map(|level| (level.price.0, level.quantity.0))
-            .collect();
-
+    pub fn get_sorted_levels(&self, depth: usize) -> (LevelList, LevelList) {
+        let mut bid_levels = Vec::with_capacity(depth.min(256));
+        let mut ask_levels = Vec::with_capacity(depth.min(256));
+        self.copy_sorted_levels(depth, &mut bid_levels, &mut ask_levels);
         (bid_levels, ask_levels)
     }
 
+    /// Top `depth` levels into caller-owned buffers, best first
+    /// Reusing the buffers keeps repeated reads allocation-free
+    pub fn copy_sorted_levels(&self, depth: usize, bids: &mut Vec<(f64, f64)>, asks: &mut Vec<(f64, f64)>) {
+        let levels = self.levels.read();
+        bids.clear();
+        bids.extend(levels.bids.top(depth));
+        asks.clear();
+        asks.extend(levels.asks.top(depth));
+    }
+
     /// Update advanced metrics (call after orderbook updates)
-    pub fn update_metrics(&self, depth_levels: &[usize], whale_threshold: f64) {
-        let (bid_levels, ask_levels) = self.get_sorted_levels(50);
+    /// Imbalance depths may be bps bands or decayed, so they see the whole book;
+    /// volume and whale metrics keep to the top 50 levels
+    pub fn update_metrics(&self, depth_measures: &[DepthMeasure], whale_threshold: f64) {
+        let (all_bids, all_asks) = self.get_sorted_levels(usize::MAX);
 
-        if bid_levels.is_empty() || ask_levels.is_empty() {
+        if all_bids.is_empty() || all_asks.is_empty() {
             return;
         }
+        let bid_levels = &all_bids[..all_bids.len().min(50)];
+        let ask_levels = &all_asks[..all_asks.len().min(50)];
 
         let mut metrics = self.metrics.write();
 
@@ -302,26 +841,59 @@ impl Orderbook {
         metrics.add_snapshot(bid_volume, ask_volume);
 
         // Update average order size
-        metrics.update_avg_order_size(&bid_levels);
-        metrics.update_avg_order_size(&ask_levels);
+        metrics.update_avg_order_size(bid_levels);
+        metrics.update_avg_order_size(ask_levels);
 
         // Detect whales
-        metrics.detect_whales(&bid_levels, &ask_levels, whale_threshold);
+        metrics.detect_whales(bid_levels, ask_levels, whale_threshold);
 
         // Calculate multi-level imbalance
-        metrics.calculate_multi_level_imbalance(&bid_levels, &ask_levels, depth_levels);
+        metrics.calculate_imbalance_by_measure(&all_bids, &all_asks, depth_measures);
 
         // Calculate pressure
         let (best_bid, best_ask) = self.best_bid_ask();
         metrics.calculate_pressure(best_bid, best_ask);
     }
 
+    /// Feed executed trades to the metrics, with the size the book shows at each trade's price
+    pub fn record_trades(&self, trades: &[Trade]) {
+        if trades.is_empty() {
+            return;
+        }
+        let displayed: Vec<f64> = {
+            let levels = self.levels.read();
+            trades.iter()
+                .map(|trade| match trade.side {
+                    AggressorSide::Buy => levels.asks.size_at(trade.price),
+                    AggressorSide::Sell => levels.bids.size_at(trade.price),
+                })
+                .collect()
+        };
+        self.metrics.write().record_trades(trades, &displayed);
+    }
+
+    /// Icebergs detected since the last call
+    pub fn take_new_icebergs(&self) -> Vec<super::iceberg::Iceberg> {
+        self.metrics.write().take_new_icebergs()
+    }
+
     /// Get advanced metrics
-    pub fn get_metrics(&self) -> parking_lot::RwLockReadGuard<super::metrics::OrderbookMetrics> {
+    pub fn get_metrics(&self) -> parking_lot::RwLockReadGuard<'_, super::metrics::OrderbookMetrics> {
         self.metrics.read()
     }
 }
 
+/// Apply one side of an `orderbook.1` frame to the current top level
+fn apply_top_level(side: &mut Option<(f64, f64)>, levels: Vec<(f64, f64)>) {
+    for (price, qty) in levels {
+        if qty > 0.0 {
+            *side = Some((price, qty));
+        } else if side.is_some_and(|(current, _)| current == price) {
+            *side = None;
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -352,4 +924,205 @@ mod tests {
         let imbalance = ob.imbalance(2);
         assert!(imbalance > 0.0); // More bids than asks
     }
+
+    #[test]
+    fn test_delta_after_snapshot_is_not_a_snapshot() {
+        let ob = Orderbook::new("BTCUSDT".to_string());
+
+        let status = ob.apply_update(UpdateKind::Snapshot, vec![(50000.0, 1.0), (49999.0, 1.0)], vec![(50001.0, 1.0)], 100, 1000);
+        assert_eq!(status, SequenceStatus::Applied);
+
+        // A delta must only touch the listed level
+        let status = ob.apply_update(UpdateKind::Delta, vec![(50000.0, 0.0)], vec![], 101, 1001);
+        assert_eq!(status, SequenceStatus::Applied);
+
+        let (bid, ask) = ob.best_bid_ask();
+        assert_eq!(bid, 49999.0);
+        assert_eq!(ask, 50001.0);
+    }
+
+    #[test]
+    fn test_levels_stay_ordered_through_deltas() {
+        let ob = Orderbook::new("BTCUSDT".to_string());
+        ob.apply_snapshot(vec![(49998.0, 1.0), (50000.0, 2.0), (49999.0, 3.0)], vec![(50003.0, 1.0), (50001.0, 2.0)]);
+
+        ob.apply_delta(vec![(50000.5, 4.0), (50000.0, 0.0), (49990.0, 0.0)], vec![(50002.0, 5.0), (50001.0, 0.0)]);
+
+        let (mut bids, mut asks) = (vec![(0.0, 0.0); 8], Vec::new());
+        ob.copy_sorted_levels(2, &mut bids, &mut asks);
+        assert_eq!(bids, vec![(50000.5, 4.0), (49999.0, 3.0)]);
+        assert_eq!(asks, vec![(50002.0, 5.0), (50003.0, 1.0)]);
+        assert_eq!(ob.best_bid_ask(), (50000.5, 50002.0));
+        assert_eq!(ob.liquidity_depth(10), 14.0);
+
+        // Emptying the top moves the best price to the next level
+        ob.apply_delta(vec![(50000.5, 0.0)], vec![]);
+        assert_eq!(ob.best_bid_ask().0, 49999.0);
+        assert_eq!(ob.get_sorted_levels(10).0.len(), 2);
+    }
+
+    #[test]
+    fn test_delta_emits_level_events() {
+        let ob = Orderbook::new("BTCUSDT".to_string());
+        ob.apply_snapshot(vec![(50000.0, 1.0), (49999.0, 2.0)], vec![(50002.0, 1.0), (50003.0, 1.0)]);
+
+        let events = ob.apply_delta(
+            vec![(50001.0, 3.0), (49999.0, 0.5)],
+            vec![(50002.0, 0.0), (50003.0, 1.0)],
+        );
+        let kinds: Vec<_> = events.iter().map(|e| (e.side, e.price, e.level, e.kind)).collect();
+        assert_eq!(kinds, vec![
+            (OrderSide::Bid, 50001.0, 0, BookEventKind::Add { qty: 3.0 }),
+            (OrderSide::Bid, 49999.0, 2, BookEventKind::Reduce { qty: 1.5 }),
+            (OrderSide::Bid, 50001.0, 0, BookEventKind::BestPrice { previous: 50000.0 }),
+            (OrderSide::Ask, 50002.0, 0, BookEventKind::Remove { qty: 1.0 }),
+            (OrderSide::Ask, 50003.0, 0, BookEventKind::BestPrice { previous: 50002.0 }),
+        ]);
+        assert_eq!(events.iter().map(|e| e.size).collect::<Vec<_>>(), vec![3.0, 0.5, 3.0, 0.0, 1.0]);
+
+        // New bid plus pulled ask is buying flow at the top
+        let metrics = ob.get_metrics();
+        assert_eq!(metrics.ofi(60_000, 1), 4.0);
+        assert_eq!(metrics.ofi(60_000, 5), 2.5);
+    }
+
+    #[test]
+    fn test_impact_walks_levels() {
+        let ob = Orderbook::new("BTCUSDT".to_string());
+        ob.apply_snapshot(vec![(99.0, 1.0), (98.0, 1.0)], vec![(101.0, 1.0), (102.0, 2.0), (104.0, 5.0)]);
+
+        // 1 @ 101 + 1 @ 102, against a mid of 100
+        let buy = ob.estimate_impact(AggressorSide::Buy, 2.0).unwrap();
+        assert_eq!((buy.vwap, buy.worst_price, buy.levels_consumed), (101.5, 102.0, 2));
+        assert_eq!(buy.slippage_bps, 150.0);
+        assert_eq!(buy.unfilled_qty, 0.0);
+
+        // More than the bids hold
+        let sell = ob.estimate_impact(AggressorSide::Sell, 3.0).unwrap();
+        assert_eq!((sell.vwap, sell.worst_price, sell.filled_qty, sell.unfilled_qty), (98.5, 98.0, 2.0, 1.0));
+        assert_eq!(sell.slippage_bps, 150.0);
+
+        // Inverse: 100 USD at 100 and 100 USD at 200 buy 1.5 coins, 133.33 per coin
+        let inverse = Orderbook::new("BTCUSD".to_string())
+            .with_instrument(InstrumentSpec::fallback("BTCUSD", Category::Inverse));
+        inverse.apply_snapshot(vec![(50.0, 100.0)], vec![(100.0, 100.0), (200.0, 100.0)]);
+        let buy = inverse.estimate_impact(AggressorSide::Buy, 200.0).unwrap();
+        assert!((buy.vwap - 400.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_fair_value_estimators() {
+        let ob = Orderbook::new("BTCUSDT".to_string());
+        ob.apply_snapshot(vec![(100.0, 3.0), (99.0, 1.0)], vec![(102.0, 1.0), (104.0, 3.0)]);
+
+        // Thin ask: the microprice leans towards it
+        assert_eq!(ob.microprice(), Some(101.5));
+        assert_eq!(ob.imbalance_microprice(1), Some(101.5));
+        assert_eq!(ob.imbalance_microprice(2), Some(101.0));
+        // (99.75 + 103.5) / 2
+        assert_eq!(ob.weighted_mid(2), Some(101.625));
+        // 150 bps of 101 keeps 99.5..102.5: only the best level on each side
+        assert_eq!(ob.weighted_mid_bps(150.0), Some(101.0));
+        assert_eq!(ob.weighted_mid_bps(300.0), ob.weighted_mid(2));
+
+        assert_eq!(ob.fair_value(ReferencePrice::from_config("microprice", 5, 0.0)), 101.5);
+        assert_eq!(ob.fair_value(ReferencePrice::from_config("unknown", 5, 0.0)), ob.mid_price());
+        assert_eq!(ReferencePrice::from_config("weighted_mid", 5, 10.0), ReferencePrice::WeightedMidBand { bps: 10.0 });
+        // Every accepted value has its own variant; only "mid" maps to Mid
+        for value in ReferencePrice::CONFIG_VALUES {
+            assert_eq!(ReferencePrice::from_config(value, 5, 0.0) == ReferencePrice::Mid, *value == "mid");
+        }
+    }
+
+    #[test]
+    fn test_spread_in_ticks() {
+        let ob = Orderbook::new("BTCUSDT".to_string());
+        assert_eq!(ob.spread_ticks(), None);
+
+        let spec = InstrumentSpec { tick_size: 0.1, ..InstrumentSpec::fallback("BTCUSDT", Category::Linear) };
+        let ob = Orderbook::new("BTCUSDT".to_string()).with_instrument(spec);
+        assert_eq!(ob.spread_ticks(), None);
+        ob.apply_snapshot(vec![(50000.0, 1.0)], vec![(50000.3, 1.0)]);
+        assert_eq!(ob.spread_ticks(), Some(3.0));
+    }
+
+    #[test]
+    fn test_liquidity_in_quote_currency() {
+        let bids = vec![(100.0, 2.0), (99.0, 1.0)];
+        let asks = vec![(101.0, 1.0), (102.0, 3.0)];
+
+        // Linear: base coin times price
+        let ob = Orderbook::new("SOLUSDT".to_string())
+            .with_instrument(InstrumentSpec::fallback("SOLUSDT", Category::Linear));
+        ob.apply_snapshot(bids.clone(), asks.clone());
+        assert_eq!(ob.depth_notional(DepthMeasure::Levels(1)), (200.0, 101.0));
+        assert_eq!(ob.liquidity_notional(DepthMeasure::Levels(10)), 200.0 + 99.0 + 101.0 + 306.0);
+
+        // 1000PEPEUSDT is sized and priced per 1000 PEPE, so its value is still qty × price
+        let spec = InstrumentSpec { contract_size: 1000.0, ..InstrumentSpec::fallback("1000PEPEUSDT", Category::Linear) };
+        let ob = Orderbook::new("1000PEPEUSDT".to_string()).with_instrument(spec);
+        ob.apply_snapshot(bids.clone(), asks.clone());
+        assert_eq!(ob.depth_notional(DepthMeasure::Levels(1)), (200.0, 101.0));
+
+        // Inverse sizes already are USD
+        let ob = Orderbook::new("BTCUSD".to_string())
+            .with_instrument(InstrumentSpec::fallback("BTCUSD", Category::Inverse));
+        ob.apply_snapshot(bids, asks);
+        assert_eq!(ob.liquidity_notional(DepthMeasure::Levels(10)), ob.liquidity_depth(10));
+    }
+
+    #[test]
+    fn test_sequence_gap_requires_snapshot() {
+        let ob = Orderbook::new("BTCUSDT".to_string());
+        ob.apply_update(UpdateKind::Snapshot, vec![(50000.0, 1.0)], vec![(50001.0, 1.0)], 10, 500);
+        assert!(ob.is_synced());
+
+        // Duplicate frame is ignored
+        let status = ob.apply_update(UpdateKind::Delta, vec![(50000.0, 5.0)], vec![], 10, 500);
+        assert_eq!(status, SequenceStatus::Stale { last_update_id: 10, update_id: 10 });
+
+        // Skipping u=11 is a gap
+        let status = ob.apply_update(UpdateKind::Delta, vec![(50000.0, 5.0)], vec![], 12, 502);
+        assert_eq!(status, SequenceStatus::Gap { expected: 11, received: 12 });
+        assert!(!ob.is_synced());
+
+        // Deltas are dropped until a snapshot arrives
+        let status = ob.apply_update(UpdateKind::Delta, vec![(50000.0, 5.0)], vec![], 13, 503);
+        assert_eq!(status, SequenceStatus::AwaitingSnapshot);
+
+        ob.apply_update(UpdateKind::Snapshot, vec![(50000.0, 2.0)], vec![(50001.0, 1.0)], 20, 600);
+        assert!(ob.is_synced());
+        assert_eq!(ob.last_update_id(), 20);
+    }
+
+    #[test]
+    fn test_bbo_feed_drives_best_prices_and_flags_mismatch() {
+        let ob = Orderbook::new("BTCUSDT".to_string()).with_bbo_feed();
+        ob.apply_update(UpdateKind::Snapshot, vec![(50000.0, 1.0), (49999.0, 1.0)], vec![(50002.0, 1.0)], 1, 1);
+        assert_eq!(ob.best_bid_ask(), (50000.0, 50002.0));
+
+        // The BBO feed takes over best prices; depth metrics still come from the deep book
+        ob.apply_bbo(UpdateKind::Snapshot, vec![(50001.0, 0.5)], vec![(50002.0, 1.0)], 10);
+        assert_eq!(ob.best_bid_ask(), (50001.0, 50002.0));
+        assert_eq!(ob.liquidity_depth(10), 3.0);
+
+        assert_eq!(ob.check_bbo(Duration::from_millis(20)), None);
+        std::thread::sleep(Duration::from_millis(30));
+        let mismatch = ob.check_bbo(Duration::from_millis(20)).unwrap();
+        assert_eq!((mismatch.bbo, mismatch.book), ((50001.0, 50002.0), (50000.0, 50002.0)));
+        assert!(ob.bbo_diverged());
+        // Reported once per episode
+        assert_eq!(ob.check_bbo(Duration::from_millis(20)), None);
+
+        ob.apply_update(UpdateKind::Delta, vec![(50001.0, 0.5)], vec![], 2, 2);
+        assert_eq!(ob.check_bbo(Duration::from_millis(20)), None);
+        assert!(!ob.bbo_diverged());
+        assert_eq!(ob.bbo_mismatches(), 1);
+
+        // A zero size at the current top empties that side; stale deltas are ignored
+        ob.apply_bbo(UpdateKind::Delta, vec![(50001.0, 0.0), (50000.5, 2.0)], vec![], 11);
+        assert_eq!(ob.best_bid_ask(), (50000.5, 50002.0));
+        let status = ob.apply_bbo(UpdateKind::Delta, vec![(49000.0, 1.0)], vec![], 11);
+        assert_eq!(status, SequenceStatus::Stale { last_update_id: 11, update_id: 11 });
+    }
 }
@@ -1,5 +1,17 @@
 use std::collections::{HashMap, VecDeque};
-use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::market_data::{InstrumentSpec, Trade};
+use crate::utils::Clock;
+use super::iceberg::{Iceberg, IcebergConfig, IcebergDetector};
+use super::manager::BookEvent;
+use super::ofi::OfiCalculator;
+use super::whales::{WallOutcome, WhaleTracker};
+
+/// Window over which pulled walls discount a side's whale score
+const SPOOF_WINDOW_MS: u64 = 60_000;
+
+/// Shortest OFI history kept, in ms
+const DEFAULT_OFI_HORIZON_MS: u64 = 60_000;
 
 /// Snapshot of orderbook volume at a specific time
 #[derive(Debug, Clone)]
@@ -15,17 +27,71 @@ pub struct VolumeSnapshot {
 pub struct LargeOrder {
     pub price: f64,
     pub size: f64,
+    pub notional: f64, // Size in the quote currency
     pub side: OrderSide,
     pub timestamp_ms: u64,
     pub size_multiplier: f64, // How many times larger than average
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum OrderSide {
     Bid,
     Ask,
 }
 
+/// How far into the book depth and imbalance reach
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DepthMeasure {
+    /// Top N levels per side, whatever prices they span
+    Levels(usize),
+    /// Levels within `bps` of mid
+    Band { bps: f64 },
+    /// Every level, weighted by exp(-distance / `decay_bps`) from mid
+    Decay { decay_bps: f64 },
+}
+
+impl DepthMeasure {
+    /// Modes accepted by `from_config`
+    pub const CONFIG_MODES: &'static [&'static str] = &["levels", "bps", "decay"];
+
+    /// Parse a mode ("levels", "bps" or "decay") and its size, defaulting to levels
+    pub fn from_config(mode: &str, value: f64) -> Self {
+        match mode {
+            "bps" => DepthMeasure::Band { bps: value },
+            "decay" => DepthMeasure::Decay { decay_bps: value },
+            _ => DepthMeasure::Levels(value as usize),
+        }
+    }
+
+    /// Level counts in "levels" mode, otherwise one band or decay length per `bands_bps` entry
+    pub fn list_from_config(mode: &str, levels: &[usize], bands_bps: &[f64]) -> Vec<Self> {
+        match mode {
+            "bps" | "decay" => bands_bps.iter().map(|&bps| Self::from_config(mode, bps)).collect(),
+            _ => levels.iter().map(|&depth| DepthMeasure::Levels(depth)).collect(),
+        }
+    }
+
+    /// Size on one side of the book, levels given best first
+    /// Band and decay measures count nothing without a mid
+    pub fn volume(&self, levels: impl Iterator<Item = (f64, f64)>, mid: Option<f64>) -> f64 {
+        let distance_bps = |price: f64, mid: f64| (price - mid).abs() / mid * 10_000.0;
+        match (*self, mid) {
+            (DepthMeasure::Levels(depth), _) => levels.take(depth).map(|(_, qty)| qty).sum(),
+            (DepthMeasure::Band { bps }, Some(mid)) => levels
+                .take_while(|(price, _)| distance_bps(*price, mid) <= bps)
+                .map(|(_, qty)| qty)
+                .sum(),
+            // Beyond 10 decay lengths a level weighs under 0.005%
+            (DepthMeasure::Decay { decay_bps }, Some(mid)) if decay_bps > 0.0 => levels
+                .map(|(price, qty)| (distance_bps(price, mid), qty))
+                .take_while(|(distance, _)| *distance <= decay_bps * 10.0)
+                .map(|(distance, qty)| qty * (-distance / decay_bps).exp())
+                .sum(),
+            _ => 0.0,
+        }
+    }
+}
+
 /// Advanced metrics calculator for orderbook analysis
 pub struct OrderbookMetrics {
     /// Historical volume snapshots (last 60 seconds)
@@ -35,16 +101,13 @@ pub struct OrderbookMetrics {
     max_history_size: usize,
 
     /// Imbalance calculated at different depths
-    imbalance_by_depth: HashMap<usize, f64>,
+    imbalance_by_depth: Vec<(DepthMeasure, f64)>,
 
     /// Average order size across all levels
     avg_order_size: f64,
 
-    /// Recently detected large orders
-    large_orders: VecDeque<LargeOrder>,
-
-    /// Maximum large orders to track
-    max_large_orders: usize,
+    /// Large resting levels and how they left the book (last 5 minutes)
+    whales: WhaleTracker,
 
     /// Bid pressure (rate of change in bid volume)
     bid_pressure: f64,
@@ -60,6 +123,15 @@ pub struct OrderbookMetrics {
 
     /// Previous timestamp for pressure calculation
     prev_timestamp_ms: u64,
+
+    /// Order-flow imbalance from level events (last 60 seconds)
+    ofi: OfiCalculator,
+
+    /// Levels trading more than they show
+    icebergs: IcebergDetector,
+
+    /// "Now" for the history windows
+    clock: Clock,
 }
 
 impl OrderbookMetrics {
@@ -67,24 +139,48 @@ impl OrderbookMetrics {
         Self {
             volume_history: VecDeque::new(),
             max_history_size: 60, // 60 seconds of history
-            imbalance_by_depth: HashMap::new(),
+            imbalance_by_depth: Vec::new(),
             avg_order_size: 0.0,
-            large_orders: VecDeque::new(),
-            max_large_orders: 20,
+            whales: WhaleTracker::new(300_000),
             bid_pressure: 0.0,
             ask_pressure: 0.0,
             prev_best_bid: 0.0,
             prev_best_ask: 0.0,
             prev_timestamp_ms: 0,
+            ofi: OfiCalculator::new(DEFAULT_OFI_HORIZON_MS),
+            icebergs: IcebergDetector::new(IcebergConfig::default()),
+            clock: Clock::wall(),
         }
     }
 
+    /// Measure windows against `clock` instead of the wall clock (replays)
+    pub fn set_clock(&mut self, clock: Clock) {
+        self.clock = clock;
+    }
+
+    /// Replace the iceberg detection thresholds, dropping what was tracked so far
+    pub fn set_iceberg_config(&mut self, config: IcebergConfig) {
+        self.icebergs = IcebergDetector::new(config);
+    }
+
+    /// Keep OFI history for at least `horizon_ms`, dropping what was recorded so far
+    pub fn set_ofi_horizon(&mut self, horizon_ms: u64) {
+        self.ofi = OfiCalculator::new(horizon_ms.max(DEFAULT_OFI_HORIZON_MS));
+    }
+
+    /// Value whale sizes with the symbol's contract size
+    pub fn set_instrument(&mut self, spec: InstrumentSpec) {
+        self.whales.set_instrument(spec);
+    }
+
+    /// Ignore whale candidates worth less than `min_notional` in the quote currency
+    pub fn set_min_whale_notional(&mut self, min_notional: f64) {
+        self.whales.set_min_notional(min_notional);
+    }
+
     /// Add a volume snapshot to history
     pub fn add_snapshot(&mut self, bid_volume: f64, ask_volume: f64) {
-        let timestamp_ms = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_millis() as u64;
+        let timestamp_ms = self.clock.now_ms();
 
         let snapshot = VolumeSnapshot {
             timestamp_ms,
@@ -102,16 +198,14 @@ impl OrderbookMetrics {
     }
 
     /// Calculate volume delta over a time window
-    /// Returns the change in total volume over the specified window
+    /// Returns the change in total resting volume over the specified window
+    /// (book liquidity change, not executed flow - see `TradeTape::cvd`)
     pub fn calculate_volume_delta(&self, window_ms: u64) -> f64 {
         if self.volume_history.len() < 2 {
             return 0.0;
         }
 
-        let now_ms = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_millis() as u64;
+        let now_ms = self.clock.now_ms();
 
         let cutoff_time = now_ms.saturating_sub(window_ms);
 
@@ -144,10 +238,7 @@ impl OrderbookMetrics {
             return (0.0, 0.0);
         }
 
-        let now_ms = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_millis() as u64;
+        let now_ms = self.clock.now_ms();
 
         let cutoff_time = now_ms.saturating_sub(window_ms);
 
@@ -182,61 +273,21 @@ impl OrderbookMetrics {
     }
 
     /// Detect whale orders (orders significantly larger than average)
+    /// A wall seen on earlier scans is the same order, reported with its first-seen time
     pub fn detect_whales(
         &mut self,
         bid_levels: &[(f64, f64)],
         ask_levels: &[(f64, f64)],
         threshold_multiplier: f64,
     ) -> Vec<LargeOrder> {
-        let timestamp_ms = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_millis() as u64;
-
-        let mut whales = Vec::new();
+        let timestamp_ms = self.clock.now_ms();
 
         if self.avg_order_size == 0.0 {
-            return whales;
+            return Vec::new();
         }
 
         let threshold = self.avg_order_size * threshold_multiplier;
-
-        // Check bid side
-        for (price, qty) in bid_levels {
-            if *qty > threshold {
-                let whale = LargeOrder {
-                    price: *price,
-                    size: *qty,
-                    side: OrderSide::Bid,
-                    timestamp_ms,
-                    size_multiplier: qty / self.avg_order_size,
-                };
-                whales.push(whale.clone());
-                self.large_orders.push_back(whale);
-            }
-        }
-
-        // Check ask side
-        for (price, qty) in ask_levels {
-            if *qty > threshold {
-                let whale = LargeOrder {
-                    price: *price,
-                    size: *qty,
-                    side: OrderSide::Ask,
-                    timestamp_ms,
-                    size_multiplier: qty / self.avg_order_size,
-                };
-                whales.push(whale.clone());
-                self.large_orders.push_back(whale);
-            }
-        }
-
-        // Trim old large orders
-        while self.large_orders.len() > self.max_large_orders {
-            self.large_orders.pop_front();
-        }
-
-        whales
+        self.whales.observe(timestamp_ms, bid_levels, ask_levels, threshold, self.avg_order_size)
     }
 
     /// Calculate imbalance at multiple depth levels
@@ -246,28 +297,41 @@ impl OrderbookMetrics {
         ask_levels: &[(f64, f64)],
         depths: &[usize],
     ) -> HashMap<usize, f64> {
-        let mut imbalances = HashMap::new();
+        let measures: Vec<_> = depths.iter().map(|&depth| DepthMeasure::Levels(depth)).collect();
+        self.calculate_imbalance_by_measure(bid_levels, ask_levels, &measures)
+            .into_iter()
+            .zip(depths)
+            .map(|((_, imbalance), &depth)| (depth, imbalance))
+            .collect()
+    }
 
-        for &depth in depths {
-            let bid_volume: f64 = bid_levels.iter()
-                .take(depth)
-                .map(|(_, qty)| qty)
-                .sum();
+    /// Calculate imbalance at several depths, each counted in levels, a bps band or with distance decay
+    /// Levels are best first; band and decay depths only see as far as the levels passed in
+    pub fn calculate_imbalance_by_measure(
+        &mut self,
+        bid_levels: &[(f64, f64)],
+        ask_levels: &[(f64, f64)],
+        measures: &[DepthMeasure],
+    ) -> Vec<(DepthMeasure, f64)> {
+        let mid = match (bid_levels.first(), ask_levels.first()) {
+            (Some((bid, _)), Some((ask, _))) => Some((bid + ask) / 2.0),
+            _ => None,
+        };
 
-            let ask_volume: f64 = ask_levels.iter()
-                .take(depth)
-                .map(|(_, qty)| qty)
-                .sum();
+        let imbalances: Vec<_> = measures.iter()
+            .map(|measure| {
+                let bid_volume = measure.volume(bid_levels.iter().copied(), mid);
+                let ask_volume = measure.volume(ask_levels.iter().copied(), mid);
 
-            let total = bid_volume + ask_volume;
-            let imbalance = if total > 0.0 {
-                (bid_volume - ask_volume) / total
-            } else {
-                0.0
-            };
-
-            imbalances.insert(depth, imbalance);
-        }
+                let total = bid_volume + ask_volume;
+                let imbalance = if total > 0.0 {
+                    (bid_volume - ask_volume) / total
+                } else {
+                    0.0
+                };
+                (*measure, imbalance)
+            })
+            .collect();
 
         self.imbalance_by_depth = imbalances.clone();
         imbalances
@@ -275,10 +339,7 @@ impl OrderbookMetrics {
 
     /// Calculate bid/ask pressure (velocity of price changes)
     pub fn calculate_pressure(&mut self, best_bid: f64, best_ask: f64) -> (f64, f64) {
-        let timestamp_ms = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_millis() as u64;
+        let timestamp_ms = self.clock.now_ms();
 
         if self.prev_timestamp_ms == 0 {
             self.prev_best_bid = best_bid;
@@ -313,7 +374,7 @@ impl OrderbookMetrics {
             return 0.0;
         }
 
-        let imbalances: Vec<f64> = self.imbalance_by_depth.values().copied().collect();
+        let imbalances: Vec<f64> = self.imbalance_by_depth.iter().map(|(_, imbalance)| *imbalance).collect();
 
         // Calculate standard deviation
         let mean = imbalances.iter().sum::<f64>() / imbalances.len() as f64;
@@ -332,38 +393,70 @@ impl OrderbookMetrics {
     }
 
     /// Get recent whale score (0-100)
-    /// Higher score means more recent whale activity
+    /// Higher score means more recent whale activity. Walls that were pulled rather than
+    /// filled don't count, and each side's walls are discounted by its recent spoof rate
     pub fn whale_score(&self, max_age_ms: u64) -> f64 {
-        let now_ms = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_millis() as u64;
+        let now_ms = self.clock.now_ms();
 
         let cutoff = now_ms.saturating_sub(max_age_ms);
 
-        let recent_whales: Vec<_> = self.large_orders.iter()
-            .filter(|w| w.timestamp_ms >= cutoff)
+        let genuine_walls: Vec<_> = self.whales.active_walls()
+            .chain(self.whales.ended_walls().filter(|w| w.outcome == Some(WallOutcome::Filled)))
+            .filter(|w| w.last_seen_ms >= cutoff)
             .collect();
 
-        if recent_whales.is_empty() {
+        if genuine_walls.is_empty() {
             return 0.0;
         }
 
+        let credibility = |side| 1.0 - self.whales.spoof_rate(side, now_ms, SPOOF_WINDOW_MS);
+        let (bid_credibility, ask_credibility) = (credibility(OrderSide::Bid), credibility(OrderSide::Ask));
+
         // Score based on:
         // 1. Number of whales
         // 2. Size multiplier
         // 3. Recency
+        // 4. Whether the side's walls have been holding up
         let mut score = 0.0;
 
-        for whale in recent_whales {
-            let age_factor = 1.0 - ((now_ms - whale.timestamp_ms) as f64 / max_age_ms as f64);
+        for whale in genuine_walls {
+            let age_factor = 1.0 - ((now_ms - whale.last_seen_ms) as f64 / max_age_ms as f64);
             let size_factor = (whale.size_multiplier - 3.0).max(0.0); // Subtract threshold
-            score += age_factor * size_factor * 10.0;
+            let credibility = match whale.side {
+                OrderSide::Bid => bid_credibility,
+                OrderSide::Ask => ask_credibility,
+            };
+            score += age_factor * size_factor * credibility * 10.0;
         }
 
         score.min(100.0)
     }
 
+    /// Spoof rate of each side's walls ended in the last `window_ms`, as (bid, ask) scores 0-100
+    pub fn spoof_scores(&self, window_ms: u64) -> (f64, f64) {
+        let now_ms = self.clock.now_ms();
+
+        (
+            self.whales.spoof_rate(OrderSide::Bid, now_ms, window_ms) * 100.0,
+            self.whales.spoof_rate(OrderSide::Ask, now_ms, window_ms) * 100.0,
+        )
+    }
+
+    /// Largest group of walls pulled together on each side in the last `window_ms`, as (bid, ask)
+    pub fn layering(&self, window_ms: u64) -> (usize, usize) {
+        let now_ms = self.clock.now_ms();
+
+        (
+            self.whales.layering(OrderSide::Bid, now_ms, window_ms),
+            self.whales.layering(OrderSide::Ask, now_ms, window_ms),
+        )
+    }
+
+    /// Tracked walls, resting and ended
+    pub fn whale_tracker(&self) -> &WhaleTracker {
+        &self.whales
+    }
+
     /// Get pressure score (-100 to 100)
     /// Positive means more bid pressure, negative means more ask pressure
     pub fn pressure_score(&self) -> f64 {
@@ -372,6 +465,72 @@ impl OrderbookMetrics {
         (diff * 100.0).clamp(-100.0, 100.0)
     }
 
+    /// Feed level events from `Orderbook::apply_delta` into the OFI, wall tracking and iceberg detection
+    pub fn record_book_events(&mut self, events: &[BookEvent]) {
+        let now_ms = self.clock.now_ms();
+
+        self.ofi.record(now_ms, events);
+        self.whales.record_book_events(now_ms, events);
+        self.icebergs.record_book_events(now_ms, events);
+    }
+
+    /// Credit executed trades to the walls they reached and the levels they traded at
+    /// `displayed[i]` is the book size at trade `i`'s price when it arrived
+    pub fn record_trades(&mut self, trades: &[Trade], displayed: &[f64]) {
+        let now_ms = self.clock.now_ms();
+
+        for (trade, &displayed) in trades.iter().zip(displayed) {
+            self.whales.record_trade(now_ms, trade);
+            self.icebergs.record_trade(now_ms, trade, displayed);
+        }
+    }
+
+    /// Levels currently trading more than they show, largest hidden size first
+    pub fn icebergs(&self) -> Vec<Iceberg> {
+        let now_ms = self.clock.now_ms();
+
+        self.icebergs.icebergs(now_ms)
+    }
+
+    /// Iceberg signal: hidden bid size against hidden ask size, -1 (hidden sellers) to 1 (hidden buyers)
+    pub fn iceberg_score(&self) -> f64 {
+        let now_ms = self.clock.now_ms();
+
+        self.icebergs.hidden_imbalance(now_ms)
+    }
+
+    /// Icebergs detected since the last call, for alerting
+    pub fn take_new_icebergs(&mut self) -> Vec<Iceberg> {
+        let now_ms = self.clock.now_ms();
+
+        self.icebergs.take_new(now_ms)
+    }
+
+    /// Net order-flow imbalance over the top `depth` levels, in book size units
+    pub fn ofi(&self, window_ms: u64, depth: usize) -> f64 {
+        let now_ms = self.clock.now_ms();
+
+        self.ofi.ofi(now_ms, window_ms, depth)
+    }
+
+    /// Order-flow imbalance per depth, normalized to -1 (selling) .. 1 (buying)
+    pub fn ofi_by_depth(&self, window_ms: u64, depths: &[usize]) -> Vec<(usize, f64)> {
+        let now_ms = self.clock.now_ms();
+
+        depths.iter()
+            .map(|&depth| (depth, self.ofi.ofi_ratio(now_ms, window_ms, depth)))
+            .collect()
+    }
+
+    /// Multi-level OFI: the normalized OFI averaged over `depths`, -1 to 1
+    pub fn ofi_score(&self, window_ms: u64, depths: &[usize]) -> f64 {
+        if depths.is_empty() {
+            return 0.0;
+        }
+        let by_depth = self.ofi_by_depth(window_ms, depths);
+        by_depth.iter().map(|(_, ofi)| ofi).sum::<f64>() / by_depth.len() as f64
+    }
+
     /// Get volume delta for multiple windows
     pub fn get_volume_deltas(&self, windows: &[u64]) -> HashMap<u64, f64> {
         windows.iter()
@@ -389,6 +548,7 @@ impl Default for OrderbookMetrics {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::orderbook::manager::BookEventKind;
 
     #[test]
     fn test_volume_snapshot() {
@@ -411,7 +571,7 @@ mod tests {
 
         let whales = metrics.detect_whales(&bid_levels, &ask_levels, 3.0);
 
-        assert!(whales.len() > 0);
+        assert!(!whales.is_empty());
         assert_eq!(whales[0].side, OrderSide::Ask);
     }
 
@@ -430,14 +590,67 @@ mod tests {
         assert!(imbalances[&2] > 0.0); // More bids
     }
 
+    #[test]
+    fn test_band_and_decay_imbalance() {
+        let mut metrics = OrderbookMetrics::new();
+
+        // A big bid 1% away dominates a level count but not the book near mid
+        let bids = vec![(100.0, 1.0), (99.9, 1.0), (99.0, 10.0)];
+        let asks = vec![(100.1, 1.0), (100.2, 1.0), (101.0, 1.0)];
+
+        let imbalances = metrics.calculate_imbalance_by_measure(&bids, &asks, &[
+            DepthMeasure::Levels(3),
+            DepthMeasure::Band { bps: 20.0 },
+            DepthMeasure::Decay { decay_bps: 10.0 },
+        ]);
+
+        assert_eq!(imbalances[0].1, 0.6);
+        assert_eq!(imbalances[1].1, 0.0);
+        assert!(imbalances[2].1.abs() < 1e-3);
+        assert_eq!(DepthMeasure::list_from_config("bps", &[5], &[5.0, 10.0]), vec![
+            DepthMeasure::Band { bps: 5.0 },
+            DepthMeasure::Band { bps: 10.0 },
+        ]);
+        let parsed: Vec<_> = DepthMeasure::CONFIG_MODES.iter()
+            .map(|mode| DepthMeasure::from_config(mode, 10.0))
+            .collect();
+        assert_eq!(parsed, vec![
+            DepthMeasure::Levels(10),
+            DepthMeasure::Band { bps: 10.0 },
+            DepthMeasure::Decay { decay_bps: 10.0 },
+        ]);
+    }
+
+    #[test]
+    fn test_ofi_horizon_covers_long_windows() {
+        let clock = Clock::event();
+        let mut metrics = OrderbookMetrics::new();
+        metrics.set_clock(clock.clone());
+        metrics.set_ofi_horizon(120_000);
+
+        let now = 1_000_000;
+        clock.advance(now);
+        let add = BookEvent {
+            side: OrderSide::Bid,
+            price: 0.0,
+            level: 0,
+            size: 0.0,
+            kind: BookEventKind::Add { qty: 2.0 },
+        };
+        metrics.ofi.record(now - 90_000, &[add]);
+        metrics.ofi.record(now, &[]);
+
+        assert_eq!(metrics.ofi(120_000, 1), 2.0);
+    }
+
     #[test]
     fn test_depth_consistency() {
         let mut metrics = OrderbookMetrics::new();
 
         // Consistent imbalance across depths
-        metrics.imbalance_by_depth.insert(5, 0.5);
-        metrics.imbalance_by_depth.insert(10, 0.52);
-        metrics.imbalance_by_depth.insert(20, 0.48);
+        metrics.imbalance_by_depth.push((DepthMeasure::Levels(5), 0.5));
+        metrics.imbalance_by_depth.push((DepthMeasure::Levels(10), 0.52));
+        metrics.imbalance_by_depth.push((DepthMeasure::Levels(20), 0.48));
 
         let consistency = metrics.depth_consistency();
         assert!(consistency > 0.8); // Should be highly consistent
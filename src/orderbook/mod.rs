@@ -1,7 +1,13 @@
+pub mod iceberg;
 pub mod manager;
 pub mod metrics;
+pub mod ofi;
 pub mod validation;
+pub mod whales;
 
-pub use manager::{Orderbook, OrderbookLevel, Price, Quantity};
-pub use metrics::{OrderbookMetrics, VolumeSnapshot, LargeOrder, OrderSide};
+pub use iceberg::{Iceberg, IcebergConfig, IcebergDetector};
+pub use manager::{BboMismatch, BookEvent, BookEventKind, ImpactEstimate, Orderbook, OrderbookLevel, Price, Quantity, ReferencePrice, UpdateKind, SequenceStatus};
+pub use metrics::{DepthMeasure, OrderbookMetrics, VolumeSnapshot, LargeOrder, OrderSide};
+pub use ofi::OfiCalculator;
 pub use validation::{OrderbookValidator, ValidationResult, ValidationConfig};
+pub use whales::{Wall, WallOutcome, WhaleTracker};
@@ -0,0 +1,109 @@
+use std::collections::VecDeque;
+
+use super::manager::{BookEvent, BookEventKind};
+use super::metrics::OrderSide;
+
+/// Order-flow imbalance from level-by-level book events (Cont, Kukanov & Stoikov)
+///
+/// Size arriving on the bid or leaving the ask is buying pressure, the reverse is
+/// selling pressure. Summed over the top `depth` levels this is multi-level OFI;
+/// at depth 1 it is the classic best-quote OFI, since a better price shows up as an
+/// add at level 0 and a worse one as the removal of the old best.
+pub struct OfiCalculator {
+    /// (timestamp_ms, level, signed contribution)
+    flow: VecDeque<(u64, usize, f64)>,
+    /// History kept, in ms
+    horizon_ms: u64,
+}
+
+impl OfiCalculator {
+    pub fn new(horizon_ms: u64) -> Self {
+        Self {
+            flow: VecDeque::new(),
+            horizon_ms,
+        }
+    }
+
+    /// Signed contribution of one event: positive for bid-side size arriving or ask-side size leaving
+    pub fn contribution(event: &BookEvent) -> f64 {
+        let sign = match event.side {
+            OrderSide::Bid => 1.0,
+            OrderSide::Ask => -1.0,
+        };
+        match event.kind {
+            BookEventKind::Add { qty } => sign * qty,
+            BookEventKind::Reduce { qty } | BookEventKind::Remove { qty } => -sign * qty,
+            BookEventKind::BestPrice { .. } => 0.0,
+        }
+    }
+
+    pub fn record(&mut self, timestamp_ms: u64, events: &[BookEvent]) {
+        for event in events {
+            let contribution = Self::contribution(event);
+            if contribution != 0.0 {
+                self.flow.push_back((timestamp_ms, event.level, contribution));
+            }
+        }
+
+        let cutoff = timestamp_ms.saturating_sub(self.horizon_ms);
+        while self.flow.front().is_some_and(|(ts, _, _)| *ts < cutoff) {
+            self.flow.pop_front();
+        }
+    }
+
+    /// Net OFI over the top `depth` levels in the last `window_ms`, in book size units
+    pub fn ofi(&self, now_ms: u64, window_ms: u64, depth: usize) -> f64 {
+        self.window(now_ms, window_ms, depth).sum()
+    }
+
+    /// OFI scaled by the gross flow in the window: -1 (all selling) to 1 (all buying)
+    pub fn ofi_ratio(&self, now_ms: u64, window_ms: u64, depth: usize) -> f64 {
+        let (net, gross) = self.window(now_ms, window_ms, depth)
+            .fold((0.0, 0.0), |(net, gross), c| (net + c, gross + c.abs()));
+        if gross > 0.0 { net / gross } else { 0.0 }
+    }
+
+    fn window(&self, now_ms: u64, window_ms: u64, depth: usize) -> impl Iterator<Item = f64> + '_ {
+        let cutoff = now_ms.saturating_sub(window_ms);
+        self.flow.iter()
+            .rev()
+            .take_while(move |(ts, _, _)| *ts >= cutoff)
+            .filter(move |(_, level, _)| *level < depth)
+            .map(|(_, _, c)| *c)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(side: OrderSide, level: usize, kind: BookEventKind) -> BookEvent {
+        BookEvent { side, price: 0.0, level, size: 0.0, kind }
+    }
+
+    #[test]
+    fn test_ofi_signs_depths_and_windows() {
+        let mut ofi = OfiCalculator::new(10_000);
+
+        ofi.record(1_000, &[
+            // Bid improves with 2.0, ask at the top is partly pulled
+            event(OrderSide::Bid, 0, BookEventKind::Add { qty: 2.0 }),
+            event(OrderSide::Bid, 0, BookEventKind::BestPrice { previous: 49_999.0 }),
+            event(OrderSide::Ask, 0, BookEventKind::Reduce { qty: 0.5 }),
+            // Deeper ask size arriving is selling pressure, but only past depth 1
+            event(OrderSide::Ask, 3, BookEventKind::Add { qty: 1.5 }),
+        ]);
+        assert_eq!(ofi.ofi(1_000, 1_000, 1), 2.5);
+        assert_eq!(ofi.ofi(1_000, 1_000, 5), 1.0);
+        assert_eq!(ofi.ofi_ratio(1_000, 1_000, 5), 0.25);
+
+        // Best bid lost: the old best's size counts against the bid
+        ofi.record(4_000, &[event(OrderSide::Bid, 0, BookEventKind::Remove { qty: 3.0 })]);
+        assert_eq!(ofi.ofi(4_000, 2_000, 1), -3.0);
+        assert_eq!(ofi.ofi(4_000, 5_000, 1), -0.5);
+
+        // History past the horizon is dropped
+        ofi.record(12_000, &[]);
+        assert_eq!(ofi.ofi(12_000, 60_000, 10), -3.0);
+    }
+}
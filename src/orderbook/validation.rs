@@ -1,6 +1,5 @@
 use std::collections::VecDeque;
-use std::time::{SystemTime, UNIX_EPOCH};
-use super::Orderbook;
+use super::{DepthMeasure, Orderbook};
 
 /// Result of orderbook validation
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -16,7 +15,6 @@ pub enum ValidationResult {
 /// Historical measurement for calculating normal ranges
 #[derive(Debug, Clone)]
 struct Measurement {
-    timestamp_ms: u64,
     spread_pct: f64,
     liquidity: f64,
 }
@@ -53,6 +51,9 @@ pub struct ValidationConfig {
     /// Minimum number of depth levels required
     pub min_depth_levels: usize,
 
+    /// Depth the liquidity check measures (a bps band holds its meaning across symbols and tick sizes)
+    pub liquidity_depth: DepthMeasure,
+
     /// Enable validation
     pub enabled: bool,
 }
@@ -64,6 +65,7 @@ impl Default for ValidationConfig {
             min_liquidity_multiplier: 0.25,
             max_data_age_ms: 5000, // 5 seconds
             min_depth_levels: 5,
+            liquidity_depth: DepthMeasure::Levels(10),
             enabled: true,
         }
     }
@@ -121,7 +123,7 @@ impl OrderbookValidator {
         }
 
         // 6. Check liquidity
-        let liquidity = orderbook.liquidity_depth(10);
+        let liquidity = orderbook.liquidity(self.config.liquidity_depth);
         if self.measurements.len() >= 10 { // Need history to validate
             let min_normal_liquidity = self.normal_liquidity_range.0;
             if liquidity < min_normal_liquidity * self.config.min_liquidity_multiplier {
@@ -137,13 +139,7 @@ impl OrderbookValidator {
 
     /// Update normal ranges based on historical measurements
     fn update_normal_ranges(&mut self, spread_pct: f64, liquidity: f64) {
-        let timestamp_ms = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_millis() as u64;
-
         let measurement = Measurement {
-            timestamp_ms,
             spread_pct,
             liquidity,
         };
@@ -0,0 +1,449 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use ordered_float::OrderedFloat;
+
+use crate::market_data::{AggressorSide, InstrumentSpec, Trade};
+use super::manager::{BookEvent, BookEventKind};
+use super::metrics::{LargeOrder, OrderSide};
+
+/// Share of a wall's last size that must have traded for it to count as filled
+const FILL_FRACTION: f64 = 0.5;
+/// Trades arriving this long after a wall left the book still count towards its fill
+/// (trades and book deltas come over different topics and can land in either order)
+const SETTLE_MS: u64 = 1_000;
+/// A new wall this soon after one was pulled, on the same side and of similar size, is the same order moved
+const MOVE_WINDOW_MS: u64 = 10_000;
+const MOVE_SIZE_TOLERANCE: f64 = 0.25;
+/// A wall pulled within this distance of mid counts as pulled on approach even if it started close
+const NEAR_BPS: f64 = 10.0;
+/// Spoofed walls on one side ending this close together are one layering episode
+const LAYER_WINDOW_MS: u64 = 1_000;
+/// Size changes kept per wall
+const MAX_SIZE_HISTORY: usize = 64;
+
+/// How a tracked wall left the book
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WallOutcome {
+    /// Consumed by trades at or through its price
+    Filled,
+    /// Pulled without trading
+    Cancelled,
+    /// Pulled and re-posted at `to`
+    Moved { to: f64 },
+}
+
+/// A large resting level followed from first sighting until it leaves the book
+#[derive(Debug, Clone)]
+pub struct Wall {
+    pub id: u64,
+    pub side: OrderSide,
+    pub price: f64,
+    pub first_seen_ms: u64,
+    pub last_seen_ms: u64,
+    /// Current size, or the size it held when it left the book
+    pub size: f64,
+    /// `size` in the quote currency
+    pub notional: f64,
+    pub peak_size: f64,
+    /// (timestamp_ms, size) on every change, oldest first
+    pub size_history: VecDeque<(u64, f64)>,
+    /// Traded volume that hit the wall's price or went through it
+    pub traded: f64,
+    /// Distance from mid in bps when first seen, and when last seen
+    pub first_distance_bps: f64,
+    pub distance_bps: f64,
+    /// Size relative to the average level when last observed
+    pub size_multiplier: f64,
+    pub ended_ms: Option<u64>,
+    pub outcome: Option<WallOutcome>,
+}
+
+impl Wall {
+    pub fn is_active(&self) -> bool {
+        self.ended_ms.is_none()
+    }
+
+    pub fn lifetime_ms(&self) -> u64 {
+        self.last_seen_ms.saturating_sub(self.first_seen_ms)
+    }
+
+    /// 0 (genuine) to 1 (spoof): how close price had come when the wall was pulled,
+    /// scaled by how little of it traded. Walls still resting or filled score 0,
+    /// as do walls moved towards the price
+    pub fn spoof_score(&self) -> f64 {
+        match self.outcome {
+            Some(WallOutcome::Cancelled) => {}
+            Some(WallOutcome::Moved { to }) if self.moved_away(to) => {}
+            _ => return 0.0,
+        }
+
+        let approach = if self.first_distance_bps > 0.0 {
+            ((self.first_distance_bps - self.distance_bps) / self.first_distance_bps).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        let closeness = (1.0 - self.distance_bps / NEAR_BPS).clamp(0.0, 1.0);
+        let unfilled = 1.0 - (self.traded / self.peak_size).clamp(0.0, 1.0);
+
+        approach.max(closeness) * unfilled
+    }
+
+    fn moved_away(&self, to: f64) -> bool {
+        match self.side {
+            OrderSide::Bid => to < self.price,
+            OrderSide::Ask => to > self.price,
+        }
+    }
+
+    fn resize(&mut self, now_ms: u64, size: f64) {
+        self.last_seen_ms = now_ms;
+        if size == self.size {
+            return;
+        }
+        self.size = size;
+        self.peak_size = self.peak_size.max(size);
+        self.size_history.push_back((now_ms, size));
+        if self.size_history.len() > MAX_SIZE_HISTORY {
+            self.size_history.pop_front();
+        }
+    }
+
+    /// Whether an aggressive trade at `price` reached this wall
+    fn hit_by(&self, side: AggressorSide, price: f64) -> bool {
+        match (self.side, side) {
+            (OrderSide::Bid, AggressorSide::Sell) => price <= self.price,
+            (OrderSide::Ask, AggressorSide::Buy) => price >= self.price,
+            _ => false,
+        }
+    }
+
+    fn classify(&mut self) {
+        if self.traded >= self.size * FILL_FRACTION {
+            self.outcome = Some(WallOutcome::Filled);
+        } else if self.outcome.is_none() {
+            self.outcome = Some(WallOutcome::Cancelled);
+        }
+    }
+}
+
+/// Gives large levels a persistent identity across ticks and records how each one ended
+/// Walls are picked up from periodic level scans, resized and ended from book events,
+/// and credited with the trades that reach them
+pub struct WhaleTracker {
+    active: HashMap<(OrderSide, OrderedFloat<f64>), Wall>,
+    /// Walls that left the book, oldest first
+    ended: VecDeque<Wall>,
+    /// History kept for ended walls, in ms
+    horizon_ms: u64,
+    next_id: u64,
+    best_bid: f64,
+    best_ask: f64,
+    /// Converts level sizes to notional; without it sizes are taken as base coin
+    instrument: Option<InstrumentSpec>,
+    /// Levels worth less than this (quote currency) are never walls
+    min_notional: f64,
+}
+
+impl WhaleTracker {
+    pub fn new(horizon_ms: u64) -> Self {
+        Self {
+            active: HashMap::new(),
+            ended: VecDeque::new(),
+            horizon_ms,
+            next_id: 1,
+            best_bid: 0.0,
+            best_ask: 0.0,
+            instrument: None,
+            min_notional: 0.0,
+        }
+    }
+
+    pub fn set_instrument(&mut self, spec: InstrumentSpec) {
+        self.instrument = Some(spec);
+    }
+
+    pub fn set_min_notional(&mut self, min_notional: f64) {
+        self.min_notional = min_notional;
+    }
+
+    /// Quote-currency value of `qty` resting at `price`
+    pub fn notional(&self, qty: f64, price: f64) -> f64 {
+        self.instrument.as_ref().map_or(qty * price, |spec| spec.notional(qty, price))
+    }
+
+    /// Scan the top levels (best first) for walls larger than `threshold` and worth at least the minimum notional
+    /// Returns the walls currently resting; new ones get an id, known ones keep theirs
+    pub fn observe(
+        &mut self,
+        now_ms: u64,
+        bid_levels: &[(f64, f64)],
+        ask_levels: &[(f64, f64)],
+        threshold: f64,
+        avg_size: f64,
+    ) -> Vec<LargeOrder> {
+        if let (Some(bid), Some(ask)) = (bid_levels.first(), ask_levels.first()) {
+            self.best_bid = bid.0;
+            self.best_ask = ask.0;
+        }
+
+        let mut whales = Vec::new();
+        for (side, levels) in [(OrderSide::Bid, bid_levels), (OrderSide::Ask, ask_levels)] {
+            let mut seen = HashSet::new();
+            for &(price, qty) in levels.iter().filter(|(_, qty)| *qty > threshold) {
+                let notional = self.notional(qty, price);
+                if notional < self.min_notional {
+                    continue;
+                }
+                let key = (side, OrderedFloat(price));
+                let distance_bps = self.distance_bps(price);
+                if !self.active.contains_key(&key) {
+                    self.start(now_ms, side, price, qty);
+                }
+                let wall = self.active.get_mut(&key).expect("wall just started");
+                wall.resize(now_ms, qty);
+                wall.notional = notional;
+                wall.distance_bps = distance_bps;
+                wall.size_multiplier = qty / avg_size;
+                seen.insert(key);
+                whales.push(LargeOrder {
+                    price,
+                    size: qty,
+                    notional,
+                    side,
+                    timestamp_ms: wall.first_seen_ms,
+                    size_multiplier: wall.size_multiplier,
+                });
+            }
+
+            // Walls missing from the scan: shrunk below the thresholds or gone without a
+            // delta (snapshot). Ones beyond the deepest scanned level merely went out of view
+            let deepest = levels.last().map(|(price, _)| *price);
+            let missing: Vec<_> = self.active.keys()
+                .filter(|key| key.0 == side && !seen.contains(*key))
+                .copied()
+                .collect();
+            for key in missing {
+                let in_view = deepest.is_some_and(|deepest| match side {
+                    OrderSide::Bid => key.1 .0 >= deepest,
+                    OrderSide::Ask => key.1 .0 <= deepest,
+                });
+                if in_view {
+                    self.end(now_ms, key);
+                } else {
+                    self.active.remove(&key);
+                }
+            }
+        }
+
+        self.prune(now_ms);
+        whales
+    }
+
+    /// Follow tracked walls through level changes, and the top of book through best-price moves
+    pub fn record_book_events(&mut self, now_ms: u64, events: &[BookEvent]) {
+        for event in events {
+            let key = (event.side, OrderedFloat(event.price));
+            match event.kind {
+                BookEventKind::BestPrice { .. } => match event.side {
+                    OrderSide::Bid => self.best_bid = event.price,
+                    OrderSide::Ask => self.best_ask = event.price,
+                },
+                BookEventKind::Add { .. } | BookEventKind::Reduce { .. } => {
+                    let notional = self.notional(event.size, event.price);
+                    if let Some(wall) = self.active.get_mut(&key) {
+                        wall.resize(now_ms, event.size);
+                        wall.notional = notional;
+                    }
+                }
+                BookEventKind::Remove { .. } => {
+                    let distance_bps = self.distance_bps(event.price);
+                    if let Some(wall) = self.active.get_mut(&key) {
+                        wall.distance_bps = distance_bps;
+                        wall.last_seen_ms = now_ms;
+                        self.end(now_ms, key);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Credit a trade to the walls it reached, and to walls at its exact price that left
+    /// the book just before it arrived
+    pub fn record_trade(&mut self, now_ms: u64, trade: &Trade) {
+        for wall in self.active.values_mut().filter(|w| w.hit_by(trade.side, trade.price)) {
+            wall.traded = (wall.traded + trade.size).min(wall.peak_size);
+        }
+
+        let settle_cutoff = now_ms.saturating_sub(SETTLE_MS);
+        for wall in self.ended.iter_mut().rev() {
+            if wall.ended_ms.is_some_and(|ended| ended < settle_cutoff) {
+                break;
+            }
+            if wall.price == trade.price && wall.hit_by(trade.side, trade.price) {
+                wall.traded = (wall.traded + trade.size).min(wall.peak_size);
+                wall.classify();
+            }
+        }
+    }
+
+    /// Walls still resting
+    pub fn active_walls(&self) -> impl Iterator<Item = &Wall> {
+        self.active.values()
+    }
+
+    /// Walls that left the book within the horizon, oldest first
+    pub fn ended_walls(&self) -> impl Iterator<Item = &Wall> {
+        self.ended.iter()
+    }
+
+    /// Mean spoof score of `side`'s walls that ended in the last `window_ms`, 0 to 1
+    pub fn spoof_rate(&self, side: OrderSide, now_ms: u64, window_ms: u64) -> f64 {
+        let (sum, count) = self.ended_in(side, now_ms, window_ms)
+            .fold((0.0, 0), |(sum, count), wall| (sum + wall.spoof_score(), count + 1));
+        if count > 0 { sum / count as f64 } else { 0.0 }
+    }
+
+    /// Layering: the largest group of `side`'s walls pulled on approach together
+    /// (spoof score of at least 0.5, ending within a second of each other) in the last `window_ms`
+    pub fn layering(&self, side: OrderSide, now_ms: u64, window_ms: u64) -> usize {
+        let pulled: Vec<u64> = self.ended_in(side, now_ms, window_ms)
+            .filter(|wall| wall.spoof_score() >= 0.5)
+            .filter_map(|wall| wall.ended_ms)
+            .collect();
+
+        let mut largest = 0;
+        let mut start = 0;
+        for end in 0..pulled.len() {
+            while pulled[end].saturating_sub(pulled[start]) > LAYER_WINDOW_MS {
+                start += 1;
+            }
+            largest = largest.max(end - start + 1);
+        }
+        if largest > 1 { largest } else { 0 }
+    }
+
+    fn ended_in(&self, side: OrderSide, now_ms: u64, window_ms: u64) -> impl Iterator<Item = &Wall> {
+        let cutoff = now_ms.saturating_sub(window_ms);
+        self.ended.iter()
+            .filter(move |wall| wall.side == side && wall.ended_ms.is_some_and(|ended| ended >= cutoff))
+    }
+
+    fn start(&mut self, now_ms: u64, side: OrderSide, price: f64, qty: f64) {
+        // The same order re-posted elsewhere marks the old wall as moved
+        let move_cutoff = now_ms.saturating_sub(MOVE_WINDOW_MS);
+        if let Some(previous) = self.ended.iter_mut().rev()
+            .take_while(|wall| wall.ended_ms.is_some_and(|ended| ended >= move_cutoff))
+            .find(|wall| {
+                wall.side == side
+                    && wall.outcome == Some(WallOutcome::Cancelled)
+                    && (qty - wall.size).abs() <= wall.size * MOVE_SIZE_TOLERANCE
+            })
+        {
+            previous.outcome = Some(WallOutcome::Moved { to: price });
+        }
+
+        let id = self.next_id;
+        self.next_id += 1;
+        let distance_bps = self.distance_bps(price);
+        let notional = self.notional(qty, price);
+        self.active.insert((side, OrderedFloat(price)), Wall {
+            id,
+            side,
+            price,
+            first_seen_ms: now_ms,
+            last_seen_ms: now_ms,
+            size: qty,
+            notional,
+            peak_size: qty,
+            size_history: VecDeque::from([(now_ms, qty)]),
+            traded: 0.0,
+            first_distance_bps: distance_bps,
+            distance_bps,
+            size_multiplier: 0.0,
+            ended_ms: None,
+            outcome: None,
+        });
+    }
+
+    fn end(&mut self, now_ms: u64, key: (OrderSide, OrderedFloat<f64>)) {
+        if let Some(mut wall) = self.active.remove(&key) {
+            wall.ended_ms = Some(now_ms);
+            wall.classify();
+            self.ended.push_back(wall);
+        }
+    }
+
+    fn prune(&mut self, now_ms: u64) {
+        let cutoff = now_ms.saturating_sub(self.horizon_ms);
+        while self.ended.front().is_some_and(|wall| wall.ended_ms.is_some_and(|ended| ended < cutoff)) {
+            self.ended.pop_front();
+        }
+    }
+
+    fn distance_bps(&self, price: f64) -> f64 {
+        if self.best_bid <= 0.0 || self.best_ask <= 0.0 {
+            return 0.0;
+        }
+        let mid = (self.best_bid + self.best_ask) / 2.0;
+        (price - mid).abs() / mid * 10_000.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn remove(side: OrderSide, price: f64, qty: f64) -> BookEvent {
+        BookEvent { side, price, level: 0, size: 0.0, kind: BookEventKind::Remove { qty } }
+    }
+
+    fn trade(side: AggressorSide, price: f64, size: f64) -> Trade {
+        Trade { timestamp_ms: 0, price, size, side, trade_id: String::new() }
+    }
+
+    #[test]
+    fn test_walls_keep_identity_and_classify_how_they_end() {
+        let mut tracker = WhaleTracker::new(60_000);
+        let bids = [(49_990.0, 1.0), (49_950.0, 20.0)];
+        let asks = [(50_010.0, 1.0), (50_050.0, 20.0), (50_100.0, 20.0)];
+
+        let first = tracker.observe(1_000, &bids, &asks, 5.0, 2.0);
+        let second = tracker.observe(2_000, &bids, &asks, 5.0, 2.0);
+        assert_eq!(first.len(), 3);
+        assert_eq!(second.iter().map(|w| w.timestamp_ms).collect::<Vec<_>>(), vec![1_000; 3]);
+        assert_eq!(tracker.active_walls().count(), 3);
+
+        // Price rallies into the 50050 ask wall, which is pulled untouched
+        let bids = [(50_040.0, 1.0), (49_950.0, 20.0)];
+        let asks = [(50_045.0, 1.0), (50_050.0, 20.0), (50_100.0, 20.0)];
+        tracker.observe(3_000, &bids, &asks, 5.0, 2.0);
+        tracker.record_book_events(3_500, &[remove(OrderSide::Ask, 50_050.0, 20.0)]);
+
+        // The 50100 wall is bought through
+        tracker.record_trade(4_000, &trade(AggressorSide::Buy, 50_100.0, 15.0));
+        tracker.record_book_events(4_000, &[remove(OrderSide::Ask, 50_100.0, 5.0)]);
+
+        let ended: Vec<_> = tracker.ended_walls().collect();
+        assert_eq!(ended[0].outcome, Some(WallOutcome::Cancelled));
+        assert!(ended[0].spoof_score() > 0.8);
+        assert_eq!(ended[0].lifetime_ms(), 2_500);
+        assert_eq!(ended[1].outcome, Some(WallOutcome::Filled));
+        assert_eq!(ended[1].spoof_score(), 0.0);
+        assert!(tracker.spoof_rate(OrderSide::Ask, 4_000, 10_000) > 0.4);
+        assert_eq!(tracker.spoof_rate(OrderSide::Bid, 4_000, 10_000), 0.0);
+
+        // Price drops to the bid wall, which is pulled and re-posted lower with about the same size
+        tracker.observe(4_500, &[(49_955.0, 1.0), (49_950.0, 20.0)], &[(49_960.0, 1.0)], 5.0, 2.0);
+        tracker.record_book_events(5_000, &[remove(OrderSide::Bid, 49_950.0, 20.0)]);
+        tracker.observe(6_000, &[(49_955.0, 1.0), (49_900.0, 19.0)], &[(49_960.0, 1.0)], 5.0, 2.0);
+        let moved = tracker.ended_walls().find(|w| w.side == OrderSide::Bid).unwrap();
+        assert_eq!(moved.outcome, Some(WallOutcome::Moved { to: 49_900.0 }));
+        assert!(moved.spoof_score() > 0.0);
+        assert_eq!(tracker.active_walls().next().unwrap().price, 49_900.0);
+
+        // Walls below the minimum notional are ignored: 19 x 49900 is under $1M
+        tracker.set_min_notional(1_000_000.0);
+        let walls = tracker.observe(7_000, &[(49_955.0, 1.0), (49_900.0, 19.0), (49_800.0, 30.0)], &[(49_960.0, 1.0)], 5.0, 2.0);
+        assert_eq!(walls.iter().map(|w| (w.price, w.notional)).collect::<Vec<_>>(), vec![(49_800.0, 1_494_000.0)]);
+    }
+}
@@ -1,6 +1,8 @@
 use std::collections::VecDeque;
 use std::time::{SystemTime, UNIX_EPOCH};
 
+use crate::market_data::Candle;
+
 /// Price point for ATR calculation
 #[derive(Debug, Clone)]
 pub struct PricePoint {
@@ -36,6 +38,7 @@ impl VolatilityCalculator {
     }
 
     /// Add a price point (using bid/ask to simulate high/low)
+    /// The resulting ATR only reflects the spread; prefer `add_candle`
     pub fn add_price(&mut self, bid: f64, ask: f64) {
         let timestamp_ms = SystemTime::now()
             .duration_since(UNIX_EPOCH)
@@ -44,13 +47,26 @@ impl VolatilityCalculator {
 
         let mid = (bid + ask) / 2.0;
 
-        let price_point = PricePoint {
+        self.add_bar(PricePoint {
             timestamp_ms,
             high: ask,
             low: bid,
             close: mid,
-        };
+        });
+    }
 
+    /// Add a closed OHLC candle
+    pub fn add_candle(&mut self, candle: &Candle) {
+        self.add_bar(PricePoint {
+            timestamp_ms: candle.end_ms,
+            high: candle.high,
+            low: candle.low,
+            close: candle.close,
+        });
+    }
+
+    /// Add a bar and recalculate the ATR
+    pub fn add_bar(&mut self, price_point: PricePoint) {
         self.price_history.push_back(price_point);
 
         // Keep only what we need
@@ -64,6 +80,11 @@ impl VolatilityCalculator {
         }
     }
 
+    /// Number of bars held (ATR is complete at `atr_period + 1`)
+    pub fn bar_count(&self) -> usize {
+        self.price_history.len()
+    }
+
     /// Calculate Average True Range
     pub fn calculate_atr(&mut self) -> f64 {
         if self.price_history.len() < 2 {
@@ -310,6 +331,30 @@ mod tests {
         assert_eq!(regime, VolatilityRegime::Low);
     }
 
+    #[test]
+    fn test_atr_from_candles() {
+        let mut calc = VolatilityCalculator::new(3);
+
+        for (i, (high, low, close)) in [(101.0, 99.0, 100.0), (103.0, 100.0, 102.0), (102.0, 98.0, 99.0)]
+            .into_iter()
+            .enumerate()
+        {
+            calc.add_candle(&Candle {
+                start_ms: i as u64 * 60_000,
+                end_ms: (i as u64 + 1) * 60_000 - 1,
+                open: close,
+                high,
+                low,
+                close,
+                volume: 1.0,
+            });
+        }
+
+        // True ranges: 3 (103-100), 4 (102-98); ATR smoothing averages 3.0 and 3.5
+        assert_eq!(calc.bar_count(), 3);
+        assert!((calc.get_atr() - 3.25).abs() < 1e-9);
+    }
+
     #[test]
     fn test_position_size_adjustment() {
         let mut calc = VolatilityCalculator::new(3);
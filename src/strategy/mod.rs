@@ -1,8 +1,9 @@
-use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
+use crate::bybit::Category;
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
 pub enum MarketBias {
     StrongLong,
@@ -56,6 +57,11 @@ pub struct TradingSignal {
     pub pressure_score: f64,       // Bid/ask pressure difference
     pub depth_consistency: f64,    // Consistency across levels
     pub momentum_score: f64,       // Combined momentum indicator
+    pub ofi: f64,                  // Multi-level order-flow imbalance (-1 to 1)
+
+    // Ticker context
+    pub funding_rate: f64,         // Current funding rate
+    pub basis_pct: f64,            // Mark premium over index
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
@@ -70,9 +76,8 @@ pub struct Strategy {
     min_score: i32,
     min_confidence: f64,
     max_spread_pct: f64,
-    min_liquidity_btc: f64,
+    min_liquidity_usd: f64,
     max_latency_ms: u64,
-    last_signal_time: Arc<RwLock<u64>>,
 
     // Phase 2: Scoring weights (should sum to ~1.0)
     imbalance_weight: f64,
@@ -80,6 +85,7 @@ pub struct Strategy {
     whale_weight: f64,
     pressure_weight: f64,
     depth_consistency_weight: f64,
+    ofi_weight: f64,
 }
 
 impl Strategy {
@@ -87,31 +93,32 @@ impl Strategy {
         min_score: i32,
         min_confidence: f64,
         max_spread_pct: f64,
-        min_liquidity_btc: f64,
+        min_liquidity_usd: f64,
         max_latency_ms: u64,
     ) -> Self {
         Self {
             min_score,
             min_confidence,
             max_spread_pct,
-            min_liquidity_btc,
+            min_liquidity_usd,
             max_latency_ms,
-            last_signal_time: Arc::new(RwLock::new(0)),
             // Default weights optimized for Bybit orderflow
             imbalance_weight: 0.30,
             volume_delta_weight: 0.25,
             whale_weight: 0.20,
             pressure_weight: 0.15,
             depth_consistency_weight: 0.10,
+            ofi_weight: 0.15,
         }
     }
 
     /// Create strategy with custom weights
+    #[allow(clippy::too_many_arguments)]
     pub fn with_weights(
         min_score: i32,
         min_confidence: f64,
         max_spread_pct: f64,
-        min_liquidity_btc: f64,
+        min_liquidity_usd: f64,
         max_latency_ms: u64,
         imbalance_weight: f64,
         volume_delta_weight: f64,
@@ -123,17 +130,23 @@ impl Strategy {
             min_score,
             min_confidence,
             max_spread_pct,
-            min_liquidity_btc,
+            min_liquidity_usd,
             max_latency_ms,
-            last_signal_time: Arc::new(RwLock::new(0)),
             imbalance_weight,
             volume_delta_weight,
             whale_weight,
             pressure_weight,
             depth_consistency_weight,
+            ofi_weight: 0.15,
         }
     }
 
+    /// Set the weight of the order-flow imbalance component
+    pub fn with_ofi_weight(mut self, weight: f64) -> Self {
+        self.ofi_weight = weight;
+        self
+    }
+
     pub fn analyze(
         &self,
         imbalance: f64,
@@ -160,10 +173,14 @@ impl Strategy {
             pressure_score: 0.0,
             depth_consistency: 0.0,
             momentum_score: 0.0,
+            ofi: 0.0,
+            funding_rate: 0.0,
+            basis_pct: 0.0,
         }
     }
 
     /// Enhanced analysis with advanced orderbook metrics (Phase 2)
+    #[allow(clippy::too_many_arguments)]
     pub fn analyze_enhanced(
         &self,
         imbalance: f64,
@@ -175,6 +192,7 @@ impl Strategy {
         whale_score: f64,
         pressure_score: f64,
         depth_consistency: f64,
+        ofi: f64,
     ) -> TradingSignal {
         let bias = self.calculate_bias(imbalance);
 
@@ -191,6 +209,7 @@ impl Strategy {
             whale_score,
             pressure_score,
             depth_consistency,
+            ofi,
         );
 
         // Enhanced confidence calculation
@@ -216,7 +235,20 @@ impl Strategy {
             pressure_score,
             depth_consistency,
             momentum_score,
+            ofi,
+            funding_rate: 0.0,
+            basis_pct: 0.0,
+        }
+    }
+
+    /// Attach ticker context to a signal (informational; scoring is unchanged)
+    pub fn apply_ticker(&self, signal: &mut TradingSignal, ticker: &crate::market_data::TickerState) {
+        if !ticker.is_initialized() {
+            return;
         }
+
+        signal.funding_rate = ticker.funding_rate;
+        signal.basis_pct = ticker.basis_pct();
     }
 
     fn calculate_bias(&self, imbalance: f64) -> MarketBias {
@@ -243,9 +275,9 @@ impl Strategy {
             score -= 30;
         }
         
-        if liquidity < self.min_liquidity_btc * 0.5 {
+        if liquidity < self.min_liquidity_usd * 0.5 {
             score -= 20;
-        } else if liquidity < self.min_liquidity_btc {
+        } else if liquidity < self.min_liquidity_usd {
             score -= 10;
         }
         
@@ -269,12 +301,13 @@ impl Strategy {
             0.3
         };
 
-        let liquidity_conf = (liquidity / self.min_liquidity_btc).min(1.0);
+        let liquidity_conf = (liquidity / self.min_liquidity_usd).min(1.0);
 
         (imbalance_conf * 0.5 + spread_conf * 0.3 + liquidity_conf * 0.2) * 100.0
     }
 
     /// Phase 2: Multi-dimensional score calculation
+    #[allow(clippy::too_many_arguments)]
     fn calculate_enhanced_score(
         &self,
         imbalance: f64,
@@ -285,6 +318,7 @@ impl Strategy {
         whale_score: f64,
         pressure_score: f64,
         depth_consistency: f64,
+        ofi: f64,
     ) -> i32 {
         let mut score = 0;
 
@@ -316,11 +350,19 @@ impl Strategy {
         // 5. Depth consistency (10% weight)
         score += (depth_consistency * 100.0 * self.depth_consistency_weight) as i32;
 
+        // 6. Order-flow imbalance: book flow agreeing with the resting imbalance
+        let ofi_component = (ofi.abs() * 100.0 * self.ofi_weight) as i32;
+        if ofi.signum() == imbalance.signum() || imbalance == 0.0 {
+            score += ofi_component;
+        } else {
+            score -= ofi_component / 2;
+        }
+
         // Standard penalties
         if spread_pct > self.max_spread_pct {
             score -= 30;
         }
-        if liquidity < self.min_liquidity_btc * 0.5 {
+        if liquidity < self.min_liquidity_usd * 0.5 {
             score -= 20;
         }
         if latency_ms > self.max_latency_ms {
@@ -367,7 +409,7 @@ impl Strategy {
             0.3
         };
 
-        let liquidity_conf = (liquidity / self.min_liquidity_btc).min(1.0);
+        let liquidity_conf = (liquidity / self.min_liquidity_usd).min(1.0);
 
         // New factors
         let consistency_conf = depth_consistency;
@@ -382,7 +424,7 @@ impl Strategy {
             * 100.0
     }
 
-    pub fn should_trade(&self, signal: &TradingSignal, min_time_between_ms: u64) -> bool {
+    pub fn should_trade(&self, signal: &TradingSignal) -> bool {
         if signal.score < self.min_score {
             return false;
         }
@@ -395,7 +437,7 @@ impl Strategy {
             return false;
         }
         
-        if signal.liquidity < self.min_liquidity_btc {
+        if signal.liquidity < self.min_liquidity_usd {
             return false;
         }
         
@@ -406,6 +448,17 @@ impl Strategy {
         true
     }
 
+    /// Check whether trading `side` would fade an active liquidation cascade
+    /// (buying into forced long selling, or selling into forced short covering)
+    pub fn fades_cascade(&self, side: TradingSide, cascade: &crate::market_data::CascadeEvent) -> bool {
+        use crate::market_data::LiquidatedSide;
+
+        matches!(
+            (side, cascade.side),
+            (TradingSide::Buy, LiquidatedSide::Long) | (TradingSide::Sell, LiquidatedSide::Short)
+        )
+    }
+
     pub fn get_signal_strength(signal: &TradingSignal) -> SignalStrength {
         if signal.score >= 70 && signal.confidence >= 70.0 {
             SignalStrength::Strong
@@ -419,13 +472,11 @@ impl Strategy {
     }
 }
 
+/// Cheap to clone; clones share the same position state
+#[derive(Clone)]
 pub struct PositionManager {
     position: Arc<RwLock<Option<Position>>>,
-    entry_price: f64,
-    side: TradingSide,
-    size: f64,
-    stop_loss: f64,
-    take_profit: f64,
+    category: Category,
 }
 
 #[derive(Debug, Clone)]
@@ -435,21 +486,57 @@ pub struct Position {
     pub size: f64,
     pub stop_loss: f64,
     pub take_profit: f64,
-    pub opened_at: u64,
+    pub opened_at_ms: u64,
+    pub category: Category,
+}
+
+impl Position {
+    /// Realised PnL and PnL percentage at `exit_price`
+    /// PnL is in the quote currency, or in the base coin for inverse contracts,
+    /// whose `size * entry_price` USD contracts pay out 1/entry - 1/exit per contract
+    pub fn pnl_at(&self, exit_price: f64) -> (f64, f64) {
+        let direction = match self.side {
+            TradingSide::Buy => 1.0,
+            TradingSide::Sell => -1.0,
+        };
+
+        match self.category {
+            Category::Inverse if exit_price > 0.0 => {
+                let contracts = self.size * self.entry_price;
+                let pnl = direction * contracts * (1.0 / self.entry_price - 1.0 / exit_price);
+                (pnl, pnl / self.size * 100.0)
+            }
+            Category::Inverse => (0.0, 0.0),
+            Category::Linear | Category::Spot => {
+                let diff = direction * (exit_price - self.entry_price);
+                (self.size * diff, diff / self.entry_price * 100.0)
+            }
+        }
+    }
+
+    /// PnL from `pnl_at` with its currency
+    pub fn format_pnl(&self, pnl: f64) -> String {
+        match self.category {
+            Category::Inverse => format!("{:.8} coin", pnl),
+            Category::Linear | Category::Spot => format!("${:.2}", pnl),
+        }
+    }
 }
 
 impl PositionManager {
     pub fn new() -> Self {
         Self {
             position: Arc::new(RwLock::new(None)),
-            entry_price: 0.0,
-            side: TradingSide::Buy,
-            size: 0.0,
-            stop_loss: 0.0,
-            take_profit: 0.0,
+            category: Category::Linear,
         }
     }
 
+    /// Product the positions are in, which sets how PnL is computed
+    pub fn with_category(mut self, category: Category) -> Self {
+        self.category = category;
+        self
+    }
+
     pub async fn has_position(&self) -> bool {
         self.position.read().await.is_some()
     }
@@ -471,16 +558,15 @@ impl PositionManager {
             size,
             stop_loss,
             take_profit,
-            opened_at: std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs(),
+            opened_at_ms: crate::utils::now_ms(),
+            category: self.category,
         };
 
         *self.position.write().await = Some(position);
     }
 
     /// Phase 3B: Open position with dynamic risk parameters based on ATR
+    #[allow(clippy::too_many_arguments)]
     pub async fn open_position_dynamic(
         &self,
         side: TradingSide,
@@ -509,10 +595,8 @@ impl PositionManager {
             size,
             stop_loss: risk_params.stop_loss_price,
             take_profit: risk_params.take_profit_price,
-            opened_at: std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs(),
+            opened_at_ms: crate::utils::now_ms(),
+            category: self.category,
         };
 
         *self.position.write().await = Some(position);
@@ -520,8 +604,17 @@ impl PositionManager {
         risk_params
     }
 
-    pub async fn close_position(&self) {
-        *self.position.write().await = None;
+    /// Backdate the open to when the entry order was sent, so exchange updates
+    /// stamped between sending and the local open are not taken as stale
+    pub async fn set_opened_at(&self, opened_at_ms: u64) {
+        if let Some(pos) = self.position.write().await.as_mut() {
+            pos.opened_at_ms = opened_at_ms;
+        }
+    }
+
+    /// Stop tracking the position; returns it unless something else closed it first
+    pub async fn close_position(&self) -> Option<Position> {
+        self.position.write().await.take()
     }
 
     pub async fn get_position_details(&self) -> Option<Position> {
@@ -555,18 +648,29 @@ impl PositionManager {
         None
     }
 
+    /// Check exits against the exchange's trigger price source
+    /// Falls back to `fallback_price` (orderbook fair value) while the ticker is
+    /// missing or older than `max_age_ms` at `now_ms`
+    /// Returns the exit reason together with the price it was evaluated at
+    pub async fn check_exit_with_ticker(
+        &self,
+        ticker: &crate::market_data::TickerState,
+        trigger_by: crate::market_data::TriggerPrice,
+        now_ms: u64,
+        max_age_ms: u64,
+        fallback_price: Option<f64>,
+    ) -> Option<(ExitReason, f64)> {
+        let price = ticker.is_fresh(now_ms, max_age_ms)
+            .then(|| ticker.trigger_price(trigger_by))
+            .flatten()
+            .or(fallback_price)?;
+        self.check_exit(price).await.map(|reason| (reason, price))
+    }
+
     pub async fn get_pnl(&self, current_price: f64) -> Option<f64> {
         let position = self.position.read().await;
         
-        if let Some(pos) = position.as_ref() {
-            let pnl = match pos.side {
-                TradingSide::Buy => (current_price - pos.entry_price) / pos.entry_price * 100.0,
-                TradingSide::Sell => (pos.entry_price - current_price) / pos.entry_price * 100.0,
-            };
-            return Some(pnl);
-        }
-        
-        None
+        position.as_ref().map(|pos| pos.pnl_at(current_price).1)
     }
 }
 
@@ -578,8 +682,63 @@ pub enum ExitReason {
     Manual,
 }
 
+impl ExitReason {
+    /// Map Bybit's `stopOrderType` of a closing fill to an exit reason
+    pub fn from_stop_order_type(stop_order_type: &str) -> Self {
+        match stop_order_type {
+            "StopLoss" | "PartialStopLoss" | "TrailingStop" => ExitReason::StopLoss,
+            "TakeProfit" | "PartialTakeProfit" => ExitReason::TakeProfit,
+            _ => ExitReason::Manual,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ExitReason::StopLoss => "Stop Loss",
+            ExitReason::TakeProfit => "Take Profit",
+            ExitReason::SignalReversal => "Signal Reversal",
+            ExitReason::Manual => "Manual",
+        }
+    }
+}
+
 impl Default for PositionManager {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_pnl_per_category() {
+        let position = |category, side| Position {
+            side,
+            entry_price: 50_000.0,
+            size: 0.02,
+            stop_loss: 0.0,
+            take_profit: 0.0,
+            opened_at_ms: 0,
+            category,
+        };
+
+        // 0.02 BTC linear: $10 per $500 move
+        assert_eq!(position(Category::Linear, TradingSide::Buy).pnl_at(50_500.0), (10.0, 1.0));
+        assert_eq!(position(Category::Linear, TradingSide::Sell).pnl_at(50_500.0), (-10.0, -1.0));
+
+        // 1000 USD contracts: 1000 * (1/50000 - 1/40000) BTC
+        let (pnl, pct) = position(Category::Inverse, TradingSide::Buy).pnl_at(40_000.0);
+        assert!((pnl + 0.005).abs() < 1e-12);
+        assert!((pct + 25.0).abs() < 1e-9);
+        let (pnl, _) = position(Category::Inverse, TradingSide::Sell).pnl_at(40_000.0);
+        assert!((pnl - 0.005).abs() < 1e-12);
+
+        let manager = PositionManager::new().with_category(Category::Inverse);
+        manager.open_position(TradingSide::Buy, 50_000.0, 0.02, 0.01, 0.02).await;
+        assert!((manager.get_pnl(40_000.0).await.unwrap() + 25.0).abs() < 1e-9);
+        assert!(manager.close_position().await.is_some());
+        assert!(manager.close_position().await.is_none());
+    }
+}
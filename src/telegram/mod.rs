@@ -1,11 +1,13 @@
 use anyhow::Result;
 use reqwest::Client;
 use serde_json::json;
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::atomic::AtomicU64;
 use std::time::{SystemTime, UNIX_EPOCH};
 use std::sync::Arc;
 use std::fs;
-use std::path::PathBuf;
+
+use crate::market_data::TickerState;
+use crate::orderbook::{Iceberg, OrderSide};
 
 const STARTUP_COOLDOWN_SECS: u64 = 600; // 10 minutes
 const LAST_STARTUP_FILE: &str = "/tmp/bybit-orderflow-bot/last_startup.txt";
@@ -27,6 +29,8 @@ pub struct TelegramNotifier {
     bot_token: String,
     chat_id: String,
     last_startup: Arc<AtomicU64>,
+    /// Decimals shown for prices, from the instrument's tick size
+    price_decimals: usize,
 }
 
 impl Clone for TelegramNotifier {
@@ -36,6 +40,7 @@ impl Clone for TelegramNotifier {
             bot_token: self.bot_token.clone(),
             chat_id: self.chat_id.clone(),
             last_startup: Arc::clone(&self.last_startup),
+            price_decimals: self.price_decimals,
         }
     }
 }
@@ -47,9 +52,16 @@ impl TelegramNotifier {
             bot_token,
             chat_id,
             last_startup: Arc::new(AtomicU64::new(0)),
+            price_decimals: 2,
         }
     }
 
+    /// Show prices with this many decimals instead of cents (sub-cent symbols)
+    pub fn with_price_decimals(mut self, decimals: usize) -> Self {
+        self.price_decimals = decimals;
+        self
+    }
+
     pub async fn send_message(&self, message: &str) -> Result<()> {
         let url = format!(
             "https://api.telegram.org/bot{}/sendMessage",
@@ -116,19 +128,31 @@ impl TelegramNotifier {
         self.send_message(&message).await
     }
 
-    pub async fn notify_summary(&self, symbol: &str, bid: f64, ask: f64, spread: f64, imbalance: f64, liquidity: f64, latency: u64, updates: u64) -> Result<()> {
-        let message = format!(
+    #[allow(clippy::too_many_arguments)]
+    pub async fn notify_summary(&self, symbol: &str, bid: f64, ask: f64, spread: f64, imbalance: f64, liquidity: f64, latency: u64, updates: u64, ticker: Option<&TickerState>) -> Result<()> {
+        let mut message = format!(
             "📊 <b>Resumen 5 min</b>\n\n\
              📊 Symbol: {}\n\
-             💰 Bid: ${:.2}\n\
-             💵 Ask: ${:.2}\n\
+             💰 Bid: ${:.*}\n\
+             💵 Ask: ${:.*}\n\
              📈 Spread: {:.4}%\n\
              ⚖️ Imbalance: {:.3}\n\
-             💧 Liquidity: {:.2} BTC\n\
+             💧 Liquidity: ${:.0}\n\
              ⏱️ Latency: {}ms\n\
              🔄 Updates: {}",
-            symbol, bid, ask, spread, imbalance, liquidity, latency, updates
+            symbol, self.price_decimals, bid, self.price_decimals, ask, spread, imbalance, liquidity, latency, updates
         );
+        if let Some(t) = ticker.filter(|t| t.is_initialized()) {
+            message.push_str(&format!(
+                "\n\n🏷️ Mark: ${:.*}\n\
+                 📍 Index: ${:.*}\n\
+                 💸 Funding: {:.4}% (in {}m)\n\
+                 📦 Open Interest: {:.2}\n\
+                 📅 24h: ${:.*} - ${:.*} ({:+.2}%)",
+                self.price_decimals, t.mark_price, self.price_decimals, t.index_price, t.funding_rate * 100.0, t.time_to_funding_ms() / 60_000,
+                t.open_interest, self.price_decimals, t.low_24h, self.price_decimals, t.high_24h, t.price_change_24h_pct * 100.0
+            ));
+        }
         self.send_message(&message).await
     }
 
@@ -140,9 +164,9 @@ impl TelegramNotifier {
              📊 Symbol: {}\n\
              📝 Type: {}\n\
              🔢 Qty: {:.4}\n\
-             💵 Price: ${:.2}\n\
+             💵 Price: ${:.*}\n\
              🆔 Order ID: {}",
-            emoji, side, symbol, order_type, qty, price, order_id
+            emoji, side, symbol, order_type, qty, self.price_decimals, price, order_id
         );
         self.send_message(&message).await
     }
@@ -159,18 +183,20 @@ impl TelegramNotifier {
         self.send_message(&message).await
     }
 
-    pub async fn notify_position_closed(&self, symbol: &str, side: &str, entry_price: f64, exit_price: f64, qty: f64, pnl: f64, pnl_pct: f64, reason: &str) -> Result<()> {
-        let (emoji, pnl_emoji) = if pnl >= 0.0 { ("💰", "🟢") } else { ("📉", "🔴") };
+    #[allow(clippy::too_many_arguments)]
+    /// `pnl` comes formatted with its currency (quote, or coin for inverse contracts)
+    pub async fn notify_position_closed(&self, symbol: &str, side: &str, entry_price: f64, exit_price: f64, qty: f64, pnl: &str, pnl_pct: f64, reason: &str) -> Result<()> {
+        let pnl_emoji = if pnl_pct >= 0.0 { "🟢" } else { "🔴" };
         let message = format!(
             "🔒 <b>Posición Cerrada</b>\n\n\
              📊 Symbol: {}\n\
              {} Side: <b>{}</b>\n\
-             🚪 Entry: ${:.2}\n\
-             🚪 Exit: ${:.2}\n\
+             🚪 Entry: ${:.*}\n\
+             🚪 Exit: ${:.*}\n\
              🔢 Qty: {:.4}\n\
-             {}{} PnL: ${:.2} ({:.2}%)\n\
+             {}{} PnL: {} ({:.2}%)\n\
              📋 Reason: {}",
-            symbol, pnl_emoji, side, entry_price, exit_price, qty, pnl_emoji, pnl_emoji, pnl, pnl_pct, reason
+            symbol, pnl_emoji, side, self.price_decimals, entry_price, self.price_decimals, exit_price, qty, pnl_emoji, pnl_emoji, pnl, pnl_pct, reason
         );
         self.send_message(&message).await
     }
@@ -181,11 +207,11 @@ impl TelegramNotifier {
             "📍 <b>Posición Abierta</b>\n\n\
              📊 Symbol: {}\n\
              {} Side: <b>{}</b>\n\
-             🎯 Entry: ${:.2}\n\
+             🎯 Entry: ${:.*}\n\
              🔢 Qty: {:.4}\n\
-             🛡️ Stop Loss: ${:.2}\n\
-             🎯 Take Profit: ${:.2}",
-            symbol, emoji, side, entry_price, qty, stop_loss, take_profit
+             🛡️ Stop Loss: ${:.*}\n\
+             🎯 Take Profit: ${:.*}",
+            symbol, emoji, side, self.price_decimals, entry_price, qty, self.price_decimals, stop_loss, self.price_decimals, take_profit
         );
         self.send_message(&message).await
     }
@@ -201,4 +227,57 @@ impl TelegramNotifier {
         );
         self.send_message(&message).await
     }
+
+    /// `notional` values a size at the iceberg's price in the quote currency
+    pub async fn notify_iceberg(&self, symbol: &str, iceberg: &Iceberg, notional: impl Fn(f64) -> f64) -> Result<()> {
+        let (emoji, side) = match iceberg.side {
+            OrderSide::Bid => ("🟢", "Bid"),
+            OrderSide::Ask => ("🔴", "Ask"),
+        };
+        let message = format!(
+            "🧊 <b>Iceberg Detected</b>\n\n\
+             📊 Symbol: {}\n\
+             {} Side: <b>{}</b>\n\
+             💵 Price: ${:.*}\n\
+             🔢 Executed: ${:.0}\n\
+             👁️ Displayed: ${:.0} (peak ${:.0})\n\
+             🔁 Refills: {}\n\
+             🕳️ Hidden: ${:.0}",
+            symbol, emoji, side, self.price_decimals, iceberg.price, notional(iceberg.executed), notional(iceberg.displayed),
+            notional(iceberg.peak_displayed), iceberg.refills, notional(iceberg.hidden_size)
+        );
+        self.send_message(&message).await
+    }
+
+    pub async fn notify_connection_down(&self, symbol: &str, reason: &str) -> Result<()> {
+        let message = format!(
+            "🔌 <b>Market Data Down</b>\n\n\
+             📊 Symbol: {}\n\
+             ❌ Reason: {}\n\
+             ⏸️ Trading paused until the feed recovers",
+            symbol, reason
+        );
+        self.send_message(&message).await
+    }
+
+    pub async fn notify_subscription_failed(&self, symbol: &str, reason: &str) -> Result<()> {
+        let message = format!(
+            "❌ <b>Subscription Rejected</b>\n\n\
+             📊 Symbol: {}\n\
+             ❌ Reason: {}\n\
+             ⚠️ The topic is no longer streamed",
+            symbol, reason
+        );
+        self.send_message(&message).await
+    }
+
+    pub async fn notify_connection_restored(&self, symbol: &str, downtime_secs: u64) -> Result<()> {
+        let message = format!(
+            "✅ <b>Market Data Restored</b>\n\n\
+             📊 Symbol: {}\n\
+             ⏱️ Downtime: {}s",
+            symbol, downtime_secs
+        );
+        self.send_message(&message).await
+    }
 }
@@ -1 +1,53 @@
-// Utilities module - to be implemented in Phase 2
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Wall-clock time in milliseconds since the Unix epoch
+pub fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+/// Wall-clock time in nanoseconds since the Unix epoch
+pub fn now_ns() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos() as u64
+}
+
+/// Time source for the rolling windows of the market-data trackers
+///
+/// Live trading reads the wall clock. A replay shares an event clock that it moves
+/// along the exchange timestamps of the recorded frames, so windows span market time.
+#[derive(Debug, Clone, Default)]
+pub struct Clock {
+    event_ms: Option<Arc<AtomicU64>>,
+}
+
+impl Clock {
+    pub fn wall() -> Self {
+        Self::default()
+    }
+
+    /// Clock that only moves through `advance`
+    pub fn event() -> Self {
+        Self { event_ms: Some(Arc::new(AtomicU64::new(0))) }
+    }
+
+    pub fn now_ms(&self) -> u64 {
+        match &self.event_ms {
+            Some(event_ms) => event_ms.load(Ordering::Acquire),
+            None => now_ms(),
+        }
+    }
+
+    /// Move an event clock forward to `ts_ms`; it never goes back and the wall clock ignores this
+    pub fn advance(&self, ts_ms: u64) {
+        if let Some(event_ms) = &self.event_ms {
+            event_ms.fetch_max(ts_ms, Ordering::AcqRel);
+        }
+    }
+}
@@ -0,0 +1,344 @@
+mod emulator;
+
+use bybit_orderflow_bot::bybit::{BybitPrivateStream, Category, ConnectionState, BybitEvent, HeartbeatPolicy, PrivateEvent, ReconnectPolicy, SubscriptionError};
+use bybit_orderflow_bot::execution::{AmendRequest, OrderRequest, OrderSide, OrderType, WsTradeSession};
+use bybit_orderflow_bot::market_data::TriggerPrice;
+use bybit_orderflow_bot::orderbook::UpdateKind;
+use bybit_orderflow_bot::{BybitAuth, BybitClient, BybitWebSocket, Orderbook};
+use emulator::{Emulator, API_KEY, API_SECRET, INITIAL_BALANCE};
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+
+fn auth() -> BybitAuth {
+    BybitAuth::new(API_KEY.to_string(), API_SECRET.to_string())
+}
+
+fn market_order(side: OrderSide, qty: f64) -> OrderRequest {
+    OrderRequest {
+        symbol: "BTCUSDT".to_string(),
+        side,
+        order_type: OrderType::Market,
+        qty,
+        price: None,
+        reduce_only: false,
+        close_on_trigger: false,
+        stop_loss: Some(49_000.0),
+        take_profit: Some(52_000.0),
+        tpsl_mode: None,
+        tp_order_type: None,
+        sl_order_type: None,
+        tp_trigger_by: None,
+        sl_trigger_by: None,
+    }
+}
+
+async fn wait_until(what: &str, mut condition: impl FnMut() -> bool) {
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(10);
+
+    while !condition() {
+        assert!(tokio::time::Instant::now() < deadline, "Timed out waiting for {}", what);
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+}
+
+#[tokio::test]
+async fn test_rest_trading_against_emulator() {
+    let emu = Emulator::start().await;
+    let client = BybitClient::new(emu.rest_url.clone(), Some(auth()));
+
+    client.set_leverage("BTCUSDT", 5).await.unwrap();
+    assert_eq!(emu.leverage(), 5);
+    assert!(client.set_leverage("BTCUSDT", 5).await.unwrap_err().to_string().contains("leverage not modified"));
+
+    let order = client.place_order(market_order(OrderSide::Buy, 0.01)).await.unwrap();
+    let orders = emu.orders();
+    assert_eq!(orders.len(), 1);
+    assert_eq!(orders[0].order_id, order.order_id);
+    assert_eq!(orders[0].status, "Filled");
+    assert_eq!(orders[0].stop_loss.as_deref(), Some("49000.00"));
+
+    emu.fail_next("order/create", 110007, "ab not enough for new order");
+    let err = client.place_order(market_order(OrderSide::Buy, 0.01)).await.unwrap_err();
+    assert!(err.to_string().contains("ab not enough for new order"));
+
+    emu.set_auto_fill(false);
+    let resting = client.place_order(market_order(OrderSide::Sell, 0.01)).await.unwrap();
+    client.amend_order(&AmendRequest {
+        symbol: "BTCUSDT".to_string(),
+        order_id: resting.order_id.clone(),
+        stop_loss: Some(51_000.0),
+        ..Default::default()
+    }).await.unwrap();
+    client.cancel_order("BTCUSDT", &resting.order_id).await.unwrap();
+    assert!(client.cancel_order("BTCUSDT", &resting.order_id).await.is_err());
+
+    let forged = BybitClient::new(emu.rest_url.clone(), Some(BybitAuth::new(API_KEY.to_string(), "wrong".to_string())));
+    assert!(forged.place_order(market_order(OrderSide::Buy, 0.01)).await.unwrap_err().to_string().contains("error sign"));
+
+    // Orders follow the loaded lot step and tick; undersized ones never leave the client
+    let spec = client.load_instrument("BTCUSDT").await.unwrap();
+    assert_eq!((spec.tick_size, spec.qty_step, spec.min_notional), (0.1, 0.001, 5.0));
+    assert!(client.load_instrument("NOPEUSDT").await.is_err());
+
+    // REST ticker backs software exits while the streams are down
+    emu.set_last_price(50_250.0);
+    let ticker = client.get_ticker("BTCUSDT").await.unwrap();
+    assert_eq!(ticker.trigger_price(TriggerPrice::MarkPrice), Some(50_250.0));
+    emu.set_auto_fill(true);
+    client.place_order(OrderRequest { stop_loss: Some(49_000.04), ..market_order(OrderSide::Buy, 0.0129) }).await.unwrap();
+    let placed = emu.orders().last().unwrap().clone();
+    assert_eq!((placed.qty, placed.stop_loss.as_deref()), (0.012, Some("49000.0")));
+    let sent = emu.count("POST /v5/order/create");
+    let limit = OrderRequest { order_type: OrderType::Limit, price: Some(50_000.0), ..market_order(OrderSide::Buy, 0.0001) };
+    assert!(client.place_order(limit).await.unwrap_err().to_string().contains("Order rejected"));
+    assert_eq!(emu.count("POST /v5/order/create"), sent);
+
+    // Inverse orders go out in whole USD contracts; spot has no leverage
+    let inverse = BybitClient::new(emu.rest_url.clone(), Some(auth())).with_category(Category::Inverse);
+    inverse.place_order(OrderRequest { symbol: "BTCUSD".to_string(), ..market_order(OrderSide::Buy, 500.0) }).await.unwrap();
+    assert_eq!(emu.orders().last().unwrap().qty, 500.0);
+    let spot = BybitClient::new(emu.rest_url.clone(), Some(auth())).with_category(Category::Spot);
+    assert!(spot.set_leverage("BTCUSDT", 2).await.is_err());
+
+    emu.set_last_price(51_000.0);
+    let candles = client.get_klines("BTCUSDT", "1", 16).await.unwrap();
+    assert_eq!(candles.len(), 15);
+    assert!(candles.iter().all(|c| c.close == 51_000.0));
+}
+
+#[tokio::test]
+async fn test_signed_get_against_emulator() {
+    let emu = Emulator::start().await;
+    let client = BybitClient::new(emu.rest_url.clone(), Some(auth()));
+
+    // The emulator checks the signature over the query string exactly as it arrives
+    let wallet = client.get_wallet().await.unwrap();
+    assert_eq!(wallet.total_margin_balance, INITIAL_BALANCE);
+    client.get_positions(Some("BTCUSDT")).await.unwrap();
+    client.get_positions(None).await.unwrap();
+    assert_eq!(emu.count("GET /v5/account/wallet-balance"), 1);
+    assert_eq!(emu.count("GET /v5/position/closed-pnl"), 2);
+
+    let forged = BybitClient::new(emu.rest_url.clone(), Some(BybitAuth::new(API_KEY.to_string(), "wrong".to_string())));
+    assert!(forged.get_wallet().await.unwrap_err().to_string().contains("error sign"));
+}
+
+#[tokio::test]
+async fn test_public_stream_recovers_from_disconnect() {
+    let emu = Emulator::start().await;
+    let ob = Arc::new(Orderbook::new("BTCUSDT".to_string()));
+
+    let mut ws = BybitWebSocket::new(emu.public_ws_url.clone())
+        .with_reconnect_policy(ReconnectPolicy {
+            initial_delay: Duration::from_millis(50),
+            max_delay: Duration::from_millis(200),
+            multiplier: 2.0,
+            jitter: 0.0,
+        })
+        .with_heartbeat(HeartbeatPolicy {
+            resync_timeout: Duration::from_millis(300),
+            ..HeartbeatPolicy::default()
+        });
+    let invalidate = ob.clone();
+    ws = ws.with_snapshot_topic("orderbook.50.BTCUSDT".to_string(), Arc::new(move || invalidate.mark_out_of_sync()));
+    let book = ob.clone();
+    ws.subscribe(
+        "orderbook.50.BTCUSDT".to_string(),
+        Arc::new(move |event: BybitEvent| {
+            let BybitEvent::Orderbook(update) = event else {
+                return Ok(());
+            };
+            let kind = if update.snapshot { UpdateKind::Snapshot } else { UpdateKind::Delta };
+            let data = update.data;
+            book.apply_update(kind, data.bids, data.asks, data.update_id, data.seq);
+            Ok(())
+        }),
+    );
+
+    let ws = Arc::new(ws);
+    let mut events = ws.connection_events();
+    let runner = ws.clone();
+    let task = tokio::spawn(async move { runner.run().await });
+
+    emu.wait_for("subscribe orderbook.50.BTCUSDT").await;
+    emu.publish("orderbook.50.BTCUSDT", "snapshot", json!({
+        "s": "BTCUSDT", "b": [["50000", "1"]], "a": [["50002", "1"]], "u": 1, "seq": 1
+    }));
+    wait_until("snapshot", || ob.best_bid_ask() == (50000.0, 50002.0)).await;
+
+    emu.disconnect_all();
+    emu.wait_for_n("subscribe orderbook.50.BTCUSDT", 2).await;
+
+    // The old book is not trusted, and the link is not live, until the restored topic's snapshot
+    assert!(!ob.is_synced());
+    assert!(!ws.connection_state().is_live());
+    emu.publish("orderbook.50.BTCUSDT", "snapshot", json!({
+        "s": "BTCUSDT", "b": [["50010", "1"]], "a": [["50011", "1"]], "u": 1, "seq": 2
+    }));
+
+    let mut saw_down = false;
+    loop {
+        match tokio::time::timeout(Duration::from_secs(10), events.recv()).await.unwrap().unwrap() {
+            ConnectionState::Down { .. } => saw_down = true,
+            ConnectionState::Live if saw_down => break,
+            _ => {}
+        }
+    }
+    wait_until("snapshot after reconnect", || ob.best_bid_ask() == (50010.0, 50011.0) && ob.is_synced()).await;
+
+    emu.reject_topic("publicTrade.BTCUSDT");
+    let err = ws.control_handle()
+        .subscribe(vec![("publicTrade.BTCUSDT".to_string(), Arc::new(|_| Ok(())))])
+        .await
+        .unwrap_err();
+    assert!(matches!(err, SubscriptionError::Rejected { .. }));
+
+    // A resync whose snapshot never arrives forces a reconnect
+    ws.resync_handle().request("orderbook.50.BTCUSDT".to_string());
+    emu.wait_for_n("subscribe orderbook.50.BTCUSDT", 3).await;
+    loop {
+        let state = tokio::time::timeout(Duration::from_secs(10), events.recv()).await.unwrap().unwrap();
+        if let ConnectionState::Down { reason, .. } = state {
+            assert!(reason.contains("resync"), "{}", reason);
+            break;
+        }
+    }
+    emu.wait_for_n("subscribe orderbook.50.BTCUSDT", 4).await;
+
+    task.abort();
+}
+
+#[tokio::test]
+async fn test_private_and_trade_streams_against_emulator() {
+    let emu = Emulator::start().await;
+
+    let private = Arc::new(BybitPrivateStream::new(emu.private_ws_url.clone(), auth()));
+    let mut events = private.events();
+    let private_task = tokio::spawn(private.clone().run());
+
+    let session = Arc::new(WsTradeSession::new(emu.trade_ws_url.clone(), auth()));
+    let trade_task = tokio::spawn(session.clone().run());
+    let client = BybitClient::new(emu.rest_url.clone(), Some(auth())).with_ws_trade(session.clone());
+
+    emu.wait_for("subscribe execution").await;
+    emu.wait_for("auth trade ok").await;
+    wait_until("trade session", || session.is_connected()).await;
+
+    emu.set_auto_fill(false);
+    let order = client.place_order(market_order(OrderSide::Buy, 0.02)).await.unwrap();
+    assert_eq!(emu.count("ws order.create"), 1);
+    assert_eq!(emu.count("POST /v5/order/create"), 0);
+    assert!(emu.fill(&order.order_id, 50_500.0));
+
+    let fill = loop {
+        match tokio::time::timeout(Duration::from_secs(10), events.recv()).await.unwrap().unwrap() {
+            PrivateEvent::Execution(fills) => break fills[0].clone(),
+            _ => continue,
+        }
+    };
+    assert_eq!(fill.order_id, order.order_id);
+    assert_eq!(fill.exec_price, "50500");
+    assert_eq!(fill.exec_qty, "0.02");
+
+    // A retCode error over the socket is final, not retried over REST
+    emu.fail_next("order/create", 10001, "Qty invalid");
+    assert!(client.place_order(market_order(OrderSide::Buy, 0.02)).await.is_err());
+    assert_eq!(emu.count("POST /v5/order/create"), 0);
+
+    // An ack lost to a disconnect is reconciled over REST by orderLinkId, without a second order
+    let placed = emu.orders().len();
+    emu.lose_next_trade_ack();
+    let order = client.place_order(market_order(OrderSide::Buy, 0.02)).await.unwrap();
+    assert_eq!(emu.count("POST /v5/order/create"), 1);
+    assert_eq!(emu.orders().len(), placed + 1);
+    assert_eq!(emu.orders().last().unwrap().order_id, order.order_id);
+
+    // While the socket is down orders go straight to REST
+    let order = client.place_order(market_order(OrderSide::Buy, 0.02)).await.unwrap();
+    assert_eq!(emu.orders().last().unwrap().order_id, order.order_id);
+
+    private_task.abort();
+    trade_task.abort();
+}
+
+#[tokio::test]
+async fn test_binary_runs_end_to_end_against_emulator() {
+    let emu = Emulator::start().await;
+
+    let dir = std::env::temp_dir().join(format!("e2e-bot-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("config")).unwrap();
+
+    // The shipped config, pointed at the emulator
+    let overrides = [
+        ("ws_url", format!("{:?}", emu.public_ws_url)),
+        ("rest_url", format!("{:?}", emu.rest_url)),
+        ("private_ws_url", format!("{:?}", emu.private_ws_url)),
+        ("trade_ws_url", format!("{:?}", emu.trade_ws_url)),
+        ("use_ws_trade", "true".to_string()),
+        ("enabled", "false".to_string()),
+    ];
+    let defaults = std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/config/default.toml")).unwrap();
+    let config: Vec<String> = defaults.lines()
+        .map(|line| match overrides.iter().find(|(key, _)| line.starts_with(&format!("{} =", key))) {
+            Some((key, value)) => format!("{} = {}", key, value),
+            None => line.to_string(),
+        })
+        .collect();
+    std::fs::write(dir.join("config/default.toml"), config.join("\n")).unwrap();
+
+    let mut bot = tokio::process::Command::new(env!("CARGO_BIN_EXE_bybit-orderflow-bot"))
+        .current_dir(&dir)
+        .env("BYBIT_API_KEY", API_KEY)
+        .env("BYBIT_API_SECRET", API_SECRET)
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .unwrap();
+
+    emu.wait_for("GET /v5/market/instruments-info").await;
+    emu.wait_for("GET /v5/market/kline").await;
+    emu.wait_for("subscribe orderbook.50.BTCUSDT").await;
+    emu.wait_for("subscribe orderbook.1.BTCUSDT").await;
+    emu.wait_for("auth private ok").await;
+    emu.wait_for("auth trade ok").await;
+    assert!(bot.try_wait().unwrap().is_none(), "bot exited early");
+
+    // A bid-heavy book keeps the strategy long until the bot has traded
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(20);
+    let mut update_id = 0;
+    while emu.orders().is_empty() {
+        assert!(tokio::time::Instant::now() < deadline, "bot never traded; log: {:#?}", emu.log());
+        assert!(bot.try_wait().unwrap().is_none(), "bot exited early");
+
+        update_id += 1;
+        let bids: Vec<_> = (0..5).map(|i| [format!("{:.1}", 50_000.0 - i as f64 * 0.1), "10".to_string()]).collect();
+        let asks: Vec<_> = (0..5).map(|i| [format!("{:.1}", 50_000.1 + i as f64 * 0.1), "1".to_string()]).collect();
+        emu.publish("orderbook.50.BTCUSDT", "snapshot", json!({
+            "s": "BTCUSDT", "b": bids, "a": asks, "u": update_id, "seq": update_id
+        }));
+        emu.publish("orderbook.1.BTCUSDT", "snapshot", json!({
+            "s": "BTCUSDT", "b": [bids[0]], "a": [asks[0]], "u": update_id, "seq": update_id
+        }));
+        tokio::time::sleep(Duration::from_millis(250)).await;
+    }
+
+    // The entry went out over the WS trade session with its native SL/TP and filled
+    let order = emu.orders()[0].clone();
+    assert_eq!((order.side.as_str(), order.status.as_str()), ("Buy", "Filled"));
+    assert!(order.stop_loss.is_some() && order.take_profit.is_some());
+    assert_eq!(emu.count("ws order.create"), 1);
+
+    // Last price through the stop: the software exit sells the position on the exchange
+    emu.publish("tickers.BTCUSDT", "snapshot", json!({
+        "symbol": "BTCUSDT", "lastPrice": "40000", "markPrice": "40000", "indexPrice": "40000"
+    }));
+    emu.wait_for_n("ws order.create", 2).await;
+    let exit = emu.orders()[1].clone();
+    assert_eq!((exit.side.as_str(), exit.qty, exit.reduce_only, exit.stop_loss), ("Sell", order.qty, true, None));
+    assert!(!order.reduce_only);
+
+    bot.kill().await.unwrap();
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,872 @@
+//! In-process Bybit v5 emulator for end-to-end tests
+//!
+//! Serves the REST endpoints `BybitClient` uses plus the public, private and trade
+//! WebSockets on localhost. Requests are signature-checked against `API_KEY`/`API_SECRET`
+//! and behaviour is scripted per test: fills, retCode errors, refused topics, disconnects.
+
+use futures::{SinkExt, StreamExt};
+use hmac::{Hmac, Mac};
+use parking_lot::Mutex;
+use serde_json::{json, Value};
+use sha2::Sha256;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::handshake::server::{Request, Response};
+use tokio_tungstenite::tungstenite::Message;
+
+pub const API_KEY: &str = "emulator-key";
+pub const API_SECRET: &str = "emulator-secret";
+pub const INITIAL_BALANCE: f64 = 10_000.0;
+const TAKER_FEE: f64 = 0.00055;
+
+/// Order as tracked by the emulator
+#[derive(Debug, Clone)]
+pub struct EmulatedOrder {
+    pub order_id: String,
+    pub order_link_id: String,
+    pub symbol: String,
+    pub side: String,
+    pub order_type: String,
+    pub qty: f64,
+    pub price: Option<f64>,
+    pub status: String,
+    pub stop_loss: Option<String>,
+    pub take_profit: Option<String>,
+    pub reduce_only: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Channel {
+    Public,
+    Private,
+    Trade,
+}
+
+struct Scenario {
+    /// Fill market orders immediately at `last_price`
+    auto_fill: bool,
+    /// Scripted retCode errors, keyed by endpoint ("order/create", "position/set-leverage", ...)
+    failures: HashMap<String, VecDeque<(i64, String)>>,
+    /// Topics whose subscription is refused
+    rejected_topics: HashSet<String>,
+    /// WS trade requests to execute and then drop the connection instead of replying
+    lost_trade_acks: usize,
+}
+
+struct Account {
+    balance: f64,
+    leverage: u32,
+    /// Signed size: positive long, negative short
+    position_size: f64,
+    entry_price: f64,
+}
+
+struct State {
+    scenario: Mutex<Scenario>,
+    orders: Mutex<Vec<EmulatedOrder>>,
+    account: Mutex<Account>,
+    last_price: Mutex<f64>,
+    public_tx: broadcast::Sender<(String, String)>,
+    private_tx: broadcast::Sender<(String, String)>,
+    disconnect_tx: broadcast::Sender<()>,
+    log: Mutex<Vec<String>>,
+    next_id: AtomicU64,
+}
+
+pub struct Emulator {
+    pub rest_url: String,
+    pub public_ws_url: String,
+    pub private_ws_url: String,
+    pub trade_ws_url: String,
+    state: Arc<State>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl Emulator {
+    pub async fn start() -> Self {
+        let state = Arc::new(State {
+            scenario: Mutex::new(Scenario {
+                auto_fill: true,
+                failures: HashMap::new(),
+                rejected_topics: HashSet::new(),
+                lost_trade_acks: 0,
+            }),
+            orders: Mutex::new(Vec::new()),
+            account: Mutex::new(Account {
+                balance: INITIAL_BALANCE,
+                leverage: 1,
+                position_size: 0.0,
+                entry_price: 0.0,
+            }),
+            last_price: Mutex::new(50_000.0),
+            public_tx: broadcast::channel(1024).0,
+            private_tx: broadcast::channel(1024).0,
+            disconnect_tx: broadcast::channel(16).0,
+            log: Mutex::new(Vec::new()),
+            next_id: AtomicU64::new(1),
+        });
+
+        let rest = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let ws = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let rest_addr = rest.local_addr().unwrap();
+        let ws_addr = ws.local_addr().unwrap();
+
+        let rest_task = {
+            let state = state.clone();
+            tokio::spawn(async move {
+                while let Ok((stream, _)) = rest.accept().await {
+                    let state = state.clone();
+                    tokio::spawn(async move {
+                        let _ = serve_http(state, stream).await;
+                    });
+                }
+            })
+        };
+
+        let ws_task = {
+            let state = state.clone();
+            tokio::spawn(async move {
+                while let Ok((stream, _)) = ws.accept().await {
+                    let state = state.clone();
+                    tokio::spawn(async move {
+                        let _ = serve_ws(state, stream).await;
+                    });
+                }
+            })
+        };
+
+        Self {
+            rest_url: format!("http://{}", rest_addr),
+            public_ws_url: format!("ws://{}/v5/public/linear", ws_addr),
+            private_ws_url: format!("ws://{}/v5/private", ws_addr),
+            trade_ws_url: format!("ws://{}/v5/trade", ws_addr),
+            state,
+            tasks: vec![rest_task, ws_task],
+        }
+    }
+
+    /// Fail the next call to `endpoint` ("order/create", "order/cancel", ...) over REST or WS
+    pub fn fail_next(&self, endpoint: &str, code: i64, msg: &str) {
+        self.state.scenario.lock().failures
+            .entry(endpoint.to_string())
+            .or_default()
+            .push_back((code, msg.to_string()));
+    }
+
+    /// Refuse subscriptions to `topic`
+    pub fn reject_topic(&self, topic: &str) {
+        self.state.scenario.lock().rejected_topics.insert(topic.to_string());
+    }
+
+    /// Execute the next WS trade request, then drop the connections without replying
+    pub fn lose_next_trade_ack(&self) {
+        self.state.scenario.lock().lost_trade_acks += 1;
+    }
+
+    /// Leave market orders open until `fill` is called
+    pub fn set_auto_fill(&self, auto_fill: bool) {
+        self.state.scenario.lock().auto_fill = auto_fill;
+    }
+
+    pub fn set_last_price(&self, price: f64) {
+        *self.state.last_price.lock() = price;
+    }
+
+    /// Fill an open order at `price`, pushing order, execution, position and wallet events
+    pub fn fill(&self, order_id: &str, price: f64) -> bool {
+        self.state.fill(order_id, price)
+    }
+
+    /// Push a public frame to every connection subscribed to `topic`
+    pub fn publish(&self, topic: &str, msg_type: &str, data: Value) {
+        let ts = now_ms();
+        let frame = json!({ "topic": topic, "type": msg_type, "ts": ts, "cts": ts, "data": data });
+        let _ = self.state.public_tx.send((topic.to_string(), frame.to_string()));
+    }
+
+    /// Drop every WebSocket connection without a close frame
+    pub fn disconnect_all(&self) {
+        let _ = self.state.disconnect_tx.send(());
+    }
+
+    pub fn orders(&self) -> Vec<EmulatedOrder> {
+        self.state.orders.lock().clone()
+    }
+
+    pub fn leverage(&self) -> u32 {
+        self.state.account.lock().leverage
+    }
+
+    /// Requests seen so far, e.g. "POST /v5/order/create", "subscribe orderbook.50.BTCUSDT"
+    pub fn log(&self) -> Vec<String> {
+        self.state.log.lock().clone()
+    }
+
+    pub fn count(&self, entry: &str) -> usize {
+        self.state.log.lock().iter().filter(|e| e.as_str() == entry).count()
+    }
+
+    /// Wait until `entry` has been logged `times` times
+    pub async fn wait_for_n(&self, entry: &str, times: usize) {
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(15);
+
+        while self.count(entry) < times {
+            if tokio::time::Instant::now() > deadline {
+                panic!("Timed out waiting for {:?} x{}; log: {:#?}", entry, times, self.log());
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    pub async fn wait_for(&self, entry: &str) {
+        self.wait_for_n(entry, 1).await;
+    }
+}
+
+impl Drop for Emulator {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+        self.disconnect_all();
+    }
+}
+
+impl State {
+    fn record(&self, entry: String) {
+        self.log.lock().push(entry);
+    }
+
+    fn take_failure(&self, endpoint: &str) -> Option<(i64, String)> {
+        self.scenario.lock().failures.get_mut(endpoint)?.pop_front()
+    }
+
+    fn next_id(&self, prefix: &str) -> String {
+        format!("{}-{}", prefix, self.next_id.fetch_add(1, Ordering::Relaxed))
+    }
+
+    fn push_private(&self, topic: &str, data: Value) {
+        let frame = json!({
+            "id": self.next_id("evt"),
+            "topic": topic,
+            "creationTime": now_ms(),
+            "data": data,
+        });
+        let _ = self.private_tx.send((topic.to_string(), frame.to_string()));
+    }
+
+    /// REST request -> Bybit envelope
+    fn handle_rest(&self, method: &str, path: &str, query: &str, headers: &HashMap<String, String>, body: &str) -> Value {
+        self.record(format!("{} {}", method, path));
+
+        if path == "/v5/market/kline" {
+            return envelope(Ok(self.klines(query)));
+        }
+        if path == "/v5/market/tickers" {
+            let price = self.last_price.lock().to_string();
+            return envelope(Ok(json!({ "category": "linear", "list": [{
+                "symbol": "BTCUSDT", "lastPrice": price, "markPrice": price, "indexPrice": price,
+            }]})));
+        }
+        if path == "/v5/market/instruments-info" {
+            return envelope(instruments_info(query));
+        }
+
+        let payload = if method == "GET" { query } else { body };
+        if let Err(e) = verify_rest_signature(headers, payload) {
+            return envelope(Err(e));
+        }
+
+        let endpoint = path.trim_start_matches("/v5/");
+        if let Some(failure) = self.take_failure(endpoint) {
+            return envelope(Err(failure));
+        }
+
+        let params: Value = if method == "GET" {
+            Value::Object(
+                url::form_urlencoded::parse(query.as_bytes())
+                    .map(|(k, v)| (k.into_owned(), Value::String(v.into_owned())))
+                    .collect(),
+            )
+        } else {
+            match serde_json::from_str(body) {
+                Ok(params) => params,
+                Err(_) => return envelope(Err((10001, "params error: body is not JSON".to_string()))),
+            }
+        };
+
+        let result = match (method, endpoint) {
+            ("POST", "order/create" | "order/cancel" | "order/amend") => self.order_op(endpoint, &params),
+            ("POST", "position/set-leverage") => self.set_leverage(&params),
+            ("GET", "order/realtime") => Ok(self.open_orders(&params)),
+            ("GET", "position/list") => Ok(self.position_list()),
+            ("GET", "position/closed-pnl") => Ok(json!({ "category": "linear", "list": [] })),
+            ("GET", "account/wallet-balance") => Ok(json!({ "list": [self.wallet()] })),
+            _ => Err((10001, format!("unsupported endpoint {} {}", method, path))),
+        };
+
+        envelope(result)
+    }
+
+    fn klines(&self, query: &str) -> Value {
+        let params: HashMap<String, String> = url::form_urlencoded::parse(query.as_bytes()).into_owned().collect();
+        let minutes: u64 = match params.get("interval").map(String::as_str) {
+            Some("D") => 1440,
+            Some(m) => m.parse().unwrap_or(1),
+            None => 1,
+        };
+        let limit: u64 = params.get("limit").and_then(|l| l.parse().ok()).unwrap_or(200);
+        let interval_ms = minutes * 60_000;
+        let current = now_ms() / interval_ms * interval_ms;
+        let price = self.last_price.lock().to_string();
+
+        // Newest first, like Bybit
+        let list: Vec<Value> = (0..limit)
+            .map(|i| {
+                let start = (current - i * interval_ms).to_string();
+                json!([start, price, price, price, price, "1", price])
+            })
+            .collect();
+
+        json!({ "category": "linear", "symbol": params.get("symbol"), "list": list })
+    }
+
+    /// create/cancel/amend shared by REST and the WS trade API
+    fn order_op(&self, endpoint: &str, params: &Value) -> Result<Value, (i64, String)> {
+        if !matches!(params["category"].as_str(), Some("linear" | "inverse" | "spot")) {
+            return Err((10001, "params error: category".to_string()));
+        }
+
+        match endpoint {
+            "order/create" => self.create_order(params),
+            "order/cancel" => {
+                let order_id = params["orderId"].as_str().unwrap_or("");
+                let order = {
+                    let mut orders = self.orders.lock();
+                    let order = orders.iter_mut()
+                        .find(|o| o.order_id == order_id && o.status == "New")
+                        .ok_or((110001, "order not exists or too late to cancel".to_string()))?;
+                    order.status = "Cancelled".to_string();
+                    order.clone()
+                };
+                self.push_private("order", json!([order_json(&order, 0.0)]));
+                Ok(json!({ "orderId": order_id, "orderLinkId": "" }))
+            }
+            "order/amend" => {
+                let order_id = params["orderId"].as_str().unwrap_or("");
+                let mut orders = self.orders.lock();
+                let order = orders.iter_mut()
+                    .find(|o| o.order_id == order_id && o.status == "New")
+                    .ok_or((110001, "order not exists or too late to replace".to_string()))?;
+                if let Some(qty) = params["qty"].as_str().and_then(|q| q.parse().ok()) {
+                    order.qty = qty;
+                }
+                if let Some(price) = params["price"].as_str().and_then(|p| p.parse().ok()) {
+                    order.price = Some(price);
+                }
+                if let Some(sl) = params["stopLoss"].as_str() {
+                    order.stop_loss = Some(sl.to_string());
+                }
+                if let Some(tp) = params["takeProfit"].as_str() {
+                    order.take_profit = Some(tp.to_string());
+                }
+                Ok(json!({ "orderId": order_id, "orderLinkId": "" }))
+            }
+            _ => Err((10001, format!("unsupported order op {}", endpoint))),
+        }
+    }
+
+    fn open_orders(&self, params: &Value) -> Value {
+        let link_id = params["orderLinkId"].as_str().unwrap_or("");
+        let list: Vec<Value> = self.orders.lock().iter()
+            .filter(|o| link_id.is_empty() || o.order_link_id == link_id)
+            .map(|o| order_json(o, o.price.unwrap_or(0.0)))
+            .collect();
+
+        json!({ "category": "linear", "list": list, "nextPageCursor": "" })
+    }
+
+    fn create_order(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let side = params["side"].as_str().filter(|s| matches!(*s, "Buy" | "Sell"))
+            .ok_or((10001, "params error: side invalid".to_string()))?;
+        let order_type = params["orderType"].as_str().filter(|t| matches!(*t, "Market" | "Limit"))
+            .ok_or((10001, "params error: orderType invalid".to_string()))?;
+        let qty = params["qty"].as_str().and_then(|q| q.parse::<f64>().ok()).filter(|q| *q > 0.0)
+            .ok_or((10001, "Qty invalid".to_string()))?;
+        let price = params["price"].as_str().and_then(|p| p.parse::<f64>().ok());
+        if order_type == "Limit" && price.is_none() {
+            return Err((10001, "params error: price required for Limit".to_string()));
+        }
+        let category = params["category"].as_str().unwrap_or("");
+        let symbol = params["symbol"].as_str().unwrap_or("");
+        if let Some(instrument) = instrument(category, symbol) {
+            let lot = &instrument["lotSizeFilter"];
+            let num = |v: &Value| v.as_str().and_then(|s| s.parse::<f64>().ok()).unwrap_or(0.0);
+            let step = num(&lot["qtyStep"]).max(num(&lot["basePrecision"]));
+            let steps = qty / step;
+            if qty < num(&lot["minOrderQty"]) || (steps - steps.round()).abs() > 1e-6 {
+                return Err((10001, "Qty invalid".to_string()));
+            }
+            let min_notional = num(&lot["minNotionalValue"]);
+            if category == "linear" && qty * price.unwrap_or(*self.last_price.lock()) < min_notional {
+                return Err((110094, format!("Order does not meet minimum order value {}USDT", min_notional)));
+            }
+        }
+
+        let order_link_id = params["orderLinkId"].as_str().unwrap_or("").to_string();
+        if !order_link_id.is_empty() && self.orders.lock().iter().any(|o| o.order_link_id == order_link_id) {
+            return Err((110072, "OrderLinkedID is duplicate".to_string()));
+        }
+
+        let order = EmulatedOrder {
+            order_id: self.next_id("ord"),
+            order_link_id,
+            symbol: params["symbol"].as_str().unwrap_or("").to_string(),
+            side: side.to_string(),
+            order_type: order_type.to_string(),
+            qty,
+            price,
+            status: "New".to_string(),
+            stop_loss: params["stopLoss"].as_str().map(str::to_string),
+            take_profit: params["takeProfit"].as_str().map(str::to_string),
+            reduce_only: params["reduceOnly"].as_bool().unwrap_or(false),
+        };
+        self.orders.lock().push(order.clone());
+        self.push_private("order", json!([order_json(&order, 0.0)]));
+
+        if order_type == "Market" && self.scenario.lock().auto_fill {
+            let price = *self.last_price.lock();
+            self.fill(&order.order_id, price);
+        }
+
+        Ok(json!({ "orderId": order.order_id, "orderLinkId": order.order_link_id }))
+    }
+
+    fn fill(&self, order_id: &str, price: f64) -> bool {
+        let order = {
+            let mut orders = self.orders.lock();
+            let Some(order) = orders.iter_mut().find(|o| o.order_id == order_id && o.status == "New") else {
+                return false;
+            };
+            order.status = "Filled".to_string();
+            order.clone()
+        };
+
+        let signed_qty = if order.side == "Buy" { order.qty } else { -order.qty };
+        let fee = order.qty * price * TAKER_FEE;
+
+        let closed_size = {
+            let mut account = self.account.lock();
+            let before = account.position_size;
+            let after = before + signed_qty;
+            let closed = if before * signed_qty < 0.0 { before.abs().min(order.qty) } else { 0.0 };
+
+            account.balance -= fee;
+            account.balance += closed * (price - account.entry_price) * before.signum();
+            account.entry_price = if after == 0.0 {
+                0.0
+            } else if before * after <= 0.0 {
+                price
+            } else if after.abs() > before.abs() {
+                (account.entry_price * before.abs() + price * order.qty) / after.abs()
+            } else {
+                account.entry_price
+            };
+            account.position_size = after;
+            closed
+        };
+
+        self.push_private("execution", json!([{
+            "category": "linear",
+            "symbol": order.symbol,
+            "orderId": order.order_id,
+            "orderLinkId": "",
+            "execId": self.next_id("exec"),
+            "side": order.side,
+            "execPrice": price.to_string(),
+            "execQty": order.qty.to_string(),
+            "execFee": format!("{:.8}", fee),
+            "execType": "Trade",
+            "execTime": now_ms().to_string(),
+            "isMaker": false,
+            "stopOrderType": "",
+            "closedSize": closed_size.to_string(),
+        }]));
+        self.push_private("order", json!([order_json(&order, price)]));
+        self.push_private("position", self.position_list()["list"].clone());
+        self.push_private("wallet", json!([self.wallet()]));
+
+        true
+    }
+
+    fn set_leverage(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let leverage = params["buyLeverage"].as_u64()
+            .or_else(|| params["buyLeverage"].as_str().and_then(|l| l.parse().ok()))
+            .ok_or((10001, "params error: buyLeverage".to_string()))? as u32;
+
+        let mut account = self.account.lock();
+        if account.leverage == leverage {
+            return Err((110043, "leverage not modified".to_string()));
+        }
+        account.leverage = leverage;
+
+        Ok(json!({}))
+    }
+
+    fn position_list(&self) -> Value {
+        let account = self.account.lock();
+        let mark = *self.last_price.lock();
+        let size = account.position_size.abs();
+        let side = if account.position_size > 0.0 { "Buy" } else if account.position_size < 0.0 { "Sell" } else { "" };
+        let upnl = (mark - account.entry_price) * account.position_size;
+
+        json!({ "category": "linear", "list": [{
+            "category": "linear",
+            "symbol": "BTCUSDT",
+            "positionIdx": 0,
+            "side": side,
+            "size": size.to_string(),
+            "avgPrice": account.entry_price.to_string(),
+            "entryPrice": account.entry_price.to_string(),
+            "markPrice": mark.to_string(),
+            "positionValue": (size * account.entry_price).to_string(),
+            "unrealisedPnl": upnl.to_string(),
+            "cumRealisedPnl": "0",
+            "leverage": account.leverage.to_string(),
+            "stopLoss": "",
+            "takeProfit": "",
+            "positionStatus": "Normal",
+            "updatedTime": now_ms().to_string(),
+        }]})
+    }
+
+    fn wallet(&self) -> Value {
+        let account = self.account.lock();
+        let mark = *self.last_price.lock();
+        let upnl = (mark - account.entry_price) * account.position_size;
+        let margin = account.position_size.abs() * account.entry_price / account.leverage.max(1) as f64;
+
+        json!({
+            "accountType": "UNIFIED",
+            "totalEquity": (account.balance + upnl).to_string(),
+            "totalWalletBalance": account.balance.to_string(),
+            "totalMarginBalance": (account.balance + upnl).to_string(),
+            "totalAvailableBalance": (account.balance + upnl - margin).to_string(),
+            "totalPerpUPL": upnl.to_string(),
+            "coin": [],
+        })
+    }
+
+    /// WebSocket request -> optional reply frame
+    fn handle_ws_request(&self, channel: Channel, text: &str, authed: &mut bool, topics: &mut HashSet<String>) -> Option<String> {
+        let req: Value = serde_json::from_str(text).ok()?;
+        let op = req["op"].as_str().unwrap_or_default().to_string();
+
+        let reply = match (channel, op.as_str()) {
+            (Channel::Public, "ping") => {
+                json!({ "success": true, "ret_msg": "pong", "conn_id": "emu", "req_id": req["req_id"], "op": "ping" })
+            }
+            (Channel::Private, "ping") => json!({ "success": true, "ret_msg": "pong", "conn_id": "emu", "op": "pong" }),
+            (Channel::Trade, "ping") => json!({ "reqId": req["reqId"], "retCode": 0, "retMsg": "OK", "op": "pong", "connId": "emu" }),
+            (Channel::Private | Channel::Trade, "auth") => {
+                let result = verify_ws_auth(&req["args"]);
+                *authed = result.is_ok();
+                let name = if channel == Channel::Private { "private" } else { "trade" };
+                self.record(format!("auth {} {}", name, if *authed { "ok" } else { "rejected" }));
+
+                match (channel, result) {
+                    (Channel::Private, Ok(())) => json!({ "success": true, "ret_msg": "", "op": "auth", "conn_id": "emu" }),
+                    (Channel::Private, Err((_, msg))) => json!({ "success": false, "ret_msg": msg, "op": "auth", "conn_id": "emu" }),
+                    (_, Ok(())) => json!({ "retCode": 0, "retMsg": "OK", "op": "auth", "connId": "emu" }),
+                    (_, Err((code, msg))) => json!({ "retCode": code, "retMsg": msg, "op": "auth", "connId": "emu" }),
+                }
+            }
+            (Channel::Public | Channel::Private, "subscribe" | "unsubscribe") => {
+                let args: Vec<String> = serde_json::from_value(req["args"].clone()).unwrap_or_default();
+                let refused = args.iter().find(|t| self.scenario.lock().rejected_topics.contains(*t)).cloned();
+
+                let error = if channel == Channel::Private && !*authed {
+                    Some("Request not authorized".to_string())
+                } else {
+                    refused.map(|topic| format!("error:handler not found,topic:{}", topic))
+                };
+
+                if error.is_none() {
+                    for topic in args {
+                        self.record(format!("{} {}", op, topic));
+                        if op == "subscribe" {
+                            topics.insert(topic);
+                        } else {
+                            topics.remove(&topic);
+                        }
+                    }
+                }
+
+                json!({
+                    "success": error.is_none(),
+                    "ret_msg": error.unwrap_or_default(),
+                    "conn_id": "emu",
+                    "req_id": req["req_id"],
+                    "op": op,
+                })
+            }
+            (Channel::Trade, op) if op.starts_with("order.") => {
+                self.record(format!("ws {}", op));
+                let endpoint = op.replace('.', "/");
+
+                let result = if !*authed {
+                    Err((10003, "Request not authorized".to_string()))
+                } else if let Some(failure) = self.take_failure(&endpoint) {
+                    Err(failure)
+                } else {
+                    self.order_op(&endpoint, &req["args"][0])
+                };
+
+                let lose_ack = {
+                    let mut scenario = self.scenario.lock();
+                    let lose = scenario.lost_trade_acks > 0;
+                    scenario.lost_trade_acks = scenario.lost_trade_acks.saturating_sub(1);
+                    lose
+                };
+                if lose_ack {
+                    let _ = self.disconnect_tx.send(());
+                    return None;
+                }
+
+                let (code, msg, data) = match result {
+                    Ok(data) => (0, "OK".to_string(), data),
+                    Err((code, msg)) => (code, msg, json!({})),
+                };
+                json!({
+                    "reqId": req["reqId"],
+                    "retCode": code,
+                    "retMsg": msg,
+                    "op": op,
+                    "data": data,
+                    "header": { "Timenow": now_ms().to_string() },
+                    "connId": "emu",
+                })
+            }
+            _ => return None,
+        };
+
+        Some(reply.to_string())
+    }
+}
+
+async fn serve_http(state: Arc<State>, stream: TcpStream) -> anyhow::Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).await?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let target = parts.next().unwrap_or_default().to_string();
+
+    let mut headers = HashMap::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).await?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+        }
+    }
+
+    let length: usize = headers.get("content-length").and_then(|l| l.parse().ok()).unwrap_or(0);
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).await?;
+
+    let (path, query) = target.split_once('?').unwrap_or((target.as_str(), ""));
+    let response = state.handle_rest(&method, path, query, &headers, &String::from_utf8_lossy(&body)).to_string();
+
+    let http = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.len(),
+        response
+    );
+    writer.write_all(http.as_bytes()).await?;
+    writer.shutdown().await?;
+
+    Ok(())
+}
+
+// The handshake callback's error type is tungstenite's, not ours
+#[allow(clippy::result_large_err)]
+async fn serve_ws(state: Arc<State>, stream: TcpStream) -> anyhow::Result<()> {
+    let mut path = String::new();
+    let ws = tokio_tungstenite::accept_hdr_async(stream, |req: &Request, resp: Response| {
+        path = req.uri().path().to_string();
+        Ok(resp)
+    })
+    .await?;
+
+    let channel = match path.as_str() {
+        "/v5/public/linear" | "/v5/public/inverse" | "/v5/public/spot" => Channel::Public,
+        "/v5/private" => Channel::Private,
+        "/v5/trade" => Channel::Trade,
+        other => anyhow::bail!("unknown WebSocket path {}", other),
+    };
+    state.record(format!("connect {}", path));
+
+    let (mut write, mut read) = ws.split();
+    let mut public_rx = state.public_tx.subscribe();
+    let mut private_rx = state.private_tx.subscribe();
+    let mut disconnect_rx = state.disconnect_tx.subscribe();
+    let mut topics = HashSet::new();
+    let mut authed = false;
+
+    loop {
+        let outbound = tokio::select! {
+            msg = read.next() => match msg {
+                Some(Ok(Message::Text(text))) => state.handle_ws_request(channel, &text, &mut authed, &mut topics),
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => None,
+            },
+            Ok((topic, frame)) = public_rx.recv(), if channel == Channel::Public => {
+                topics.contains(&topic).then_some(frame)
+            }
+            Ok((topic, frame)) = private_rx.recv(), if channel == Channel::Private && authed => {
+                topics.contains(&topic).then_some(frame)
+            }
+            _ = disconnect_rx.recv() => {
+                state.record(format!("disconnect {}", path));
+                return Ok(());
+            }
+        };
+
+        if let Some(frame) = outbound {
+            write.send(Message::Text(frame)).await?;
+        }
+    }
+
+    Ok(())
+}
+
+fn envelope(result: Result<Value, (i64, String)>) -> Value {
+    let (code, msg, result) = match result {
+        Ok(result) => (0, "OK".to_string(), result),
+        Err((code, msg)) => (code, msg, json!({})),
+    };
+
+    json!({ "retCode": code, "retMsg": msg, "result": result, "retExtInfo": {}, "time": now_ms() })
+}
+
+fn order_json(order: &EmulatedOrder, avg_price: f64) -> Value {
+    let filled = order.status == "Filled";
+
+    json!({
+        "category": "linear",
+        "symbol": order.symbol,
+        "orderId": order.order_id,
+        "orderLinkId": order.order_link_id,
+        "side": order.side,
+        "orderType": order.order_type,
+        "price": order.price.map(|p| p.to_string()).unwrap_or_else(|| "0".to_string()),
+        "qty": order.qty.to_string(),
+        "orderStatus": order.status,
+        "avgPrice": if filled { avg_price.to_string() } else { String::new() },
+        "cumExecQty": if filled { order.qty.to_string() } else { "0".to_string() },
+        "stopOrderType": "",
+        "reduceOnly": false,
+        "rejectReason": "EC_NoError",
+        "updatedTime": now_ms().to_string(),
+    })
+}
+
+fn hmac_hex(message: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(API_SECRET.as_bytes()).unwrap();
+    mac.update(message.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Bybit v5 HMAC: sign(timestamp + apiKey + recvWindow + payload)
+fn verify_rest_signature(headers: &HashMap<String, String>, payload: &str) -> Result<(), (i64, String)> {
+    let header = |name: &str| headers.get(name).map(String::as_str).unwrap_or_default();
+
+    if header("x-bapi-api-key") != API_KEY {
+        return Err((10003, "API key is invalid.".to_string()));
+    }
+
+    let timestamp: u64 = header("x-bapi-timestamp").parse().unwrap_or(0);
+    let recv_window: u64 = header("x-bapi-recv-window").parse().unwrap_or(5000);
+    if now_ms().abs_diff(timestamp) > recv_window {
+        return Err((10002, "invalid request, please check your server timestamp or recv_window param".to_string()));
+    }
+
+    let expected = hmac_hex(&format!("{}{}{}{}", header("x-bapi-timestamp"), API_KEY, header("x-bapi-recv-window"), payload));
+    if header("x-bapi-sign") != expected {
+        return Err((10004, "error sign! origin_string[...]".to_string()));
+    }
+
+    Ok(())
+}
+
+/// Instrument rules served by `/v5/market/instruments-info`, as Bybit publishes them
+fn instrument(category: &str, symbol: &str) -> Option<Value> {
+    match (category, symbol) {
+        ("linear", "BTCUSDT") => Some(json!({
+            "symbol": "BTCUSDT", "contractType": "LinearPerpetual", "status": "Trading",
+            "priceFilter": { "minPrice": "0.10", "maxPrice": "1999999.80", "tickSize": "0.10" },
+            "lotSizeFilter": { "maxOrderQty": "100.000", "minOrderQty": "0.001", "qtyStep": "0.001", "minNotionalValue": "5" }
+        })),
+        ("inverse", "BTCUSD") => Some(json!({
+            "symbol": "BTCUSD", "contractType": "InversePerpetual", "status": "Trading",
+            "priceFilter": { "minPrice": "0.50", "maxPrice": "999999.00", "tickSize": "0.50" },
+            "lotSizeFilter": { "maxOrderQty": "1000000", "minOrderQty": "1", "qtyStep": "1" }
+        })),
+        ("spot", "BTCUSDT") => Some(json!({
+            "symbol": "BTCUSDT", "status": "Trading",
+            "priceFilter": { "tickSize": "0.01" },
+            "lotSizeFilter": { "basePrecision": "0.000001", "minOrderQty": "0.000048", "maxOrderQty": "71.73956243", "minOrderAmt": "1" }
+        })),
+        _ => None,
+    }
+}
+
+fn instruments_info(query: &str) -> Result<Value, (i64, String)> {
+    let params: HashMap<String, String> = url::form_urlencoded::parse(query.as_bytes()).into_owned().collect();
+    let category = params.get("category").map(String::as_str).unwrap_or("");
+    if !matches!(category, "linear" | "inverse" | "spot") {
+        return Err((10001, "params error: category".to_string()));
+    }
+    let symbol = params.get("symbol").map(String::as_str).unwrap_or("");
+    let list: Vec<Value> = instrument(category, symbol).into_iter().collect();
+    Ok(json!({ "category": category, "list": list, "nextPageCursor": "" }))
+}
+
+/// WS auth args: [apiKey, expires, hmac("GET/realtime" + expires)]
+fn verify_ws_auth(args: &Value) -> Result<(), (i64, String)> {
+    let api_key = args[0].as_str().unwrap_or_default();
+    let expires = match &args[1] {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+    let signature = args[2].as_str().unwrap_or_default();
+
+    if api_key != API_KEY {
+        return Err((10003, "Invalid apikey".to_string()));
+    }
+    if expires.parse::<u64>().unwrap_or(0) < now_ms() {
+        return Err((10002, "Params Error: expires is expired".to_string()));
+    }
+    if signature != hmac_hex(&format!("GET/realtime{}", expires)) {
+        return Err((10004, "Invalid sign".to_string()));
+    }
+
+    Ok(())
+}
+
+fn now_ms() -> u64 {
+    chrono::Utc::now().timestamp_millis() as u64
+}
@@ -71,3 +71,124 @@ fn test_orderbook_delta_updates() {
     
     println!("✅ Delta update tests passed!");
 }
+
+#[tokio::test]
+async fn test_replay_drives_orderbook_handler() {
+    use bybit_orderflow_bot::bybit::{BybitEvent, BybitWebSocket};
+    use bybit_orderflow_bot::capture::{Pacing, Recorder, RecorderConfig, ReplayFeed};
+    use bybit_orderflow_bot::orderbook::UpdateKind;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    let dir = std::env::temp_dir().join(format!("replay-it-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+
+    // Record a snapshot followed by two deltas
+    let recorder = Recorder::start(RecorderConfig {
+        dir: dir.clone(),
+        prefix: "public".to_string(),
+        topics: Vec::new(),
+        max_file_bytes: u64::MAX,
+        max_file_age: Duration::from_secs(3600),
+        queue_capacity: 1024,
+    })
+    .unwrap();
+
+    let frames = [
+        r#"{"topic":"orderbook.50.BTCUSDT","type":"snapshot","ts":1,"data":{"s":"BTCUSDT","b":[["50000","1"]],"a":[["50002","1"]],"u":1,"seq":10}}"#,
+        r#"{"topic":"orderbook.50.BTCUSDT","type":"delta","ts":2,"data":{"s":"BTCUSDT","b":[["50001","2"]],"a":[],"u":2,"seq":11}}"#,
+        r#"{"topic":"orderbook.50.BTCUSDT","type":"delta","ts":3,"data":{"s":"BTCUSDT","b":[],"a":[["50002","0"],["50003","1"]],"u":3,"seq":12}}"#,
+    ];
+    for (i, frame) in frames.iter().enumerate() {
+        recorder.record(i as u64 * 1_000_000, Some(i as u64 + 1), Some("orderbook.50.BTCUSDT"), frame);
+    }
+    recorder.close();
+
+    // Replay through the same handler shape the binary registers
+    let ob = Arc::new(Orderbook::new("BTCUSDT".to_string()));
+    let mut ws = BybitWebSocket::new("wss://unused".to_string());
+    let ob_clone = ob.clone();
+    ws.subscribe(
+        "orderbook.50.BTCUSDT".to_string(),
+        Arc::new(move |event: BybitEvent| {
+            let BybitEvent::Orderbook(update) = event else {
+                return Ok(());
+            };
+            let kind = if update.snapshot { UpdateKind::Snapshot } else { UpdateKind::Delta };
+            let data = update.data;
+            ob_clone.apply_update(kind, data.bids, data.asks, data.update_id, data.seq);
+            Ok(())
+        }),
+    );
+
+    let feed = ReplayFeed::open(&dir, Pacing::AsFastAsPossible).unwrap();
+    let stats = ws.replay(feed).await.unwrap();
+
+    assert_eq!(stats.frames, 3);
+    assert!(ob.is_synced());
+    assert_eq!(ob.best_bid_ask(), (50001.0, 50003.0));
+}
+
+#[tokio::test]
+async fn test_stepped_replay_runs_on_exchange_time() {
+    use bybit_orderflow_bot::bybit::{BybitEvent, BybitWebSocket};
+    use bybit_orderflow_bot::capture::{Pacing, Recorder, RecorderConfig, ReplayFeed};
+    use bybit_orderflow_bot::market_data::TradeTape;
+    use bybit_orderflow_bot::utils::Clock;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    let dir = std::env::temp_dir().join(format!("replay-steps-it-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+
+    // Trades from 2020 spread over 12s of exchange time
+    let recorder = Recorder::start(RecorderConfig {
+        dir: dir.clone(),
+        prefix: "public".to_string(),
+        topics: Vec::new(),
+        max_file_bytes: u64::MAX,
+        max_file_age: Duration::from_secs(3600),
+        queue_capacity: 1024,
+    })
+    .unwrap();
+
+    let start_ms: u64 = 1_600_000_000_000;
+    for (i, (offset_ms, side)) in [(0, "Buy"), (1_000, "Buy"), (6_000, "Sell"), (12_000, "Sell")].into_iter().enumerate() {
+        let ts = start_ms + offset_ms;
+        let frame = format!(
+            r#"{{"topic":"publicTrade.BTCUSDT","type":"snapshot","ts":{ts},"data":[{{"T":{ts},"s":"BTCUSDT","S":"{side}","v":"1","p":"50000","i":"t{i}"}}]}}"#
+        );
+        recorder.record(i as u64, Some(ts), Some("publicTrade.BTCUSDT"), &frame);
+    }
+    recorder.close();
+
+    let clock = Clock::event();
+    let tape = Arc::new(TradeTape::new(100).with_clock(clock.clone()));
+    let mut ws = BybitWebSocket::new("wss://unused".to_string());
+    let tape_clone = tape.clone();
+    ws.subscribe(
+        "publicTrade.BTCUSDT".to_string(),
+        Arc::new(move |event: BybitEvent| {
+            if let BybitEvent::Trades(update) = event {
+                tape_clone.ingest(&update.data);
+            }
+            Ok(())
+        }),
+    );
+
+    let (feed, mut steps) = ReplayFeed::open(&dir, Pacing::AsFastAsPossible).unwrap()
+        .with_clock(clock.clone())
+        .stepped(5_000);
+    let replay = tokio::spawn(async move { ws.replay(feed).await.unwrap() });
+
+    // Every 5s slice is evaluated, even at full speed, with that slice's trades in the window
+    let mut seen = Vec::new();
+    while steps.next().await.is_some() {
+        seen.push((clock.now_ms() - start_ms, tape.cvd(5_000)));
+    }
+
+    assert_eq!(seen, vec![(5_000, 2.0), (10_000, -1.0), (12_000, -1.0)]);
+    assert_eq!(replay.await.unwrap().frames, 4);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}